@@ -0,0 +1,114 @@
+use crate::store;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+const AUDIT_FILE: &str = "audit.json";
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum AuditAction {
+    BalanceAdjustment,
+    PurchaseRefund { purchase_id: i64, product: String },
+    // The amount is the donated balance, negated
+    AccountClosure,
+}
+
+// Balance changes and account closures made by admins in management mode
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct AuditEntry {
+    pub time: DateTime<Utc>,
+    pub admin_id: i32,
+    pub admin: String,
+    pub user_id: i32,
+    pub username: String,
+    pub action: AuditAction,
+    // Positive credits and negative debits the balance of the user
+    pub amount: i32,
+    pub reason: String,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct AuditLog {
+    pub entries: Vec<AuditEntry>,
+}
+
+impl AuditLog {
+    pub fn load(dir: &str) -> std::io::Result<Self> {
+        store::load(dir, AUDIT_FILE)
+    }
+
+    pub fn save(&self, dir: &str) -> std::io::Result<()> {
+        store::save(dir, AUDIT_FILE, self)
+    }
+
+    // Appends to the log file, the entry is also written to the application log
+    pub fn record(dir: &str, entry: AuditEntry) -> std::io::Result<()> {
+        log::info!(
+            "event=audit admin_id={} user_id={} action={:?} amount={} reason={:?}",
+            entry.admin_id,
+            entry.user_id,
+            entry.action,
+            entry.amount,
+            entry.reason
+        );
+        let mut audit_log = Self::load(dir)?;
+        audit_log.entries.push(entry);
+        audit_log.save(dir)
+    }
+
+    // Newest first
+    pub fn for_user(&self, user_id: i32, count: usize) -> Vec<&AuditEntry> {
+        self.entries
+            .iter()
+            .rev()
+            .filter(|entry| entry.user_id == user_id)
+            .take(count)
+            .collect()
+    }
+
+    pub fn is_refunded(&self, purchase_id: i64) -> bool {
+        self.entries.iter().any(|entry| {
+            matches!(entry.action, AuditAction::PurchaseRefund { purchase_id: id, .. } if id == purchase_id)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn audit_log_works() {
+        let entry = |user_id, action, amount| AuditEntry {
+            time: Utc::now(),
+            admin_id: 1,
+            admin: "admin".to_string(),
+            user_id,
+            username: format!("user{user_id}"),
+            action,
+            amount,
+            reason: "double charge".to_string(),
+        };
+        let audit_log = AuditLog {
+            entries: vec![
+                entry(2, AuditAction::BalanceAdjustment, 500),
+                entry(3, AuditAction::BalanceAdjustment, -120),
+                entry(
+                    2,
+                    AuditAction::PurchaseRefund {
+                        purchase_id: 42,
+                        product: "Coffee".to_string(),
+                    },
+                    80,
+                ),
+            ],
+        };
+        let amounts: Vec<i32> = audit_log
+            .for_user(2, 5)
+            .iter()
+            .map(|entry| entry.amount)
+            .collect();
+        assert_eq!(amounts, [80, 500]);
+        assert_eq!(audit_log.for_user(2, 1).len(), 1);
+        assert!(audit_log.is_refunded(42));
+        assert!(!audit_log.is_refunded(43));
+    }
+}
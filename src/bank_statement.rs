@@ -0,0 +1,303 @@
+use crate::reference;
+use crate::rv_api::PendingDeposit;
+use crate::utils::parse_signed_money;
+
+// An incoming payment with a valid reference number
+#[derive(Debug, PartialEq)]
+pub struct StatementEntry {
+    // Normalized national form
+    pub reference: String,
+    pub amount: i32,
+    pub date: String,
+    pub payer: String,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum MatchKind {
+    Exact,
+    // Same reference but a different amount, left for the admin to sort out
+    AmountMismatch,
+}
+
+pub struct Match<'a> {
+    pub pending: &'a PendingDeposit,
+    pub entry: &'a StatementEntry,
+    pub kind: MatchKind,
+}
+
+fn child<'a, 'input>(
+    node: roxmltree::Node<'a, 'input>,
+    path: &[&str],
+) -> Option<roxmltree::Node<'a, 'input>> {
+    path.iter().try_fold(node, |node, name| {
+        node.children()
+            .find(|child| child.tag_name().name() == *name)
+    })
+}
+
+fn child_text(node: roxmltree::Node, path: &[&str]) -> String {
+    child(node, path)
+        .and_then(|node| node.text())
+        .unwrap_or_default()
+        .trim()
+        .to_string()
+}
+
+// ISO 20022 bank to customer statement, only credited entries are returned.
+// Namespaces are ignored as banks use different versions of the schema.
+pub fn parse_camt053(xml: &str) -> Result<Vec<StatementEntry>, String> {
+    let document = roxmltree::Document::parse(xml).map_err(|e| e.to_string())?;
+    let mut entries = Vec::new();
+    for entry in document
+        .descendants()
+        .filter(|node| node.tag_name().name() == "Ntry")
+    {
+        if child_text(entry, &["CdtDbtInd"]) != "CRDT" {
+            continue;
+        }
+        let mut date = child_text(entry, &["BookgDt", "Dt"]);
+        if date.is_empty() {
+            date = child_text(entry, &["BookgDt", "DtTm"]);
+        }
+        let transactions: Vec<_> = entry
+            .descendants()
+            .filter(|node| node.tag_name().name() == "TxDtls")
+            .collect();
+        for transaction in &transactions {
+            let Some(reference) = transaction
+                .descendants()
+                .find(|node| node.tag_name().name() == "CdtrRefInf")
+                .map(|node| child_text(node, &["Ref"]))
+                .and_then(|reference| reference::normalize(&reference))
+            else {
+                continue;
+            };
+            // Batched entries have the amount of each transaction separately
+            let amount = match transactions.len() {
+                1 => child_text(entry, &["Amt"]),
+                _ => child_text(*transaction, &["AmtDtls", "TxAmt", "Amt"]),
+            };
+            let Some(amount) = parse_signed_money(&amount) else {
+                continue;
+            };
+            entries.push(StatementEntry {
+                reference,
+                amount,
+                date: date.clone(),
+                payer: child_text(*transaction, &["RltdPties", "Dbtr", "Nm"]),
+            });
+        }
+    }
+    Ok(entries)
+}
+
+// Headers starting with one of the names, e.g. 'Määrä EUROA' for 'määrä' but
+// not 'Päivämäärä'
+fn column(headers: &csv::StringRecord, names: &[&str]) -> Option<usize> {
+    headers.iter().position(|header| {
+        let header = header.trim().to_lowercase();
+        names.iter().any(|name| header.starts_with(name))
+    })
+}
+
+// Account statement CSV with a header row, as exported by the Finnish banks.
+// Columns are recognized by their Finnish or English names.
+pub fn parse_csv(text: &str) -> Result<Vec<StatementEntry>, String> {
+    let header_line = text.lines().next().unwrap_or_default();
+    let delimiter = match header_line.contains(';') {
+        true => b';',
+        false => b',',
+    };
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .flexible(true)
+        .from_reader(text.as_bytes());
+    let headers = reader.headers().map_err(|e| e.to_string())?.clone();
+    let reference_column =
+        column(&headers, &["viite", "reference"]).ok_or("no reference number column in the CSV")?;
+    let amount_column =
+        column(&headers, &["määrä", "summa", "amount"]).ok_or("no amount column in the CSV")?;
+    let date_column = column(
+        &headers,
+        &["kirjauspäivä", "päivämäärä", "pvm", "date", "booking date"],
+    );
+    let payer_column = column(&headers, &["maksaja", "payer", "nimi", "name"]);
+
+    let mut entries = Vec::new();
+    for record in reader.records() {
+        let record = record.map_err(|e| e.to_string())?;
+        let field = |column: Option<usize>| {
+            column
+                .and_then(|column| record.get(column))
+                .unwrap_or_default()
+                .trim()
+                .to_string()
+        };
+        let Some(reference) = reference::normalize(&field(Some(reference_column))) else {
+            continue;
+        };
+        let Some(amount) = parse_signed_money(&field(Some(amount_column))).filter(|&a| a > 0)
+        else {
+            continue;
+        };
+        entries.push(StatementEntry {
+            reference,
+            amount,
+            date: field(date_column),
+            payer: field(payer_column),
+        });
+    }
+    Ok(entries)
+}
+
+pub fn parse(text: &str) -> Result<Vec<StatementEntry>, String> {
+    match text.trim_start().starts_with('<') {
+        true => parse_camt053(text),
+        false => parse_csv(text),
+    }
+}
+
+// Each statement entry matches at most one pending deposit
+pub fn match_pending<'a>(
+    pending: &'a [PendingDeposit],
+    entries: &'a [StatementEntry],
+) -> Vec<Match<'a>> {
+    let mut used = vec![false; entries.len()];
+    let mut matches = Vec::new();
+    for deposit in pending {
+        let Some(reference) = reference::normalize(&deposit.reference) else {
+            continue;
+        };
+        let candidates: Vec<usize> = (0..entries.len())
+            .filter(|&i| !used[i] && entries[i].reference == reference)
+            .collect();
+        let exact = candidates
+            .iter()
+            .copied()
+            .find(|&i| entries[i].amount == deposit.amount);
+        let (i, kind) = match (exact, candidates.first()) {
+            (Some(i), _) => (i, MatchKind::Exact),
+            (None, Some(&i)) => (i, MatchKind::AmountMismatch),
+            (None, None) => continue,
+        };
+        used[i] = true;
+        matches.push(Match {
+            pending: deposit,
+            entry: &entries[i],
+            kind,
+        });
+    }
+    matches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CAMT053: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<Document xmlns="urn:iso:std:iso:20022:tech:xsd:camt.053.001.02">
+  <BkToCstmrStmt><Stmt>
+    <Ntry>
+      <Amt Ccy="EUR">20.00</Amt>
+      <CdtDbtInd>CRDT</CdtDbtInd>
+      <BookgDt><Dt>2026-10-12</Dt></BookgDt>
+      <NtryDtls><TxDtls>
+        <RltdPties><Dbtr><Nm>MEIKALAINEN MATTI</Nm></Dbtr></RltdPties>
+        <RmtInf><Strd><CdtrRefInf><Ref>RF08 12344</Ref></CdtrRefInf></Strd></RmtInf>
+      </TxDtls></NtryDtls>
+    </Ntry>
+    <Ntry>
+      <Amt Ccy="EUR">15.00</Amt>
+      <CdtDbtInd>DBIT</CdtDbtInd>
+      <BookgDt><Dt>2026-10-12</Dt></BookgDt>
+      <NtryDtls><TxDtls>
+        <RmtInf><Strd><CdtrRefInf><Ref>12344</Ref></CdtrRefInf></Strd></RmtInf>
+      </TxDtls></NtryDtls>
+    </Ntry>
+    <Ntry>
+      <Amt Ccy="EUR">15.50</Amt>
+      <CdtDbtInd>CRDT</CdtDbtInd>
+      <BookgDt><Dt>2026-10-13</Dt></BookgDt>
+      <NtryDtls>
+        <TxDtls>
+          <AmtDtls><TxAmt><Amt Ccy="EUR">5.50</Amt></TxAmt></AmtDtls>
+          <RmtInf><Strd><CdtrRefInf><Ref>00000000000000001232</Ref></CdtrRefInf></Strd></RmtInf>
+        </TxDtls>
+        <TxDtls>
+          <AmtDtls><TxAmt><Amt Ccy="EUR">10.00</Amt></TxAmt></AmtDtls>
+          <RmtInf><Ustrd>no reference</Ustrd></RmtInf>
+        </TxDtls>
+      </NtryDtls>
+    </Ntry>
+  </Stmt></BkToCstmrStmt>
+</Document>"#;
+
+    fn pending(deposit_id: i64, amount: i32, reference: &str) -> PendingDeposit {
+        PendingDeposit {
+            deposit_id,
+            user_id: 1,
+            username: "matti".to_string(),
+            amount,
+            reference: reference.to_string(),
+            created: "2026-10-10T12:00:00Z".to_string(),
+        }
+    }
+
+    #[test]
+    fn bank_statements_work() {
+        let entries = parse(CAMT053).unwrap();
+        assert_eq!(
+            entries,
+            [
+                StatementEntry {
+                    reference: "12344".to_string(),
+                    amount: 2000,
+                    date: "2026-10-12".to_string(),
+                    payer: "MEIKALAINEN MATTI".to_string(),
+                },
+                StatementEntry {
+                    reference: "1232".to_string(),
+                    amount: 550,
+                    date: "2026-10-13".to_string(),
+                    payer: String::new(),
+                },
+            ]
+        );
+
+        let csv = "Kirjauspäivä;Määrä;Maksaja;Viite\n\
+                   12.10.2026;20,00;MEIKALAINEN MATTI;RF08 12344\n\
+                   12.10.2026;-15,00;KAUPPA OY;12344\n\
+                   13.10.2026;7,00;VIRTANEN MAIJA;1232\n";
+        let csv_entries = parse(csv).unwrap();
+        assert_eq!(csv_entries.len(), 2);
+        assert_eq!(csv_entries[0].payer, "MEIKALAINEN MATTI");
+        assert_eq!(csv_entries[1].amount, 700);
+        assert!(parse("Date,Amount\n").is_err());
+
+        let csv = "Päivämäärä;Saajan nimi;Maksajan nimi;Määrä EUROA;Viitenumero\n\
+                   12.10.2026;RV RY;MEIKALAINEN MATTI;20,00;RF08 12344\n";
+        let entry = &parse(csv).unwrap()[0];
+        assert_eq!(
+            (entry.date.as_str(), entry.payer.as_str(), entry.amount),
+            ("12.10.2026", "MEIKALAINEN MATTI", 2000)
+        );
+
+        let pending = [
+            pending(1, 2000, "RF0812344"),
+            pending(2, 550, "RF111232"),
+            pending(3, 1000, "RF0812344"),
+        ];
+        let matches = match_pending(&pending, &csv_entries);
+        let summary: Vec<_> = matches
+            .iter()
+            .map(|m| (m.pending.deposit_id, m.entry.amount, &m.kind))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                (1, 2000, &MatchKind::Exact),
+                (2, 700, &MatchKind::AmountMismatch)
+            ]
+        );
+    }
+}
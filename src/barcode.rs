@@ -0,0 +1,348 @@
+use chrono::{Datelike, NaiveDate};
+use std::fmt;
+
+// FNC1 is transmitted as the ASCII group separator between variable length fields
+pub const GS: char = '\u{1d}';
+
+#[derive(Debug, PartialEq)]
+pub struct Barcode {
+    // GTIN-14 for GTIN barcodes, other numeric codes are kept as they are
+    pub code: String,
+    // The input without a symbology identifier
+    pub scanned: String,
+    pub batch: Option<String>,
+    pub best_before: Option<NaiveDate>,
+}
+
+impl Barcode {
+    // Forms the product may be stored under in the backend, the normalized code
+    // first. Products added before the normalization keep the scanned form.
+    pub fn lookup_codes(&self) -> Vec<String> {
+        let mut codes = vec![self.code.clone()];
+        if self.code.len() == 14 && is_barcode(&self.code) {
+            for len in [13, 12, 8] {
+                let (padding, short) = self.code.split_at(14 - len);
+                if padding.chars().all(|c| c == '0') {
+                    codes.push(short.to_string());
+                }
+            }
+        }
+        if !codes.contains(&self.scanned) {
+            codes.push(self.scanned.clone());
+        }
+        codes
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum BarcodeError {
+    Invalid,
+    CheckDigit(String),
+    UnknownAi(String),
+}
+
+impl fmt::Display for BarcodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BarcodeError::Invalid => write!(f, "not a valid barcode"),
+            BarcodeError::CheckDigit(code) => write!(f, "invalid check digit in {code}"),
+            BarcodeError::UnknownAi(ai) => write!(f, "unsupported GS1 application identifier {ai}"),
+        }
+    }
+}
+
+enum AiData {
+    Fixed(usize),
+    Variable(usize),
+}
+
+// (length of the identifier, length of its data)
+fn ai_spec(input: &str) -> Option<(usize, AiData)> {
+    match input.get(..2)? {
+        "00" => Some((2, AiData::Fixed(18))),
+        "01" | "02" => Some((2, AiData::Fixed(14))),
+        "10" | "21" | "22" => Some((2, AiData::Variable(20))),
+        "11" | "12" | "13" | "15" | "16" | "17" => Some((2, AiData::Fixed(6))),
+        "20" => Some((2, AiData::Fixed(2))),
+        "30" | "37" => Some((2, AiData::Variable(8))),
+        // Trade measures, e.g. 3103 is net weight in kg with three decimals
+        "31" | "32" | "33" | "34" | "35" | "36" if input.len() >= 4 => Some((4, AiData::Fixed(6))),
+        _ => None,
+    }
+}
+
+fn check_digit(payload: &[u32]) -> u32 {
+    let sum: u32 = payload
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, &digit)| if i % 2 == 0 { 3 * digit } else { digit })
+        .sum();
+    (10 - (sum % 10)) % 10
+}
+
+// EAN-8, UPC-A, EAN-13 or GTIN-14 with a valid check digit
+pub fn is_barcode(input: &str) -> bool {
+    if !input.chars().all(|chr| chr.is_ascii_digit()) {
+        return false;
+    }
+
+    let len = input.len();
+    if len != 8 && len != 12 && len != 13 && len != 14 {
+        return false;
+    }
+
+    let code: Vec<u32> = input
+        .chars()
+        .map(|char| char.to_digit(10).unwrap())
+        .collect();
+    check_digit(&code[..len - 1]) == code[len - 1]
+}
+
+pub fn to_gtin14(gtin: &str) -> String {
+    format!("{gtin:0>14}")
+}
+
+// Scanners prefix the data with e.g. "]C1" when AIM symbology identifiers are enabled
+fn split_symbology_identifier(input: &str) -> (Option<&str>, &str) {
+    match input.starts_with(']') {
+        true => (input.get(..3), input.get(3..).unwrap_or("")),
+        false => (None, input),
+    }
+}
+
+// GS1-128, GS1 DataBar, GS1 DataMatrix and GS1 QR Code
+fn is_gs1_symbology(identifier: &str) -> bool {
+    matches!(identifier, "]C1" | "]e0" | "]d2" | "]Q3")
+}
+
+// YYMMDD, day 00 means the last day of the month
+fn parse_gs1_date(value: &str) -> Result<NaiveDate, BarcodeError> {
+    let number = |range: std::ops::Range<usize>| -> Result<u32, BarcodeError> {
+        value
+            .get(range)
+            .and_then(|s| s.parse().ok())
+            .ok_or(BarcodeError::Invalid)
+    };
+    let year = 2000 + number(0..2)? as i32;
+    let month = number(2..4)?;
+    let date = match number(4..6)? {
+        0 => NaiveDate::from_ymd_opt(year, month, 1).and_then(|first| {
+            first
+                .checked_add_months(chrono::Months::new(1))?
+                .pred_opt()
+                .filter(|last| last.month() == month)
+        }),
+        day => NaiveDate::from_ymd_opt(year, month, day),
+    };
+    date.ok_or(BarcodeError::Invalid)
+}
+
+fn parse_gs1_fields(input: &str) -> Result<Vec<(&str, &str)>, BarcodeError> {
+    let mut fields = Vec::new();
+
+    // Human readable form, e.g. (01)09501101530003(10)AB-123
+    if input.starts_with('(') {
+        for segment in input.split('(').skip(1) {
+            let (ai, value) = segment.split_once(')').ok_or(BarcodeError::Invalid)?;
+            let Some((ai_len, data)) = ai_spec(ai) else {
+                return Err(BarcodeError::UnknownAi(ai.to_string()));
+            };
+            let valid_length = match data {
+                AiData::Fixed(len) => value.len() == len,
+                AiData::Variable(max) => !value.is_empty() && value.len() <= max,
+            };
+            if ai.len() != ai_len || !valid_length {
+                return Err(BarcodeError::Invalid);
+            }
+            fields.push((ai, value));
+        }
+        return Ok(fields);
+    }
+
+    let mut rest = input;
+    loop {
+        rest = rest.trim_start_matches(GS);
+        if rest.is_empty() {
+            return Ok(fields);
+        }
+        let Some((ai_len, data)) = ai_spec(rest) else {
+            return Err(BarcodeError::UnknownAi(rest.chars().take(2).collect()));
+        };
+        let (ai, data_and_rest) = rest.split_at(ai_len);
+        let value_len = match data {
+            AiData::Fixed(len) if data_and_rest.len() >= len => len,
+            AiData::Fixed(_) => return Err(BarcodeError::Invalid),
+            AiData::Variable(max) => {
+                let len = data_and_rest.find(GS).unwrap_or(data_and_rest.len());
+                if len == 0 || len > max {
+                    return Err(BarcodeError::Invalid);
+                }
+                len
+            }
+        };
+        let (value, next) = data_and_rest.split_at(value_len);
+        fields.push((ai, value));
+        rest = next;
+    }
+}
+
+fn parse_gs1(input: &str) -> Result<Barcode, BarcodeError> {
+    let mut barcode = Barcode {
+        code: String::new(),
+        scanned: input.to_string(),
+        batch: None,
+        best_before: None,
+    };
+    let mut expiry = None;
+    for (ai, value) in parse_gs1_fields(input)? {
+        match ai {
+            "01" => {
+                if !value.chars().all(|c| c.is_ascii_digit()) {
+                    return Err(BarcodeError::Invalid);
+                }
+                if !is_barcode(value) {
+                    return Err(BarcodeError::CheckDigit(value.to_string()));
+                }
+                barcode.code = value.to_string();
+            }
+            "10" => barcode.batch = Some(value.to_string()),
+            "15" => barcode.best_before = Some(parse_gs1_date(value)?),
+            "17" => expiry = Some(parse_gs1_date(value)?),
+            _ => (),
+        }
+    }
+    if barcode.code.is_empty() {
+        return Err(BarcodeError::Invalid);
+    }
+    barcode.best_before = barcode.best_before.or(expiry);
+    Ok(barcode)
+}
+
+// Parses plain GTINs, other numeric codes and GS1 element strings
+pub fn parse(input: &str) -> Result<Barcode, BarcodeError> {
+    let input = input.trim();
+    if !input.is_ascii() {
+        return Err(BarcodeError::Invalid);
+    }
+    let (identifier, input) = split_symbology_identifier(input);
+    let numeric = !input.is_empty() && input.chars().all(|c| c.is_ascii_digit());
+
+    if identifier.is_some_and(is_gs1_symbology) || input.contains(GS) || input.starts_with('(') {
+        return parse_gs1(input);
+    }
+    if !numeric {
+        return Err(BarcodeError::Invalid);
+    }
+    // GS1-128 from scanners without symbology identifiers, or just a long code
+    if input.len() > 14 && input.starts_with("01") {
+        if let Ok(barcode) = parse_gs1(input) {
+            return Ok(barcode);
+        }
+    }
+    let code = match input.len() {
+        8 | 12 | 13 | 14 if is_barcode(input) => to_gtin14(input),
+        8 | 12 | 13 | 14 => return Err(BarcodeError::CheckDigit(input.to_string())),
+        _ => input.to_string(),
+    };
+    Ok(Barcode {
+        code,
+        scanned: input.to_string(),
+        batch: None,
+        best_before: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn is_barcode_works() {
+        assert!(is_barcode("38588901797050"));
+        assert!(is_barcode("3858890179705"));
+        assert!(is_barcode("4901234567894"));
+        assert!(is_barcode("700941359952"));
+        assert!(is_barcode("80111351"));
+        assert!(is_barcode("01234565"));
+        assert!(!is_barcode("user"));
+    }
+
+    #[test]
+    fn parse_normalizes_to_gtin14() {
+        let ean13 = parse("0700941359952").unwrap();
+        assert_eq!(ean13.code, "00700941359952");
+        assert_eq!(parse("700941359952").unwrap().code, ean13.code);
+        assert_eq!(parse("]E00700941359952").unwrap(), ean13);
+        assert_eq!(parse("80111351").unwrap().code, "00000080111351");
+        assert_eq!(parse("1234").unwrap().code, "1234");
+        assert_eq!(
+            parse("3858890179706"),
+            Err(BarcodeError::CheckDigit("3858890179706".to_string()))
+        );
+        assert_eq!(parse("abc"), Err(BarcodeError::Invalid));
+        assert_eq!(parse(""), Err(BarcodeError::Invalid));
+    }
+
+    #[test]
+    fn lookup_codes_include_the_stored_forms() {
+        assert_eq!(
+            parse("0700941359952").unwrap().lookup_codes(),
+            ["00700941359952", "0700941359952", "700941359952"]
+        );
+        assert_eq!(
+            parse("80111351").unwrap().lookup_codes(),
+            [
+                "00000080111351",
+                "0000080111351",
+                "000080111351",
+                "80111351"
+            ]
+        );
+        assert_eq!(parse("1234").unwrap().lookup_codes(), ["1234"]);
+        assert_eq!(
+            parse("0109501101530003").unwrap().lookup_codes(),
+            ["09501101530003", "9501101530003", "0109501101530003"]
+        );
+    }
+
+    #[test]
+    fn parse_gs1_works() {
+        let expected = |scanned: &str| Barcode {
+            code: "09501101530003".to_string(),
+            scanned: scanned.to_string(),
+            batch: Some("AB-123".to_string()),
+            best_before: NaiveDate::from_ymd_opt(2025, 1, 31),
+        };
+        assert_eq!(
+            parse("]C101095011015300031525013110AB-123").unwrap(),
+            expected("01095011015300031525013110AB-123")
+        );
+        assert_eq!(
+            parse("0109501101530003\u{1d}10AB-123\u{1d}15250131").unwrap(),
+            expected("0109501101530003\u{1d}10AB-123\u{1d}15250131")
+        );
+        assert_eq!(
+            parse("(01)09501101530003(10)AB-123(17)250100").unwrap(),
+            expected("(01)09501101530003(10)AB-123(17)250100")
+        );
+        assert_eq!(
+            parse("01095011015300033103000250").unwrap().best_before,
+            None
+        );
+        assert_eq!(
+            parse("]C10109501101530004"),
+            Err(BarcodeError::CheckDigit("09501101530004".to_string()))
+        );
+        // Without a symbology identifier a long numeric code need not be GS1
+        assert_eq!(parse("0109501101530004").unwrap().code, "0109501101530004");
+        assert_eq!(
+            parse("(01)09501101530003(99)X"),
+            Err(BarcodeError::UnknownAi("99".to_string()))
+        );
+        assert_eq!(
+            parse("]C10109501101530003151302"),
+            Err(BarcodeError::Invalid)
+        );
+        assert_eq!(parse("(10)AB-123"), Err(BarcodeError::Invalid));
+    }
+}
@@ -0,0 +1,163 @@
+use crate::barcode;
+use crate::store;
+use chrono::{NaiveDate, Utc};
+use chrono_tz::Europe::Helsinki;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+const BATCHES_FILE: &str = "batches.json";
+
+pub fn today() -> NaiveDate {
+    Utc::now().with_timezone(&Helsinki).date_naive()
+}
+
+// Batches are kept by the GTIN-14, so that the box bought in, the product stored
+// in the backend and the scanned package find the same batches
+fn key(barcode: &str) -> String {
+    barcode::parse(barcode).map_or_else(|_| barcode.to_string(), |barcode| barcode.code)
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Batch {
+    pub batch: Option<String>,
+    pub best_before: NaiveDate,
+    pub count: i32,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct ProductBatches {
+    pub name: String,
+    // Oldest best-before date first
+    pub batches: Vec<Batch>,
+}
+
+// Best-before dates of the stock per product barcode
+#[derive(Serialize, Deserialize, Default)]
+pub struct Batches {
+    pub products: BTreeMap<String, ProductBatches>,
+}
+
+impl Batches {
+    pub fn load(dir: &str) -> std::io::Result<Self> {
+        store::load(dir, BATCHES_FILE)
+    }
+
+    pub fn save(&self, dir: &str) -> std::io::Result<()> {
+        store::save(dir, BATCHES_FILE, self)
+    }
+
+    pub fn add(&mut self, barcode: &str, name: &str, batch: Batch) {
+        let product = self.products.entry(key(barcode)).or_default();
+        product.name = name.to_string();
+        match product
+            .batches
+            .iter_mut()
+            .find(|b| b.batch == batch.batch && b.best_before == batch.best_before)
+        {
+            Some(existing) => existing.count += batch.count,
+            None => {
+                product.batches.push(batch);
+                product.batches.sort_by_key(|b| b.best_before);
+            }
+        }
+    }
+
+    // Sold items are taken from the oldest batches first
+    pub fn consume(&mut self, barcode: &str, count: i32) {
+        let barcode = key(barcode);
+        let Some(product) = self.products.get_mut(&barcode) else {
+            return;
+        };
+        let mut remaining = count;
+        for batch in product.batches.iter_mut() {
+            let taken = remaining.min(batch.count);
+            batch.count -= taken;
+            remaining -= taken;
+            if remaining == 0 {
+                break;
+            }
+        }
+        product.batches.retain(|b| b.count > 0);
+        if product.batches.is_empty() {
+            self.products.remove(&barcode);
+        }
+    }
+
+    pub fn oldest(&self, barcode: &str) -> Option<&Batch> {
+        self.products.get(&key(barcode))?.batches.first()
+    }
+
+    // (barcode, product name, batch) of batches with best-before on or before the given date
+    pub fn expiring(&self, until: NaiveDate) -> Vec<(&str, &str, &Batch)> {
+        let mut expiring: Vec<(&str, &str, &Batch)> = self
+            .products
+            .iter()
+            .flat_map(|(barcode, product)| {
+                product
+                    .batches
+                    .iter()
+                    .filter(move |b| b.best_before <= until)
+                    .map(move |b| (barcode.as_str(), product.name.as_str(), b))
+            })
+            .collect();
+        expiring.sort_by_key(|(_, _, b)| b.best_before);
+        expiring
+    }
+
+    pub fn remove_expired(&mut self, today: NaiveDate) -> i32 {
+        let mut removed = 0;
+        for product in self.products.values_mut() {
+            product.batches.retain(|b| {
+                if b.best_before < today {
+                    removed += b.count;
+                    return false;
+                }
+                true
+            });
+        }
+        self.products.retain(|_, p| !p.batches.is_empty());
+        removed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn batch(day: u32, count: i32) -> Batch {
+        Batch {
+            batch: None,
+            best_before: NaiveDate::from_ymd_opt(2025, 1, day).unwrap(),
+            count,
+        }
+    }
+
+    #[test]
+    fn batches_are_consumed_oldest_first() {
+        let mut batches = Batches::default();
+        batches.add("00000080111351", "Sandwich", batch(20, 3));
+        batches.add("00000080111351", "Sandwich", batch(10, 2));
+        batches.add("80111351", "Sandwich", batch(20, 1));
+        assert_eq!(batches.oldest("00000080111351"), Some(&batch(10, 2)));
+        assert_eq!(batches.oldest("80111351"), Some(&batch(10, 2)));
+
+        batches.consume("00000080111351", 3);
+        assert_eq!(batches.oldest("00000080111351"), Some(&batch(20, 3)));
+        assert_eq!(
+            batches
+                .expiring(NaiveDate::from_ymd_opt(2025, 1, 20).unwrap())
+                .len(),
+            1
+        );
+        assert!(batches
+            .expiring(NaiveDate::from_ymd_opt(2025, 1, 19).unwrap())
+            .is_empty());
+
+        assert_eq!(
+            batches.remove_expired(NaiveDate::from_ymd_opt(2025, 1, 21).unwrap()),
+            3
+        );
+        batches.consume("00000080111351", 1);
+        assert!(batches.products.is_empty());
+    }
+}
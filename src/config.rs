@@ -0,0 +1,528 @@
+use serde::{Deserialize, Serialize};
+use std::{fs, path::Path, str::FromStr, time::Duration};
+
+// Precedence: defaults < config file < environment variables < command line arguments
+const DEFAULT_CONFIG_PATH: &str = "/etc/rvterminal/config.toml";
+
+pub const USAGE: &str = "\
+Usage: rvterminal [OPTIONS]
+
+Options:
+  --config <PATH>           Config file (default /etc/rvterminal/config.toml, env RV_CONFIG)
+  --print-config            Print the effective configuration and exit
+  --api-url <URL>           Backend API url (env RV_API_URL)
+  --development             Enable development mode (env DEVELOPMENT)
+  --no-font-change          Do not change the console font (env NO_FONT_CHANGE)
+  --receipt-printer <PATH>  ESC/POS receipt printer device (env RV_RECEIPT_PRINTER)
+  --log-dir <PATH>          Log file directory (env RV_LOG_DIR)
+  --log-level <LEVEL>       off, error, warn, info, debug or trace (env RV_LOG_LEVEL)
+  --help                    Print this help";
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub development: bool,
+    pub api: ApiConfig,
+    pub input: InputConfig,
+    pub font: FontConfig,
+    pub deposit: DepositConfig,
+    pub balance: BalanceConfig,
+    pub leaderboard: LeaderboardConfig,
+    pub idle: IdleConfig,
+    pub store: StoreConfig,
+    pub batches: BatchesConfig,
+    pub receipt: ReceiptConfig,
+    pub log: LogConfig,
+    pub supervisor: SupervisorConfig,
+    pub pin: PinConfig,
+    pub login: LoginConfig,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct ApiConfig {
+    pub url: String,
+    pub terminal_secret: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct InputConfig {
+    pub timeout_short_secs: u64,
+    pub timeout_long_secs: u64,
+    // Software input socket, only used in development mode
+    pub socket_path: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct FontConfig {
+    pub change: bool,
+    pub small: String,
+    pub big: String,
+    pub console: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct DepositConfig {
+    pub max_cents: u32,
+    // Account for bank transfer deposits, shown with the reference number
+    pub iban: String,
+    pub recipient: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct BalanceConfig {
+    // Default for users who have not set their own low balance warning
+    pub low_warning_cents: i32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct LeaderboardConfig {
+    pub rows: usize,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct IdleConfig {
+    // Seconds without input on the login screen before the attract screen starts
+    pub timeout_secs: u64,
+    pub panel_secs: u64,
+    pub refresh_secs: u64,
+    // Plain text file, paragraphs separated by empty lines are shown as separate panels
+    pub announcements: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct StoreConfig {
+    // Directory of the local data files
+    pub dir: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct BatchesConfig {
+    // Batches with best-before within this many days are listed as expiring soon
+    pub expiring_days: u32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct ReceiptConfig {
+    // ESC/POS receipt printer device, e.g. /dev/usb/lp0
+    pub printer: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogConfig {
+    pub dir: String,
+    pub level: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct SupervisorConfig {
+    // Exit the process when main loop crashes this many times within the window
+    pub max_crashes: usize,
+    pub crash_window_secs: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct PinConfig {
+    pub max_attempts: u32,
+    pub lockout_secs: u64,
+    // Purchases above this need the PIN after an RFID login
+    pub purchase_threshold_cents: i32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct LoginConfig {
+    // Failed passwords per username before the delays start
+    pub free_attempts: u32,
+    // The delay doubles after each failure up to this
+    pub max_delay_secs: u64,
+    pub lockout_after: u32,
+    pub lockout_secs: u64,
+    // Failures of any usernames within lockout_secs that lock the whole terminal
+    pub terminal_lockout_after: u32,
+    // Tell the account owner about a lockout by email
+    pub notify_owner: bool,
+}
+
+impl PinConfig {
+    pub fn lockout(&self) -> chrono::Duration {
+        chrono::Duration::seconds(self.lockout_secs as i64)
+    }
+}
+
+impl Default for ApiConfig {
+    fn default() -> Self {
+        Self {
+            url: "http://localhost:4040/api".to_string(),
+            terminal_secret: "unsecure".to_string(),
+        }
+    }
+}
+
+impl Default for InputConfig {
+    fn default() -> Self {
+        Self {
+            timeout_short_secs: 60,
+            timeout_long_secs: 5 * 60,
+            socket_path: "/tmp/rvterminal.sock".to_string(),
+        }
+    }
+}
+
+impl InputConfig {
+    pub fn timeout_short(&self) -> Duration {
+        Duration::from_secs(self.timeout_short_secs)
+    }
+
+    pub fn timeout_long(&self) -> Duration {
+        Duration::from_secs(self.timeout_long_secs)
+    }
+}
+
+impl Default for FontConfig {
+    fn default() -> Self {
+        Self {
+            change: true,
+            small: "Uni2-VGA16.psf.gz".to_string(),
+            big: "Uni2-VGA28x16.psf.gz".to_string(),
+            console: "/dev/tty1".to_string(),
+        }
+    }
+}
+
+impl Default for DepositConfig {
+    fn default() -> Self {
+        Self {
+            max_cents: 25000,
+            iban: String::new(),
+            recipient: String::new(),
+        }
+    }
+}
+
+impl Default for BalanceConfig {
+    fn default() -> Self {
+        Self {
+            low_warning_cents: 500,
+        }
+    }
+}
+
+impl Default for LeaderboardConfig {
+    fn default() -> Self {
+        Self { rows: 20 }
+    }
+}
+
+impl Default for IdleConfig {
+    fn default() -> Self {
+        Self {
+            timeout_secs: 60,
+            panel_secs: 10,
+            refresh_secs: 5 * 60,
+            announcements: "/etc/rvterminal/announcements.txt".to_string(),
+        }
+    }
+}
+
+impl IdleConfig {
+    pub fn timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout_secs)
+    }
+
+    pub fn panel_duration(&self) -> Duration {
+        Duration::from_secs(self.panel_secs)
+    }
+
+    pub fn refresh_interval(&self) -> Duration {
+        Duration::from_secs(self.refresh_secs)
+    }
+}
+
+impl Default for StoreConfig {
+    fn default() -> Self {
+        Self {
+            dir: "/var/lib/rvterminal".to_string(),
+        }
+    }
+}
+
+impl Default for BatchesConfig {
+    fn default() -> Self {
+        Self { expiring_days: 2 }
+    }
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self {
+            dir: "/var/log/rvterminal".to_string(),
+            level: "info".to_string(),
+        }
+    }
+}
+
+impl Default for SupervisorConfig {
+    fn default() -> Self {
+        Self {
+            max_crashes: 3,
+            crash_window_secs: 10 * 60,
+        }
+    }
+}
+
+impl Default for PinConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            lockout_secs: 15 * 60,
+            purchase_threshold_cents: 1000,
+        }
+    }
+}
+
+impl Default for LoginConfig {
+    fn default() -> Self {
+        Self {
+            free_attempts: 3,
+            max_delay_secs: 5 * 60,
+            lockout_after: 10,
+            lockout_secs: 15 * 60,
+            terminal_lockout_after: 30,
+            notify_owner: true,
+        }
+    }
+}
+
+pub enum CliAction {
+    Run,
+    PrintConfig,
+    Help,
+}
+
+impl Config {
+    pub fn load(args: &[String]) -> Result<(CliAction, Config), Vec<String>> {
+        Self::load_with_env(args, |key| std::env::var(key).ok())
+    }
+
+    pub fn load_with_env<E>(args: &[String], env: E) -> Result<(CliAction, Config), Vec<String>>
+    where
+        E: Fn(&str) -> Option<String>,
+    {
+        let mut action = CliAction::Run;
+        let mut config_path: Option<String> = None;
+        let mut overrides: Vec<(&str, String)> = Vec::new();
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--help" | "-h" => action = CliAction::Help,
+                "--print-config" => action = CliAction::PrintConfig,
+                "--development" | "--no-font-change" => overrides.push((arg, String::new())),
+                "--config" | "--api-url" | "--receipt-printer" | "--log-dir" | "--log-level" => {
+                    let Some(value) = args.next() else {
+                        return Err(vec![format!("missing value for {arg}")]);
+                    };
+                    if arg == "--config" {
+                        config_path = Some(value.clone());
+                    } else {
+                        overrides.push((arg, value.clone()));
+                    }
+                }
+                other => return Err(vec![format!("unknown argument: {other}")]),
+            }
+        }
+
+        let mut config = match config_path.or_else(|| env("RV_CONFIG")) {
+            Some(path) => Self::from_file(Path::new(&path))?,
+            None if Path::new(DEFAULT_CONFIG_PATH).exists() => {
+                Self::from_file(Path::new(DEFAULT_CONFIG_PATH))?
+            }
+            None => Config::default(),
+        };
+
+        if let Some(url) = env("RV_API_URL") {
+            config.api.url = url;
+        }
+        if let Some(secret) = env("RV_TERMINAL_SECRET") {
+            config.api.terminal_secret = secret;
+        }
+        if env("DEVELOPMENT").is_some() {
+            config.development = true;
+        }
+        if env("NO_FONT_CHANGE").is_some() {
+            config.font.change = false;
+        }
+        if let Some(printer) = env("RV_RECEIPT_PRINTER") {
+            config.receipt.printer = Some(printer);
+        }
+        if let Some(dir) = env("RV_LOG_DIR") {
+            config.log.dir = dir;
+        }
+        if let Some(level) = env("RV_LOG_LEVEL") {
+            config.log.level = level;
+        }
+
+        for (flag, value) in overrides {
+            match flag {
+                "--api-url" => config.api.url = value,
+                "--development" => config.development = true,
+                "--no-font-change" => config.font.change = false,
+                "--receipt-printer" => config.receipt.printer = Some(value),
+                "--log-dir" => config.log.dir = value,
+                "--log-level" => config.log.level = value,
+                _ => unreachable!(),
+            }
+        }
+
+        config.validate()?;
+        Ok((action, config))
+    }
+
+    fn from_file(path: &Path) -> Result<Config, Vec<String>> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| vec![format!("reading config file {}: {e}", path.display())])?;
+        toml::from_str(&content)
+            .map_err(|e| vec![format!("parsing config file {}: {e}", path.display())])
+    }
+
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut errors = Vec::new();
+        if !self.api.url.starts_with("http://") && !self.api.url.starts_with("https://") {
+            errors.push(format!(
+                "api.url must be a http(s) url, got {:?}",
+                self.api.url
+            ));
+        }
+        if self.input.timeout_short_secs == 0 || self.input.timeout_long_secs == 0 {
+            errors.push("input timeouts must be greater than zero".to_string());
+        }
+        if self.input.timeout_short_secs > self.input.timeout_long_secs {
+            errors.push("input.timeout_short_secs must not exceed timeout_long_secs".to_string());
+        }
+        if self.deposit.max_cents == 0 {
+            errors.push("deposit.max_cents must be greater than zero".to_string());
+        }
+        if self.balance.low_warning_cents < 0 {
+            errors.push("balance.low_warning_cents must not be negative".to_string());
+        }
+        if !(1..=100).contains(&self.leaderboard.rows) {
+            errors.push("leaderboard.rows must be between 1 and 100".to_string());
+        }
+        if self.idle.timeout_secs == 0 || self.idle.panel_secs == 0 || self.idle.refresh_secs == 0 {
+            errors.push("idle timeouts must be greater than zero".to_string());
+        }
+        if self.font.small.is_empty() || self.font.big.is_empty() {
+            errors.push("font.small and font.big must not be empty".to_string());
+        }
+        if log::LevelFilter::from_str(&self.log.level).is_err() {
+            errors.push(format!(
+                "log.level {:?} is not a valid level",
+                self.log.level
+            ));
+        }
+        if self.pin.max_attempts == 0 {
+            errors.push("pin.max_attempts must be at least 1".to_string());
+        }
+        if self.login.lockout_after == 0 || self.login.terminal_lockout_after == 0 {
+            errors.push(
+                "login.lockout_after and login.terminal_lockout_after must be at least 1"
+                    .to_string(),
+            );
+        }
+        if self.supervisor.max_crashes == 0 {
+            errors.push("supervisor.max_crashes must be at least 1".to_string());
+        }
+        match errors.is_empty() {
+            true => Ok(()),
+            false => Err(errors),
+        }
+    }
+
+    // TOML with secrets masked
+    pub fn to_printable(&self) -> String {
+        let mut printable = self.clone();
+        printable.api.terminal_secret = "[REDACTED]".to_string();
+        toml::to_string(&printable).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|a| a.to_string()).collect()
+    }
+
+    // Given as RV_CONFIG so that the config file of the build host is not read
+    fn empty_config_file(name: &str) -> String {
+        let path =
+            std::env::temp_dir().join(format!("rvterminal-{name}-{}.toml", std::process::id()));
+        fs::write(&path, "").unwrap();
+        path.to_string_lossy().to_string()
+    }
+
+    #[test]
+    fn config_file_overrides_defaults() {
+        let config: Config = toml::from_str(
+            "development = true\n[input]\ntimeout_short_secs = 30\n[leaderboard]\nrows = 10\n",
+        )
+        .unwrap();
+        assert!(config.development);
+        assert_eq!(config.input.timeout_short(), Duration::from_secs(30));
+        assert_eq!(config.input.timeout_long(), Duration::from_secs(300));
+        assert_eq!(config.leaderboard.rows, 10);
+        assert_eq!(config.deposit.max_cents, 25000);
+        assert!(toml::from_str::<Config>("[input]\ntimeout_shrot_secs = 1\n").is_err());
+    }
+
+    #[test]
+    fn env_and_cli_overrides_work() {
+        let config_file = empty_config_file("env-and-cli");
+        let env = |key: &str| match key {
+            "RV_CONFIG" => Some(config_file.clone()),
+            "RV_API_URL" => Some("http://env/api".to_string()),
+            "NO_FONT_CHANGE" => Some("1".to_string()),
+            _ => None,
+        };
+        let (_, config) = Config::load_with_env(&args(&[]), env).unwrap();
+        assert_eq!(config.api.url, "http://env/api");
+        assert!(!config.font.change);
+
+        let (action, config) = Config::load_with_env(
+            &args(&["--api-url", "https://cli/api", "--print-config"]),
+            env,
+        )
+        .unwrap();
+        assert_eq!(config.api.url, "https://cli/api");
+        assert!(matches!(action, CliAction::PrintConfig));
+        assert!(!config.to_printable().contains("unsecure"));
+    }
+
+    #[test]
+    fn validation_errors_are_reported() {
+        let config_file = empty_config_file("validation");
+        let env = |key: &str| (key == "RV_CONFIG").then(|| config_file.clone());
+        assert!(Config::load_with_env(&args(&["--bogus"]), env).is_err());
+        assert!(Config::load_with_env(&args(&["--api-url"]), env).is_err());
+        let errors = Config::load_with_env(
+            &args(&["--api-url", "localhost", "--log-level", "loud"]),
+            env,
+        )
+        .err()
+        .unwrap();
+        assert_eq!(errors.len(), 2);
+    }
+}
@@ -0,0 +1,130 @@
+use crate::pin::PinMode;
+use crate::prefs::{SpendingLimits, UserPrefs};
+use crate::rv_api::{DepositHistoryEvent, PurchaseHistoryEvent, RfidTag, UserInfo};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+// Settings kept on this terminal, without PIN hashes
+#[derive(Serialize)]
+pub struct TerminalSettings {
+    pub spending_limits: SpendingLimits,
+    pub low_balance_cents: Option<i32>,
+    pub pin_mode: Option<PinMode>,
+}
+
+impl From<&UserPrefs> for TerminalSettings {
+    fn from(prefs: &UserPrefs) -> Self {
+        Self {
+            spending_limits: prefs.limits.clone(),
+            low_balance_cents: prefs.low_balance_cents,
+            pin_mode: prefs.pin.as_ref().map(|pin| pin.mode),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct Privacy {
+    pub level: u8,
+    pub public_statistics: &'static str,
+}
+
+impl Privacy {
+    pub fn new(level: u8) -> Self {
+        Self {
+            level,
+            public_statistics: match level {
+                0 => "shown with your username",
+                1 => "shown anonymously",
+                _ => "not shown",
+            },
+        }
+    }
+}
+
+// Everything the service and this terminal hold about a user
+#[derive(Serialize)]
+pub struct DataExport {
+    pub generated: DateTime<Utc>,
+    pub user: UserInfo,
+    pub privacy: Privacy,
+    pub purchases: Vec<PurchaseHistoryEvent>,
+    pub deposits: Vec<DepositHistoryEvent>,
+    pub rfid_tags: Vec<RfidTag>,
+    // Without the TOTP secret
+    pub two_factor_enabled: bool,
+    pub terminal_settings: TerminalSettings,
+}
+
+impl DataExport {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("serializable export")
+    }
+}
+
+// Splits a document into QR code payloads of at most `size` bytes of data,
+// each prefixed with "RVDATA i/n:" so that a reader can put them back together
+pub fn qr_chunks(document: &str, size: usize) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut rest = document;
+    while !rest.is_empty() {
+        let mut end = size.min(rest.len());
+        while !rest.is_char_boundary(end) {
+            end -= 1;
+        }
+        if end == 0 {
+            end = rest.chars().next().map_or(0, char::len_utf8);
+        }
+        let (part, tail) = rest.split_at(end);
+        parts.push(part);
+        rest = tail;
+    }
+    let count = parts.len();
+    parts
+        .iter()
+        .enumerate()
+        .map(|(i, part)| format!("RVDATA {}/{count}:{part}", i + 1))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn data_export_works() {
+        let export = DataExport {
+            generated: Utc::now(),
+            user: UserInfo {
+                user_id: 7,
+                username: "maija".to_string(),
+                full_name: "Maija Virtanen".to_string(),
+                email: "maija@example.com".to_string(),
+                email_verified: true,
+                money_balance: 1250,
+                role: "USER".to_string(),
+                privacy_level: 1,
+            },
+            privacy: Privacy::new(1),
+            purchases: Vec::new(),
+            deposits: Vec::new(),
+            rfid_tags: Vec::new(),
+            two_factor_enabled: false,
+            terminal_settings: TerminalSettings::from(&UserPrefs::default()),
+        };
+        let json: serde_json::Value = serde_json::from_str(&export.to_json()).unwrap();
+        assert_eq!(json["user"]["username"], "maija");
+        assert_eq!(json["privacy"]["public_statistics"], "shown anonymously");
+        assert_eq!(json["two_factor_enabled"], false);
+
+        assert_eq!(
+            qr_chunks("abcdeäfg", 3),
+            [
+                "RVDATA 1/4:abc",
+                "RVDATA 2/4:de",
+                "RVDATA 3/4:äf",
+                "RVDATA 4/4:g"
+            ]
+        );
+        assert_eq!(qr_chunks("ä", 1), ["RVDATA 1/1:ä"]);
+        assert!(qr_chunks("", 3).is_empty());
+    }
+}
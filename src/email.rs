@@ -0,0 +1,74 @@
+// Address syntax as accepted by common mail servers: a dot-atom local part and
+// a domain of hostname labels with an alphabetic top level domain. Quoted local
+// parts and IP address literals are not supported.
+
+const LOCAL_SPECIALS: &str = "!#$%&'*+-/=?^_`{|}~";
+
+fn is_valid_local_part(local: &str) -> bool {
+    (1..=64).contains(&local.len())
+        && !local.starts_with('.')
+        && !local.ends_with('.')
+        && !local.contains("..")
+        && local
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '.' || LOCAL_SPECIALS.contains(c))
+}
+
+fn is_valid_label(label: &str) -> bool {
+    (1..=63).contains(&label.len())
+        && !label.starts_with('-')
+        && !label.ends_with('-')
+        && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+}
+
+fn is_valid_domain(domain: &str) -> bool {
+    let labels: Vec<&str> = domain.split('.').collect();
+    domain.len() <= 253
+        && labels.len() >= 2
+        && labels.iter().all(|label| is_valid_label(label))
+        && labels
+            .last()
+            .is_some_and(|tld| tld.len() >= 2 && tld.chars().all(|c| c.is_ascii_alphabetic()))
+}
+
+pub fn is_valid(email: &str) -> bool {
+    match email.split_once('@') {
+        Some((local, domain)) => {
+            email.len() <= 254 && is_valid_local_part(local) && is_valid_domain(domain)
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn email_validation_works() {
+        for email in [
+            "matti@example.com",
+            "matti.meikalainen+rv@cs.helsinki.fi",
+            "o'brien@tko-aly.fi",
+        ] {
+            assert!(is_valid(email), "{email}");
+        }
+        for email in [
+            "",
+            "matti",
+            "matti@",
+            "@example.com",
+            "matti@example",
+            "matti@@example.com",
+            "matti@exa mple.com",
+            "matti..m@example.com",
+            ".matti@example.com",
+            "matti@-example.com",
+            "matti@example..com",
+            "matti@example.c0m",
+            "matti@example.com.",
+            "mätti@example.com",
+        ] {
+            assert!(!is_valid(email), "{email}");
+        }
+    }
+}
@@ -17,7 +17,8 @@ use std::{
     time::{Duration, Instant},
 };
 
-use crate::DEVELOPMENT_MODE;
+use crate::barcode;
+use crate::config::Config;
 
 const RFID_VENDOR: u16 = 0x413d;
 const RFID_PRODUCT: u16 = 0x2107;
@@ -61,8 +62,9 @@ fn rfid_key_to_char(key: evdev::KeyCode) -> Option<char> {
     }
 }
 
-fn barcode_key_to_char(key: evdev::KeyCode) -> Option<char> {
-    match key {
+// Letters are uppercase with Shift, e.g. in the "]E0" symbology identifier and lot numbers
+fn barcode_key_to_char(key: evdev::KeyCode, shift: bool) -> Option<char> {
+    let ch = match key {
         evdev::KeyCode::KEY_0 => Some('0'),
         evdev::KeyCode::KEY_1 => Some('1'),
         evdev::KeyCode::KEY_2 => Some('2'),
@@ -104,7 +106,54 @@ fn barcode_key_to_char(key: evdev::KeyCode) -> Option<char> {
         evdev::KeyCode::KEY_EQUAL => Some('='),
         evdev::KeyCode::KEY_MINUS => Some('-'),
         evdev::KeyCode::KEY_DOT => Some('.'),
+        evdev::KeyCode::KEY_RIGHTBRACE => Some(']'),
         _ => None,
+    };
+    match shift {
+        true => ch.map(|ch| ch.to_ascii_uppercase()),
+        false => ch,
+    }
+}
+
+// Collects the key events of a reader or a scanner into lines of input
+struct KeyDecoder<K> {
+    key_to_char: K,
+    input: String,
+    ctrl: bool,
+    shift: bool,
+}
+
+impl<K> KeyDecoder<K>
+where
+    K: Fn(evdev::KeyCode, bool) -> Option<char>,
+{
+    fn new(key_to_char: K) -> Self {
+        Self {
+            key_to_char,
+            input: String::new(),
+            ctrl: false,
+            shift: false,
+        }
+    }
+
+    // `value` is 1 for a press, 0 for a release and 2 for a repeat.
+    // Returns the input line when Enter is pressed.
+    fn key(&mut self, k: evdev::KeyCode, value: i32) -> Option<String> {
+        if k == evdev::KeyCode::KEY_LEFTCTRL || k == evdev::KeyCode::KEY_RIGHTCTRL {
+            self.ctrl = value != 0;
+        } else if k == evdev::KeyCode::KEY_LEFTSHIFT || k == evdev::KeyCode::KEY_RIGHTSHIFT {
+            self.shift = value != 0;
+        } else if value != 1 {
+            // Key release or repeat
+        } else if k == evdev::KeyCode::KEY_ENTER {
+            return Some(std::mem::take(&mut self.input));
+        } else if self.ctrl && k == evdev::KeyCode::KEY_RIGHTBRACE {
+            // Scanners in keyboard mode send the GS1 group separator as Ctrl+]
+            self.input.push(barcode::GS);
+        } else if let Some(ch) = (self.key_to_char)(k, self.shift) {
+            self.input.push(ch);
+        }
+        None
     }
 }
 
@@ -125,7 +174,7 @@ fn capture_device_input<K>(
     input_event_variant: fn(String) -> InputEvent,
     sender: Sender<InputEvent>,
 ) where
-    K: Fn(evdev::KeyCode) -> Option<char>,
+    K: Fn(evdev::KeyCode, bool) -> Option<char>,
 {
     if let Some(mut device) = get_device(vendor, product) {
         if device.is_grabbed() {
@@ -136,27 +185,36 @@ fn capture_device_input<K>(
         loop {
             match device.grab() {
                 Ok(_) => break,
-                Err(_) => {
+                Err(e) => {
                     if start.elapsed() > Duration::from_secs(1) {
+                        log::warn!(
+                            "event=device_grab vendor={vendor:#06x} product={product:#06x} ok=false error={:?}",
+                            e.to_string()
+                        );
                         return;
                     }
                     thread::sleep(Duration::from_millis(50));
                 }
             }
         }
+        log::info!("event=device_grab vendor={vendor:#06x} product={product:#06x} ok=true");
 
-        let mut input = String::new();
+        let mut decoder = KeyDecoder::new(key_to_char);
         loop {
-            let Ok(ev) = device.fetch_events() else {
-                return;
+            let ev = match device.fetch_events() {
+                Ok(ev) => ev,
+                Err(e) => {
+                    log::warn!(
+                        "event=device_read vendor={vendor:#06x} product={product:#06x} error={:?}",
+                        e.to_string()
+                    );
+                    return;
+                }
             };
-            for e in ev.filter(|e| e.value() == 1) {
-                if let EventSummary::Key(_, k, _) = e.destructure() {
-                    if k == evdev::KeyCode::KEY_ENTER {
-                        sender.send(input_event_variant(input.clone())).unwrap();
-                        input.clear();
-                    } else if let Some(ch) = key_to_char(k) {
-                        input.push(ch);
+            for e in ev {
+                if let EventSummary::Key(_, k, value) = e.destructure() {
+                    if let Some(input) = decoder.key(k, value) {
+                        sender.send(input_event_variant(input)).unwrap();
                     }
                 }
             }
@@ -181,14 +239,23 @@ fn register_device_input(vendor: u16, product: u16, sender: Sender<InputEvent>)
 }
 
 impl<T: rusb::UsbContext> rusb::Hotplug<T> for HotPlugHandler {
-    fn device_arrived(&mut self, _device: rusb::Device<T>) {
+    fn device_arrived(&mut self, device: rusb::Device<T>) {
+        if let Ok(descriptor) = device.device_descriptor() {
+            log::info!(
+                "event=device_arrived vendor={:#06x} product={:#06x}",
+                descriptor.vendor_id(),
+                descriptor.product_id()
+            );
+        }
+
         // RFID reader
         let sender = self.chan.clone();
         thread::spawn(move || {
             capture_device_input(
                 RFID_VENDOR,
                 RFID_PRODUCT,
-                rfid_key_to_char,
+                // Tag ids are stored in lowercase
+                |key, _shift| rfid_key_to_char(key),
                 InputEvent::Rfid,
                 sender,
             );
@@ -218,7 +285,15 @@ impl<T: rusb::UsbContext> rusb::Hotplug<T> for HotPlugHandler {
             );
         });
     }
-    fn device_left(&mut self, _device: rusb::Device<T>) {}
+    fn device_left(&mut self, device: rusb::Device<T>) {
+        if let Ok(descriptor) = device.device_descriptor() {
+            log::info!(
+                "event=device_left vendor={:#06x} product={:#06x}",
+                descriptor.vendor_id(),
+                descriptor.product_id()
+            );
+        }
+    }
 }
 
 fn deserialize_software_keyboard_input_event(key: &str) -> Option<InputEvent> {
@@ -293,13 +368,12 @@ fn deserialize_software_input_event(line: &str) -> Result<InputEvent, String> {
     }
 }
 
-fn software_input(sender: Sender<InputEvent>) {
+fn software_input(sender: Sender<InputEvent>, socket_path: String) {
     thread::spawn(move || {
-        let socket_path = "/tmp/rvterminal.sock";
-        if Path::new(socket_path).exists() {
-            fs::remove_file(socket_path).expect("Failed to remove existing socket");
+        if Path::new(&socket_path).exists() {
+            fs::remove_file(&socket_path).expect("Failed to remove existing socket");
         }
-        let listener = UnixListener::bind(socket_path).expect("Failed to bind");
+        let listener = UnixListener::bind(&socket_path).expect("Failed to bind");
 
         for stream in listener.incoming() {
             match stream {
@@ -325,7 +399,7 @@ fn software_input(sender: Sender<InputEvent>) {
 }
 
 // Call only once
-pub fn init() -> Receiver<InputEvent> {
+pub fn init(config: &Config) -> Receiver<InputEvent> {
     let (sender, receiver) = sync::mpsc::channel::<InputEvent>();
 
     // Terminal input
@@ -353,9 +427,60 @@ pub fn init() -> Receiver<InputEvent> {
         register_device_input(BARCODE2_VENDOR, BARCODE2_PRODUCT, sender2);
     });
 
-    if *DEVELOPMENT_MODE {
-        software_input(sender);
+    if config.development {
+        software_input(sender, config.input.socket_path.clone());
     }
 
     receiver
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Key events of a scanner typing `text` on a US layout, Shift held for capitals
+    fn scan(text: &str) -> String {
+        let mut events = Vec::new();
+        for ch in text.chars() {
+            let (modifier, key) = match ch {
+                barcode::GS => (
+                    Some(evdev::KeyCode::KEY_LEFTCTRL),
+                    evdev::KeyCode::KEY_RIGHTBRACE,
+                ),
+                ch => (
+                    ch.is_ascii_uppercase()
+                        .then_some(evdev::KeyCode::KEY_LEFTSHIFT),
+                    (0..256)
+                        .map(evdev::KeyCode::new)
+                        .find(|&key| {
+                            barcode_key_to_char(key, false) == Some(ch.to_ascii_lowercase())
+                        })
+                        .unwrap(),
+                ),
+            };
+            events.extend(modifier.map(|modifier| (modifier, 1)));
+            events.extend([(key, 1), (key, 0)]);
+            events.extend(modifier.map(|modifier| (modifier, 0)));
+        }
+        events.push((evdev::KeyCode::KEY_ENTER, 1));
+
+        let mut decoder = KeyDecoder::new(barcode_key_to_char);
+        events
+            .into_iter()
+            .filter_map(|(key, value)| decoder.key(key, value))
+            .next()
+            .unwrap()
+    }
+
+    #[test]
+    fn scanned_barcodes_work() {
+        let ean = barcode::parse(&scan("]E00700941359952")).unwrap();
+        assert_eq!(ean.code, "00700941359952");
+
+        let gs1 = scan("]C1010950110153000310AB12\x1d17260930");
+        assert_eq!(gs1, "]C1010950110153000310AB12\x1d17260930");
+        let gs1 = barcode::parse(&gs1).unwrap();
+        assert_eq!(gs1.code, "09501101530003");
+        assert_eq!(gs1.batch.as_deref(), Some("AB12"));
+    }
+}
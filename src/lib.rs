@@ -1,27 +1,36 @@
+mod audit;
+mod bank_statement;
+mod barcode;
+mod batches;
+pub mod config;
+mod data_export;
+mod email;
 pub mod input;
+mod line_editor;
+mod logging;
+mod login_guard;
 mod loops;
+mod password_policy;
+mod pin;
+mod prefs;
+mod receipt;
+mod reconciliation;
+mod reference;
 mod rv_api;
+mod store;
+mod supervisor;
+mod totp;
 mod utils;
 
-use crossterm::{
-    cursor, execute,
-    terminal::{enable_raw_mode, EnterAlternateScreen},
-};
+pub use loops::main_loop;
 
+use config::{CliAction, Config};
 use std::{
     io::{self, stdout, Result, Stdout, Write},
-    sync::{mpsc::Receiver, LazyLock},
-    time::Duration,
+    process::ExitCode,
+    sync::mpsc::Receiver,
 };
 
-pub const INPUT_TIMEOUT_SHORT: Duration = Duration::from_secs(60);
-pub const INPUT_TIMEOUT_LONG: Duration = Duration::from_secs(5 * 60);
-
-pub static DEVELOPMENT_MODE: LazyLock<bool> =
-    LazyLock::new(|| std::env::var("DEVELOPMENT").is_ok());
-pub static NO_FONT_CHANGE: LazyLock<bool> =
-    LazyLock::new(|| std::env::var("NO_FONT_CHANGE").is_ok());
-
 pub struct TerminalWriter {
     stdout: Stdout,
     pub test_output: Vec<u8>,
@@ -58,20 +67,49 @@ impl Write for TerminalWriter {
 pub struct TerminalIO {
     pub recv: Receiver<input::InputEvent>,
     pub writer: TerminalWriter,
+    pub config: Config,
+}
+
+pub fn start() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let config = match Config::load(&args) {
+        Ok((CliAction::Run, config)) => config,
+        Ok((CliAction::PrintConfig, config)) => {
+            print!("{}", config.to_printable());
+            return ExitCode::SUCCESS;
+        }
+        Ok((CliAction::Help, _)) => {
+            println!("{}", config::USAGE);
+            return ExitCode::SUCCESS;
+        }
+        Err(errors) => {
+            for error in errors {
+                eprintln!("config error: {error}");
+            }
+            eprintln!("\n{}", config::USAGE);
+            return ExitCode::from(2);
+        }
+    };
+
+    match run(config) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{e}");
+            ExitCode::FAILURE
+        }
+    }
 }
 
-pub fn start() -> io::Result<()> {
-    utils::set_big_font();
+fn run(config: Config) -> io::Result<()> {
+    logging::init(&config.log);
+    log::info!("event=startup version={:?}", env!("CARGO_PKG_VERSION"));
+    utils::set_big_font(&config.font);
     let mut terminal_io = TerminalIO {
-        recv: input::init(),
+        recv: input::init(&config),
         writer: TerminalWriter::new(false),
+        config,
     };
-    enable_raw_mode().expect("Enabling raw mode failed");
-    execute!(
-        terminal_io.writer,
-        EnterAlternateScreen,
-        cursor::EnableBlinking,
-        cursor::MoveTo(0, 0)
-    )?;
-    loops::main_loop(&mut terminal_io)
+    supervisor::install_panic_hook();
+    supervisor::enter_terminal(&mut terminal_io)?;
+    supervisor::run(&mut terminal_io)
 }
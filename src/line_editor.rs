@@ -0,0 +1,202 @@
+use crossterm::{
+    cursor,
+    event::{KeyCode, KeyEvent, KeyModifiers},
+    queue,
+    style::{Print, PrintStyledContent, StyledContent},
+    terminal::{Clear, ClearType},
+};
+use std::io::{self, Write};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Echo {
+    Plain,
+    // Each character is shown as '*'
+    Masked,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Action {
+    Edited,
+    Submit,
+    Ignored,
+}
+
+// A single line of input with the cursor anywhere in it. The screen is redrawn
+// relative to the cursor, so the line must fit on the terminal row.
+pub struct LineEditor {
+    chars: Vec<char>,
+    cursor: usize,
+    echo: Echo,
+    accept: fn(char) -> bool,
+    // Cursor position on the screen after the last render
+    shown_cursor: usize,
+}
+
+impl LineEditor {
+    pub fn new(echo: Echo) -> Self {
+        Self {
+            chars: Vec::new(),
+            cursor: 0,
+            echo,
+            accept: |c| !c.is_control(),
+            shown_cursor: 0,
+        }
+    }
+
+    // Other characters are dropped when typed or pasted
+    pub fn accepting(mut self, accept: fn(char) -> bool) -> Self {
+        self.accept = accept;
+        self
+    }
+
+    pub fn text(&self) -> String {
+        self.chars.iter().collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.chars.is_empty()
+    }
+
+    fn insert(&mut self, c: char) -> bool {
+        if !(self.accept)(c) {
+            return false;
+        }
+        self.chars.insert(self.cursor, c);
+        self.cursor += 1;
+        true
+    }
+
+    // Pasted text up to the first line break, it is not submitted
+    pub fn insert_str(&mut self, text: &str) -> Action {
+        let line = text.lines().next().unwrap_or_default();
+        let inserted = line.chars().filter(|&c| self.insert(c)).count();
+        match inserted {
+            0 => Action::Ignored,
+            _ => Action::Edited,
+        }
+    }
+
+    fn delete_word(&mut self) {
+        let mut start = self.cursor;
+        while start > 0 && self.chars[start - 1].is_whitespace() {
+            start -= 1;
+        }
+        while start > 0 && !self.chars[start - 1].is_whitespace() {
+            start -= 1;
+        }
+        self.chars.drain(start..self.cursor);
+        self.cursor = start;
+    }
+
+    pub fn handle_key(&mut self, key: &KeyEvent) -> Action {
+        let before = (self.chars.clone(), self.cursor);
+        match key.code {
+            KeyCode::Enter => return Action::Submit,
+            KeyCode::Char(c) if key.modifiers.contains(KeyModifiers::CONTROL) => match c {
+                'u' => {
+                    self.chars.clear();
+                    self.cursor = 0;
+                }
+                'w' => self.delete_word(),
+                'a' => self.cursor = 0,
+                'e' => self.cursor = self.chars.len(),
+                _ => (),
+            },
+            KeyCode::Char(c) => {
+                self.insert(c);
+            }
+            KeyCode::Backspace if self.cursor > 0 => {
+                self.cursor -= 1;
+                self.chars.remove(self.cursor);
+            }
+            KeyCode::Delete if self.cursor < self.chars.len() => {
+                self.chars.remove(self.cursor);
+            }
+            KeyCode::Left => self.cursor = self.cursor.saturating_sub(1),
+            KeyCode::Right => self.cursor = (self.cursor + 1).min(self.chars.len()),
+            KeyCode::Home => self.cursor = 0,
+            KeyCode::End => self.cursor = self.chars.len(),
+            _ => (),
+        }
+        match before.0 == self.chars && before.1 == self.cursor {
+            true => Action::Ignored,
+            false => Action::Edited,
+        }
+    }
+
+    // Redraws the line from its start, `suffix` is shown after the text
+    pub fn render<W: Write>(
+        &mut self,
+        writer: &mut W,
+        suffix: Option<StyledContent<String>>,
+    ) -> io::Result<()> {
+        if self.shown_cursor > 0 {
+            queue!(writer, cursor::MoveLeft(self.shown_cursor as u16))?;
+        }
+        let shown = match self.echo {
+            Echo::Plain => self.text(),
+            Echo::Masked => "*".repeat(self.chars.len()),
+        };
+        queue!(writer, Print(shown), Clear(ClearType::UntilNewLine))?;
+        let mut back = self.chars.len() - self.cursor;
+        if let Some(suffix) = suffix {
+            back += suffix.content().chars().count();
+            queue!(writer, PrintStyledContent(suffix))?;
+        }
+        if back > 0 {
+            queue!(writer, cursor::MoveLeft(back as u16))?;
+        }
+        self.shown_cursor = self.cursor;
+        writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    fn ctrl(c: char) -> KeyEvent {
+        KeyEvent::new(KeyCode::Char(c), KeyModifiers::CONTROL)
+    }
+
+    #[test]
+    fn line_editor_works() {
+        let mut editor = LineEditor::new(Echo::Plain);
+        for c in "kahvi maito".chars() {
+            editor.handle_key(&key(KeyCode::Char(c)));
+        }
+        editor.handle_key(&key(KeyCode::Home));
+        editor.handle_key(&key(KeyCode::Right));
+        editor.handle_key(&key(KeyCode::Delete));
+        editor.handle_key(&key(KeyCode::Char('o')));
+        assert_eq!(editor.text(), "kohvi maito");
+        assert_eq!(editor.handle_key(&key(KeyCode::Left)), Action::Edited);
+        editor.handle_key(&ctrl('a'));
+        assert_eq!(editor.handle_key(&key(KeyCode::Left)), Action::Ignored);
+
+        editor.handle_key(&key(KeyCode::End));
+        editor.handle_key(&ctrl('w'));
+        assert_eq!(editor.text(), "kohvi ");
+        editor.handle_key(&ctrl('w'));
+        assert!(editor.is_empty());
+        assert_eq!(editor.handle_key(&key(KeyCode::Backspace)), Action::Ignored);
+
+        assert_eq!(editor.insert_str("salasana\r\nrm -rf /"), Action::Edited);
+        assert_eq!(editor.text(), "salasana");
+        editor.handle_key(&ctrl('u'));
+        assert!(editor.is_empty());
+        assert_eq!(editor.handle_key(&key(KeyCode::Enter)), Action::Submit);
+
+        let mut editor = LineEditor::new(Echo::Masked).accepting(|c| c.is_ascii_digit());
+        assert_eq!(editor.insert_str("a"), Action::Ignored);
+        editor.insert_str("64 1234");
+        let mut screen = Vec::new();
+        editor.render(&mut screen, None).unwrap();
+        assert_eq!(editor.text(), "641234");
+        assert!(String::from_utf8(screen).unwrap().starts_with("******"));
+    }
+}
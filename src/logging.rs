@@ -0,0 +1,149 @@
+use crate::config::LogConfig;
+use chrono::Local;
+use log::{LevelFilter, Log, Metadata, Record};
+use regex::Regex;
+use std::{
+    fs::{self, File},
+    io::Write,
+    path::PathBuf,
+    str::FromStr,
+    sync::{LazyLock, Mutex},
+};
+
+// Log lines are written as `key=value` pairs, e.g. `event=login method=rfid user="foo"`
+const LOG_FILE_NAME: &str = "rvterminal.log";
+const MAX_LOG_FILE_SIZE: u64 = 1024 * 1024;
+const KEPT_LOG_FILES: usize = 5;
+
+static SECRET_VALUE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r#"(?i)\b([a-z_]*(?:password|passwd|token|secret|rfid|pin))=("(?:[^"\\]|\\.)*"|\S+)"#,
+    )
+    .unwrap()
+});
+static BEARER_TOKEN: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)\bbearer\s+[A-Za-z0-9._~+/=-]+").unwrap());
+
+// Masks values of secret looking keys and bearer tokens
+pub fn redact(line: &str) -> String {
+    let line = SECRET_VALUE.replace_all(line, "$1=[REDACTED]");
+    BEARER_TOKEN
+        .replace_all(&line, "Bearer [REDACTED]")
+        .to_string()
+}
+
+struct LogFile {
+    file: File,
+    size: u64,
+}
+
+struct RotatingFileLogger {
+    level: LevelFilter,
+    dir: PathBuf,
+    current: Mutex<LogFile>,
+}
+
+impl RotatingFileLogger {
+    fn path(&self, index: usize) -> PathBuf {
+        match index {
+            0 => self.dir.join(LOG_FILE_NAME),
+            n => self.dir.join(format!("{LOG_FILE_NAME}.{n}")),
+        }
+    }
+
+    // rvterminal.log -> rvterminal.log.1 -> ... -> rvterminal.log.KEPT_LOG_FILES
+    fn rotate(&self, current: &mut LogFile) -> std::io::Result<()> {
+        for index in (0..KEPT_LOG_FILES).rev() {
+            let from = self.path(index);
+            if from.exists() {
+                fs::rename(from, self.path(index + 1))?;
+            }
+        }
+        current.file = open_log_file(&self.path(0))?;
+        current.size = 0;
+        Ok(())
+    }
+}
+
+fn open_log_file(path: &PathBuf) -> std::io::Result<File> {
+    fs::OpenOptions::new().create(true).append(true).open(path)
+}
+
+impl Log for RotatingFileLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let line = format!(
+            "{} {} {} {}\n",
+            Local::now().to_rfc3339(),
+            record.level(),
+            record.target(),
+            redact(&record.args().to_string())
+        );
+        let Ok(mut current) = self.current.lock() else {
+            return;
+        };
+        if current.size + line.len() as u64 > MAX_LOG_FILE_SIZE
+            && self.rotate(&mut current).is_err()
+        {
+            return;
+        }
+        if current.file.write_all(line.as_bytes()).is_ok() {
+            current.size += line.len() as u64;
+        }
+    }
+
+    fn flush(&self) {
+        if let Ok(mut current) = self.current.lock() {
+            let _ = current.file.flush();
+        }
+    }
+}
+
+// Logging is disabled if the log directory is not writable, the terminal is never used as a fallback
+pub fn init(config: &LogConfig) {
+    let dir = PathBuf::from(&config.dir);
+    let level = LevelFilter::from_str(&config.level).unwrap_or(LevelFilter::Info);
+    if fs::create_dir_all(&dir).is_err() {
+        return;
+    }
+    let path = dir.join(LOG_FILE_NAME);
+    let Ok(file) = open_log_file(&path) else {
+        return;
+    };
+    let size = file.metadata().map(|m| m.len()).unwrap_or(0);
+    let logger = RotatingFileLogger {
+        level,
+        dir,
+        current: Mutex::new(LogFile { file, size }),
+    };
+    if log::set_boxed_logger(Box::new(logger)).is_ok() {
+        log::set_max_level(level);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn redact_works() {
+        assert_eq!(
+            redact("event=login user=\"foo\" password=hunter2"),
+            "event=login user=\"foo\" password=[REDACTED]"
+        );
+        assert_eq!(
+            redact("event=rfid_login rfid=\"0a1b2c\" ok=true"),
+            "event=rfid_login rfid=[REDACTED] ok=true"
+        );
+        assert_eq!(
+            redact("access_token=\"a b\" header=Bearer abc.def"),
+            "access_token=[REDACTED] header=Bearer [REDACTED]"
+        );
+        assert_eq!(redact("event=purchase count=2"), "event=purchase count=2");
+    }
+}
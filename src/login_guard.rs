@@ -0,0 +1,178 @@
+use crate::config::LoginConfig;
+use crate::store;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+
+const LOGIN_GUARD_FILE: &str = "login_guard.json";
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct Attempts {
+    failures: u32,
+    last_failure: DateTime<Utc>,
+    locked_until: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Blocked {
+    // Exponential backoff between attempts on the same username
+    Wait(DateTime<Utc>),
+    Locked(DateTime<Utc>),
+    // Too many failures on this terminal regardless of the username
+    TerminalLocked(DateTime<Utc>),
+}
+
+// Failed password logins per username and per terminal, kept over restarts
+#[derive(Serialize, Deserialize, Default)]
+pub struct LoginGuard {
+    users: BTreeMap<String, Attempts>,
+    terminal_failures: VecDeque<DateTime<Utc>>,
+    terminal_locked_until: Option<DateTime<Utc>>,
+}
+
+fn backoff(failures: u32, config: &LoginConfig) -> Duration {
+    if failures < config.free_attempts {
+        return Duration::zero();
+    }
+    let exponent = (failures - config.free_attempts).min(16);
+    Duration::seconds((2i64 << exponent).min(config.max_delay_secs as i64))
+}
+
+impl LoginGuard {
+    pub fn load(dir: &str) -> std::io::Result<Self> {
+        store::load(dir, LOGIN_GUARD_FILE)
+    }
+
+    pub fn save(&self, dir: &str) -> std::io::Result<()> {
+        store::save(dir, LOGIN_GUARD_FILE, self)
+    }
+
+    pub fn check(
+        &self,
+        username: &str,
+        now: DateTime<Utc>,
+        config: &LoginConfig,
+    ) -> Option<Blocked> {
+        if let Some(until) = self.terminal_locked_until.filter(|&until| until > now) {
+            return Some(Blocked::TerminalLocked(until));
+        }
+        let attempts = self.users.get(&username.to_lowercase())?;
+        if let Some(until) = attempts.locked_until.filter(|&until| until > now) {
+            return Some(Blocked::Locked(until));
+        }
+        let next = attempts.last_failure + backoff(attempts.failures, config);
+        (next > now).then_some(Blocked::Wait(next))
+    }
+
+    // Returns the failure count of the username, the account gets locked at
+    // `lockout_after` failures
+    pub fn record_failure(
+        &mut self,
+        username: &str,
+        now: DateTime<Utc>,
+        config: &LoginConfig,
+    ) -> u32 {
+        let window = Duration::seconds(config.lockout_secs as i64);
+        self.terminal_failures.push_back(now);
+        while self
+            .terminal_failures
+            .front()
+            .is_some_and(|&time| time + window < now)
+        {
+            self.terminal_failures.pop_front();
+        }
+        if self.terminal_failures.len() >= config.terminal_lockout_after as usize {
+            self.terminal_failures.clear();
+            self.terminal_locked_until = Some(now + window);
+        }
+
+        let attempts = self
+            .users
+            .entry(username.to_lowercase())
+            .or_insert(Attempts {
+                failures: 0,
+                last_failure: now,
+                locked_until: None,
+            });
+        // Failures are forgotten after a quiet period
+        if attempts.last_failure + window < now {
+            attempts.failures = 0;
+        }
+        attempts.failures += 1;
+        attempts.last_failure = now;
+        if attempts.failures >= config.lockout_after {
+            attempts.locked_until = Some(now + window);
+        }
+        attempts.failures
+    }
+
+    pub fn record_success(&mut self, username: &str) {
+        self.users.remove(&username.to_lowercase());
+    }
+
+    // Drops usernames without recent failures so that the file does not grow forever
+    pub fn prune(&mut self, now: DateTime<Utc>, config: &LoginConfig) {
+        let window = Duration::seconds(config.lockout_secs as i64);
+        self.users.retain(|_, attempts| {
+            attempts.last_failure + window >= now
+                || attempts.locked_until.is_some_and(|until| until >= now)
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn login_guard_backoff_and_lockout_work() {
+        let config = LoginConfig {
+            free_attempts: 2,
+            max_delay_secs: 60,
+            lockout_after: 6,
+            lockout_secs: 900,
+            terminal_lockout_after: 10,
+            notify_owner: false,
+        };
+        let now = Utc::now();
+        let mut guard = LoginGuard::default();
+        assert_eq!(guard.check("Matti", now, &config), None);
+
+        guard.record_failure("Matti", now, &config);
+        assert_eq!(guard.check("matti", now, &config), None);
+        guard.record_failure("matti", now, &config);
+        assert_eq!(
+            guard.check("matti", now, &config),
+            Some(Blocked::Wait(now + Duration::seconds(2)))
+        );
+        assert_eq!(
+            guard.check("matti", now + Duration::seconds(2), &config),
+            None
+        );
+        guard.record_failure("matti", now, &config);
+        assert_eq!(
+            guard.check("matti", now, &config),
+            Some(Blocked::Wait(now + Duration::seconds(4)))
+        );
+        for _ in 0..3 {
+            guard.record_failure("matti", now, &config);
+        }
+        assert_eq!(
+            guard.check("matti", now + Duration::seconds(100), &config),
+            Some(Blocked::Locked(now + Duration::seconds(900)))
+        );
+        assert_eq!(guard.check("maija", now, &config), None);
+
+        guard.record_success("Matti");
+        assert_eq!(guard.check("matti", now, &config), None);
+
+        for i in 0..4 {
+            guard.record_failure(&format!("user{i}"), now, &config);
+        }
+        assert_eq!(
+            guard.check("maija", now, &config),
+            Some(Blocked::TerminalLocked(now + Duration::seconds(900)))
+        );
+        guard.prune(now + Duration::seconds(1000), &config);
+        assert!(guard.users.is_empty());
+    }
+}
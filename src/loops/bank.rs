@@ -0,0 +1,222 @@
+use crate::bank_statement::{self, MatchKind};
+use crate::rv_api::{self, ApiResult, ApiResultValue, PendingDeposit};
+use crate::utils::{self, print_error_line, print_title, printline, ConfirmResult, TimeoutResult};
+use crate::TerminalIO;
+
+use crossterm::{execute, style::Print};
+use std::time::Duration;
+
+fn print_pending(terminal_io: &mut TerminalIO, pending: &[PendingDeposit]) {
+    for (i, deposit) in pending.iter().enumerate() {
+        printline(
+            terminal_io,
+            &format!(
+                "{:>3}. {:<20} {:>9}€ {} created {}",
+                i + 1,
+                deposit.username,
+                utils::format_money(&deposit.amount),
+                deposit.reference,
+                deposit.created
+            ),
+        );
+    }
+}
+
+fn resolve(
+    terminal_io: &mut TerminalIO,
+    credentials: &rv_api::AuthenticationResponse,
+    deposit: &PendingDeposit,
+    confirm: bool,
+) {
+    match rv_api::resolve_pending_deposit_admin(
+        &terminal_io.config.api,
+        credentials,
+        deposit.deposit_id,
+        confirm,
+    )
+    .unwrap()
+    {
+        ApiResult::Success => log::info!(
+            "event=pending_deposit deposit_id={} user_id={} amount={} confirmed={confirm}",
+            deposit.deposit_id,
+            deposit.user_id,
+            deposit.amount
+        ),
+        ApiResult::Fail(msg) => print_error_line(
+            terminal_io,
+            &format!(
+                "Deposit {} of {}: {msg}",
+                deposit.reference, deposit.username
+            ),
+        ),
+    }
+}
+
+fn import_statement(
+    terminal_io: &mut TerminalIO,
+    credentials: &rv_api::AuthenticationResponse,
+    pending: &[PendingDeposit],
+    path: &str,
+) -> TimeoutResult<()> {
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) => {
+            print_error_line(terminal_io, &format!("Reading {path} failed: {e}"));
+            return TimeoutResult::RESULT(());
+        }
+    };
+    let entries = match bank_statement::parse(&text) {
+        Ok(entries) => entries,
+        Err(e) => {
+            print_error_line(terminal_io, &format!("Invalid bank statement: {e}"));
+            return TimeoutResult::RESULT(());
+        }
+    };
+    let matches = bank_statement::match_pending(pending, &entries);
+    printline(
+        terminal_io,
+        &format!(
+            "{} payments with a reference number, {} match pending deposits",
+            entries.len(),
+            matches.len()
+        ),
+    );
+    for m in matches
+        .iter()
+        .filter(|m| m.kind == MatchKind::AmountMismatch)
+    {
+        print_error_line(
+            terminal_io,
+            &format!(
+                "{} paid {}€ instead of {}€ with {} ({}), not confirmed",
+                m.pending.username,
+                utils::format_money(&m.entry.amount),
+                utils::format_money(&m.pending.amount),
+                m.pending.reference,
+                m.entry.payer
+            ),
+        );
+    }
+    let exact: Vec<_> = matches
+        .iter()
+        .filter(|m| m.kind == MatchKind::Exact)
+        .collect();
+    if exact.is_empty() {
+        return TimeoutResult::RESULT(());
+    }
+    for m in &exact {
+        printline(
+            terminal_io,
+            &format!(
+                "{:<20} {:>9}€ paid {} by {}",
+                m.pending.username,
+                utils::format_money(&m.pending.amount),
+                m.entry.date,
+                m.entry.payer
+            ),
+        );
+    }
+    printline(
+        terminal_io,
+        &format!("Confirm these {} deposits? [yN]", exact.len()),
+    );
+    match utils::confirm_with_default(terminal_io, ConfirmResult::NO).unwrap() {
+        ConfirmResult::YES => (),
+        ConfirmResult::NO => return TimeoutResult::RESULT(()),
+        ConfirmResult::TIMEOUT => return TimeoutResult::TIMEOUT,
+    }
+    for m in exact {
+        resolve(terminal_io, credentials, m.pending, true);
+    }
+    printline(terminal_io, "Deposits confirmed");
+    TimeoutResult::RESULT(())
+}
+
+fn reject_deposit(
+    timeout: Duration,
+    terminal_io: &mut TerminalIO,
+    credentials: &rv_api::AuthenticationResponse,
+    pending: &[PendingDeposit],
+) -> TimeoutResult<()> {
+    execute!(
+        terminal_io.writer,
+        Print("Number of the deposit to reject: ")
+    )
+    .unwrap();
+    let input = match utils::readline(terminal_io, timeout) {
+        TimeoutResult::TIMEOUT => return TimeoutResult::TIMEOUT,
+        TimeoutResult::RESULT(s) => s,
+    };
+    printline(terminal_io, "");
+    let Some(deposit) = input
+        .trim()
+        .parse::<usize>()
+        .ok()
+        .and_then(|number| pending.get(number.checked_sub(1)?))
+    else {
+        if !input.is_empty() {
+            print_error_line(terminal_io, "No such deposit");
+        }
+        return TimeoutResult::RESULT(());
+    };
+    printline(
+        terminal_io,
+        &format!(
+            "Reject the deposit of {}€ by {}? [yN]",
+            utils::format_money(&deposit.amount),
+            deposit.username
+        ),
+    );
+    match utils::confirm_with_default(terminal_io, ConfirmResult::NO).unwrap() {
+        ConfirmResult::YES => resolve(terminal_io, credentials, deposit, false),
+        ConfirmResult::NO => (),
+        ConfirmResult::TIMEOUT => return TimeoutResult::TIMEOUT,
+    }
+    TimeoutResult::RESULT(())
+}
+
+// Bank transfer deposits are credited only after they are found on a bank statement
+pub(super) fn pending_deposits(
+    timeout: Duration,
+    terminal_io: &mut TerminalIO,
+    credentials: &rv_api::AuthenticationResponse,
+) -> TimeoutResult<()> {
+    loop {
+        print_title(terminal_io, "Pending bank transfer deposits");
+        let pending = match rv_api::get_pending_deposits_admin(&terminal_io.config.api, credentials)
+        {
+            Ok(ApiResultValue::Success(pending)) => pending,
+            Ok(ApiResultValue::Fail(msg)) => {
+                print_error_line(terminal_io, &msg);
+                return TimeoutResult::RESULT(());
+            }
+            Err(e) => {
+                print_error_line(terminal_io, &format!("Fetching deposits failed: {e}"));
+                return TimeoutResult::RESULT(());
+            }
+        };
+        if pending.is_empty() {
+            printline(terminal_io, "No pending deposits");
+            return TimeoutResult::RESULT(());
+        }
+        print_pending(terminal_io, &pending);
+        execute!(
+            terminal_io.writer,
+            Print("\r\nPath of a bank statement (CAMT.053 XML or CSV), R to reject a deposit, ENTER to exit: ")
+        )
+        .unwrap();
+        let input = match utils::readline(terminal_io, timeout) {
+            TimeoutResult::TIMEOUT => return TimeoutResult::TIMEOUT,
+            TimeoutResult::RESULT(s) => s.trim().to_string(),
+        };
+        printline(terminal_io, "");
+        let result = match input.as_str() {
+            "" => return TimeoutResult::RESULT(()),
+            "r" | "R" => reject_deposit(timeout, terminal_io, credentials, &pending),
+            path => import_statement(terminal_io, credentials, &pending, path),
+        };
+        if let TimeoutResult::TIMEOUT = result {
+            return TimeoutResult::TIMEOUT;
+        }
+    }
+}
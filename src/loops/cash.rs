@@ -0,0 +1,172 @@
+use crate::reconciliation::{self, Reconciliation, Reconciliations};
+use crate::rv_api::{self, ApiResultValue};
+use crate::utils::{self, print_error_line, print_title, printline, ConfirmResult, TimeoutResult};
+use crate::TerminalIO;
+
+use chrono::{DateTime, Utc};
+use chrono_tz::Europe::Helsinki;
+use crossterm::{execute, style::Print};
+use std::{path::Path, time::Duration};
+
+fn local_time(time: &DateTime<Utc>) -> String {
+    time.with_timezone(&Helsinki)
+        .format("%d/%m/%Y %H:%M")
+        .to_string()
+}
+
+// Compares the cash deposits since the last reconciliation to the counted box
+pub(super) fn cash_reconciliation(
+    timeout: Duration,
+    terminal_io: &mut TerminalIO,
+    credentials: &rv_api::AuthenticationResponse,
+) -> TimeoutResult<()> {
+    let dir = terminal_io.config.store.dir.clone();
+    let mut reconciliations = match Reconciliations::load(&dir) {
+        Ok(reconciliations) => reconciliations,
+        Err(e) => {
+            print_error_line(
+                terminal_io,
+                &format!("Reading the earlier reconciliations failed: {e}"),
+            );
+            return TimeoutResult::RESULT(());
+        }
+    };
+    let since = reconciliations.last().map(|last| last.closed_at);
+    print_title(terminal_io, "Cash box reconciliation");
+    match since {
+        Some(since) => printline(
+            terminal_io,
+            &format!("Cash deposits since {}", local_time(&since)),
+        ),
+        None => printline(terminal_io, "No earlier reconciliations, all cash deposits"),
+    }
+
+    // Deposits made while counting belong to the next period
+    let closed_at = Utc::now();
+    let deposits =
+        match rv_api::get_cash_deposits_admin(&terminal_io.config.api, credentials, since.as_ref())
+        {
+            Ok(ApiResultValue::Success(deposits)) => deposits,
+            Ok(ApiResultValue::Fail(msg)) => {
+                print_error_line(terminal_io, &msg);
+                return TimeoutResult::RESULT(());
+            }
+            Err(e) => {
+                print_error_line(terminal_io, &format!("Fetching deposits failed: {e}"));
+                return TimeoutResult::RESULT(());
+            }
+        };
+    let deposits: Vec<_> = deposits
+        .into_iter()
+        .filter(|deposit| {
+            DateTime::parse_from_rfc3339(&deposit.time).map_or(true, |time| time < closed_at)
+        })
+        .collect();
+    let totals = reconciliation::user_totals(&deposits);
+    for total in &totals {
+        printline(
+            terminal_io,
+            &format!(
+                "{:<20} {:>3} deposits {:>9}€",
+                total.username,
+                total.count,
+                utils::format_money(&total.total)
+            ),
+        );
+    }
+    let expected: i32 = totals.iter().map(|total| total.total).sum();
+    printline(
+        terminal_io,
+        &format!(
+            "\r\n{} deposits, {}€ expected in the box",
+            deposits.len(),
+            utils::format_money(&expected)
+        ),
+    );
+
+    execute!(
+        terminal_io.writer,
+        Print("\r\nCounted cash in the box, ENTER to only view: ")
+    )
+    .unwrap();
+    let input = match utils::readline(terminal_io, timeout) {
+        TimeoutResult::TIMEOUT => return TimeoutResult::TIMEOUT,
+        TimeoutResult::RESULT(s) => s.trim().to_string(),
+    };
+    printline(terminal_io, "");
+    if input.is_empty() {
+        return TimeoutResult::RESULT(());
+    }
+    let Some(counted) = utils::parse_money(&input).and_then(|cents| i32::try_from(cents).ok())
+    else {
+        print_error_line(terminal_io, "Invalid amount");
+        return TimeoutResult::RESULT(());
+    };
+    let difference = counted - expected;
+    let flagged = reconciliation::flagged_users(&totals, difference);
+    match difference {
+        0 => printline(terminal_io, "The box matches the deposits"),
+        d if d > 0 => printline(
+            terminal_io,
+            &format!("The box has {}€ too much", utils::format_money(&d)),
+        ),
+        d => {
+            print_error_line(
+                terminal_io,
+                &format!("The box is {}€ short", utils::format_money(&-d)),
+            );
+            printline(
+                terminal_io,
+                &format!("Flagged users: {}", flagged.join(", ")),
+            );
+        }
+    }
+
+    printline(
+        terminal_io,
+        "\r\nClose the period and export it as CSV? [yN]",
+    );
+    match utils::confirm_with_default(terminal_io, ConfirmResult::NO).unwrap() {
+        ConfirmResult::YES => (),
+        ConfirmResult::NO => return TimeoutResult::RESULT(()),
+        ConfirmResult::TIMEOUT => return TimeoutResult::TIMEOUT,
+    }
+    let reconciliation = Reconciliation {
+        closed_at,
+        closed_by: rv_api::get_user_info(&terminal_io.config.api, credentials)
+            .unwrap()
+            .username,
+        deposit_count: deposits.len(),
+        expected,
+        counted,
+        difference,
+        flagged,
+    };
+    let csv_path = Path::new(&dir).join(format!(
+        "cash_{}.csv",
+        closed_at.with_timezone(&Helsinki).format("%Y%m%d_%H%M")
+    ));
+    if let Err(e) = std::fs::write(
+        &csv_path,
+        reconciliation::to_csv(&deposits, &reconciliation),
+    ) {
+        log::error!("event=cash_csv_save error={:?}", e.to_string());
+        print_error_line(terminal_io, "Writing the CSV failed, period not closed");
+        return TimeoutResult::RESULT(());
+    }
+    log::info!(
+        "event=cash_reconciliation expected={expected} counted={counted} difference={difference} flagged={:?}",
+        reconciliation.flagged
+    );
+    reconciliations.periods.push(reconciliation);
+    if let Err(e) = reconciliations.save(&dir) {
+        log::error!("event=reconciliations_save error={:?}", e.to_string());
+        print_error_line(terminal_io, "Saving the reconciliation failed");
+        return TimeoutResult::RESULT(());
+    }
+    printline(
+        terminal_io,
+        &format!("Period closed, exported to {}", csv_path.display()),
+    );
+    TimeoutResult::RESULT(())
+}
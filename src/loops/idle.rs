@@ -0,0 +1,241 @@
+use crate::config::Config;
+use crate::input::InputEvent;
+use crate::rv_api::{self, ApiResultValue, LeaderboardRow, ProductInfo, TopSellerRow};
+use crate::utils;
+use crate::TerminalIO;
+
+use super::leaderboard::public_name;
+use super::user::COFFEE_MSG;
+use chrono::{DateTime, Utc};
+use chrono_tz::Europe::Helsinki;
+use crossterm::{
+    event::Event,
+    execute, queue,
+    style::{Print, PrintStyledContent, Stylize},
+};
+use std::{fs, sync::mpsc::RecvTimeoutError, time::Instant};
+
+const PANEL_ROWS: usize = 20;
+
+enum Panel<'a> {
+    Leaderboard,
+    NewProducts,
+    TopSellers,
+    Coffee,
+    Announcement(&'a str),
+}
+
+struct IdleData {
+    leaderboard: Vec<LeaderboardRow>,
+    new_products: Vec<ProductInfo>,
+    top_sellers: Vec<TopSellerRow>,
+    announcements: Vec<String>,
+    fetched: Instant,
+}
+
+impl IdleData {
+    fn new() -> Self {
+        Self {
+            leaderboard: Vec::new(),
+            new_products: Vec::new(),
+            top_sellers: Vec::new(),
+            announcements: Vec::new(),
+            fetched: Instant::now(),
+        }
+    }
+
+    // Keeps the previous data of a panel if fetching it fails
+    fn refresh(&mut self, config: &Config) {
+        if let Some(rows) = fetched("leaderboard", rv_api::get_leaderboard(&config.api)) {
+            self.leaderboard = rows;
+        }
+        if let Some(rows) = fetched("new_products", rv_api::get_new_products(&config.api)) {
+            self.new_products = rows;
+        }
+        if let Some(rows) = fetched(
+            "top_sellers",
+            rv_api::get_top_sellers(&config.api, &start_of_today()),
+        ) {
+            self.top_sellers = rows;
+        }
+        self.announcements = fs::read_to_string(&config.idle.announcements)
+            .map(|text| parse_announcements(&text))
+            .unwrap_or_default();
+        self.fetched = Instant::now();
+    }
+
+    // Panels without any data are skipped, the coffee promo is always shown
+    fn panels(&self) -> Vec<Panel<'_>> {
+        let mut panels = Vec::new();
+        if !self.leaderboard.is_empty() {
+            panels.push(Panel::Leaderboard);
+        }
+        if !self.new_products.is_empty() {
+            panels.push(Panel::NewProducts);
+        }
+        if !self.top_sellers.is_empty() {
+            panels.push(Panel::TopSellers);
+        }
+        panels.push(Panel::Coffee);
+        panels.extend(
+            self.announcements
+                .iter()
+                .map(|text| Panel::Announcement(text)),
+        );
+        panels
+    }
+}
+
+fn fetched<T>(
+    panel: &str,
+    result: Result<ApiResultValue<Vec<T>>, reqwest::Error>,
+) -> Option<Vec<T>> {
+    match result {
+        Ok(ApiResultValue::Success(rows)) => Some(rows),
+        Ok(ApiResultValue::Fail(err)) => {
+            log::warn!("event=idle_refresh panel={panel} error={err:?}");
+            None
+        }
+        Err(err) => {
+            log::warn!(
+                "event=idle_refresh panel={panel} error={:?}",
+                err.to_string()
+            );
+            None
+        }
+    }
+}
+
+fn start_of_today() -> DateTime<Utc> {
+    Utc::now()
+        .with_timezone(&Helsinki)
+        .date_naive()
+        .and_hms_opt(0, 0, 0)
+        .unwrap()
+        .and_local_timezone(Helsinki)
+        .earliest()
+        .unwrap_or_else(|| Utc::now().with_timezone(&Helsinki))
+        .with_timezone(&Utc)
+}
+
+fn parse_announcements(text: &str) -> Vec<String> {
+    text.split("\n\n")
+        .map(|paragraph| paragraph.trim())
+        .filter(|paragraph| !paragraph.is_empty())
+        .map(|paragraph| paragraph.replace("\n", "\r\n"))
+        .collect()
+}
+
+fn draw_panel(terminal_io: &mut TerminalIO, data: &IdleData, panel: &Panel) {
+    utils::clear_terminal(terminal_io);
+    match panel {
+        Panel::Leaderboard => {
+            utils::print_title(terminal_io, "Leaderboard");
+            let rows = terminal_io.config.leaderboard.rows;
+            let public = data
+                .leaderboard
+                .iter()
+                .filter_map(|row| Some((public_name(&row.name, row.privacy_level)?, row.saldo)));
+            for (idx, (name, saldo)) in public.take(rows).enumerate() {
+                queue!(
+                    terminal_io.writer,
+                    Print(format!(
+                        "{:>3}. {:<20} | {:>8}\r\n",
+                        idx + 1,
+                        name.chars().take(20).collect::<String>(),
+                        utils::format_money(&saldo)
+                    ))
+                )
+                .unwrap();
+            }
+        }
+        Panel::NewProducts => {
+            utils::print_title(terminal_io, "Newly stocked");
+            for product in data.new_products.iter().take(PANEL_ROWS) {
+                queue!(
+                    terminal_io.writer,
+                    Print(format!(
+                        "{:<40} | {:>6} EUR | {:>4} in stock\r\n",
+                        product.name.chars().take(40).collect::<String>(),
+                        utils::format_money(&product.price),
+                        product.stock
+                    ))
+                )
+                .unwrap();
+            }
+        }
+        Panel::TopSellers => {
+            utils::print_title(terminal_io, "Today's top sellers");
+            for (idx, row) in data.top_sellers.iter().take(PANEL_ROWS).enumerate() {
+                queue!(
+                    terminal_io.writer,
+                    Print(format!(
+                        "{:>3}. {:<40} | {:>4} sold\r\n",
+                        idx + 1,
+                        row.name.chars().take(40).collect::<String>(),
+                        row.count
+                    ))
+                )
+                .unwrap();
+            }
+        }
+        Panel::Coffee => utils::printline(terminal_io, &COFFEE_MSG),
+        Panel::Announcement(text) => {
+            utils::print_title(terminal_io, "Announcement");
+            utils::printline(terminal_io, text);
+        }
+    }
+    execute!(
+        terminal_io.writer,
+        Print("\r\n"),
+        PrintStyledContent("Press any key or show your RFID tag to log in".dark_green()),
+    )
+    .unwrap();
+}
+
+// Cycles the attract screen panels until input arrives, returns the input so
+// that it can be handled by the login prompt. None if the input channel is closed.
+pub fn idle_loop(terminal_io: &mut TerminalIO) -> Option<InputEvent> {
+    log::info!("event=idle_start");
+    let mut data = IdleData::new();
+    data.refresh(&terminal_io.config);
+    let mut panel_index = 0;
+    loop {
+        if data.fetched.elapsed() >= terminal_io.config.idle.refresh_interval() {
+            data.refresh(&terminal_io.config);
+        }
+        let panels = data.panels();
+        draw_panel(terminal_io, &data, &panels[panel_index % panels.len()]);
+
+        match terminal_io
+            .recv
+            .recv_timeout(terminal_io.config.idle.panel_duration())
+        {
+            Ok(
+                event @ (InputEvent::Terminal(Event::Key(_))
+                | InputEvent::Barcode(_)
+                | InputEvent::Rfid(_)),
+            ) => {
+                log::info!("event=idle_end");
+                return Some(event);
+            }
+            // Redraw the same panel on e.g. resize
+            Ok(_) => (),
+            Err(RecvTimeoutError::Timeout) => panel_index += 1,
+            Err(RecvTimeoutError::Disconnected) => return None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn parse_announcements_works() {
+        assert_eq!(
+            parse_announcements("Sauna tonight\nat 18\n\n\n  \nNew fridge!\n"),
+            vec!["Sauna tonight\r\nat 18", "New fridge!"]
+        );
+        assert!(parse_announcements("\n\n").is_empty());
+    }
+}
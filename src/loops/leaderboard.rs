@@ -0,0 +1,252 @@
+use crate::config::ApiConfig;
+use crate::rv_api::{self, ApiResultValue, RankingRow};
+use crate::utils;
+use crate::TerminalIO;
+
+use chrono::{DateTime, Datelike, Local, Utc};
+use chrono_tz::Europe::Helsinki;
+use crossterm::{
+    cursor::{self, RestorePosition, SavePosition},
+    execute, queue,
+    style::{Print, PrintStyledContent, Stylize},
+    terminal,
+};
+
+// The login prompt and the logo are on the left side of the screen
+const LEFT_MARGIN: u16 = 40;
+const TOP: u16 = 3;
+const MIN_NAME_WIDTH: usize = 10;
+const MAX_NAME_WIDTH: usize = 20;
+const VALUE_WIDTH: usize = 8;
+
+#[derive(Clone, Copy)]
+enum Ranking {
+    Saldo,
+    PurchasesThisMonth,
+    CoffeeKing,
+}
+
+const RANKINGS: [Ranking; 3] = [
+    Ranking::Saldo,
+    Ranking::PurchasesThisMonth,
+    Ranking::CoffeeKing,
+];
+
+impl Ranking {
+    fn title(&self) -> &'static str {
+        match self {
+            Ranking::Saldo => "Leaderboard",
+            Ranking::PurchasesThisMonth => "Most purchases this month",
+            Ranking::CoffeeKing => "Coffee king of the month",
+        }
+    }
+
+    fn fetch(&self, api: &ApiConfig) -> Result<Vec<Row>, String> {
+        let rows = match self {
+            Ranking::Saldo => match rv_api::get_leaderboard(api) {
+                Ok(ApiResultValue::Success(rows)) => rows
+                    .into_iter()
+                    .filter_map(|row| {
+                        Some(Row {
+                            name: public_name(&row.name, row.privacy_level)?,
+                            value: utils::format_money(&row.saldo),
+                        })
+                    })
+                    .collect(),
+                Ok(ApiResultValue::Fail(err)) => return Err(err),
+                Err(err) => return Err(err.to_string()),
+            },
+            Ranking::PurchasesThisMonth => {
+                counts(rv_api::get_purchase_ranking(api, &start_of_month()))?
+            }
+            Ranking::CoffeeKing => counts(rv_api::get_coffee_ranking(api, &start_of_month()))?,
+        };
+        Ok(rows)
+    }
+}
+
+struct Row {
+    name: String,
+    value: String,
+}
+
+struct Cached {
+    rows: Vec<Row>,
+    fetched: DateTime<Local>,
+    stale: bool,
+}
+
+struct Layout {
+    column: u16,
+    name_width: usize,
+    rows: usize,
+}
+
+// None if the terminal is too small for the panel
+fn layout(width: u16, height: u16, max_rows: usize) -> Option<Layout> {
+    // "nnn. " + name + " | " + value
+    let name_width = (width.saturating_sub(LEFT_MARGIN) as usize)
+        .saturating_sub(5 + 3 + VALUE_WIDTH)
+        .min(MAX_NAME_WIDTH);
+    let rows = (height.saturating_sub(TOP + 3) as usize).min(max_rows);
+    if name_width < MIN_NAME_WIDTH || rows == 0 {
+        return None;
+    }
+    Some(Layout {
+        column: width - (5 + name_width + 3 + VALUE_WIDTH) as u16,
+        name_width,
+        rows,
+    })
+}
+
+// Level 1 hides the name, level 2 hides the user from public statistics altogether
+pub(super) fn public_name(name: &str, privacy_level: u8) -> Option<String> {
+    match privacy_level {
+        0 => Some(name.to_string()),
+        1 => Some("anonymous".to_string()),
+        _ => None,
+    }
+}
+
+fn counts(
+    result: Result<ApiResultValue<Vec<RankingRow>>, reqwest::Error>,
+) -> Result<Vec<Row>, String> {
+    match result {
+        Ok(ApiResultValue::Success(rows)) => Ok(rows
+            .into_iter()
+            .filter_map(|row| {
+                Some(Row {
+                    name: public_name(&row.name, row.privacy_level)?,
+                    value: row.count.to_string(),
+                })
+            })
+            .collect()),
+        Ok(ApiResultValue::Fail(err)) => Err(err),
+        Err(err) => Err(err.to_string()),
+    }
+}
+
+fn start_of_month() -> DateTime<Utc> {
+    let now = Utc::now().with_timezone(&Helsinki);
+    now.date_naive()
+        .with_day(1)
+        .unwrap()
+        .and_hms_opt(0, 0, 0)
+        .unwrap()
+        .and_local_timezone(Helsinki)
+        .earliest()
+        .unwrap_or(now)
+        .with_timezone(&Utc)
+}
+
+// Statistics panel on the login screen, shows the next ranking on every draw
+pub(super) struct LoginStats {
+    next: usize,
+    cache: [Option<Cached>; RANKINGS.len()],
+}
+
+impl LoginStats {
+    pub(super) fn new() -> Self {
+        Self {
+            next: 0,
+            cache: [None, None, None],
+        }
+    }
+
+    // A failed fetch falls back to the previously fetched rows of the ranking
+    fn refresh(&mut self, api: &ApiConfig, index: usize) -> Option<&Cached> {
+        match RANKINGS[index].fetch(api) {
+            Ok(rows) => {
+                self.cache[index] = Some(Cached {
+                    rows,
+                    fetched: Local::now(),
+                    stale: false,
+                })
+            }
+            Err(err) => {
+                log::warn!(
+                    "event=leaderboard_fetch ranking={:?} error={err:?}",
+                    RANKINGS[index].title()
+                );
+                if let Some(cached) = &mut self.cache[index] {
+                    cached.stale = true;
+                }
+            }
+        }
+        self.cache[index].as_ref()
+    }
+
+    pub(super) fn draw(&mut self, terminal_io: &mut TerminalIO) {
+        let (width, height) = terminal::size().unwrap_or((80, 24));
+        let Some(layout) = layout(width, height, terminal_io.config.leaderboard.rows) else {
+            return;
+        };
+        let index = self.next % RANKINGS.len();
+        self.next += 1;
+        let Some(cached) = self.refresh(&terminal_io.config.api, index) else {
+            return;
+        };
+
+        let title = match cached.stale {
+            true => format!(
+                "{} (offline, {})",
+                RANKINGS[index].title(),
+                cached.fetched.format("%H:%M")
+            ),
+            false => RANKINGS[index].title().to_string(),
+        };
+        execute!(
+            terminal_io.writer,
+            SavePosition,
+            cursor::MoveTo(layout.column, TOP),
+            PrintStyledContent(
+                title
+                    .chars()
+                    .take(5 + layout.name_width + 3 + VALUE_WIDTH)
+                    .collect::<String>()
+                    .dark_green()
+                    .bold()
+            ),
+        )
+        .unwrap();
+        for (idx, row) in cached.rows.iter().take(layout.rows).enumerate() {
+            queue!(
+                terminal_io.writer,
+                cursor::MoveTo(layout.column, TOP + 2 + idx as u16),
+                Print(format!(
+                    "{:>3}. {:<name_width$} | {:>VALUE_WIDTH$}",
+                    idx + 1,
+                    row.name.chars().take(layout.name_width).collect::<String>(),
+                    row.value,
+                    name_width = layout.name_width
+                ))
+            )
+            .unwrap();
+        }
+        execute!(terminal_io.writer, RestorePosition).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn layout_adapts_to_terminal_size() {
+        let wide = layout(120, 50, 20).unwrap();
+        assert_eq!(wide.name_width, MAX_NAME_WIDTH);
+        assert_eq!(wide.rows, 20);
+        assert_eq!(wide.column as usize + 5 + 20 + 3 + VALUE_WIDTH, 120);
+
+        let small = layout(70, 15, 20).unwrap();
+        assert_eq!(small.name_width, 14);
+        assert_eq!(small.rows, 9);
+        assert!(small.column >= LEFT_MARGIN);
+
+        assert!(layout(60, 50, 20).is_none());
+        assert!(layout(120, 6, 20).is_none());
+
+        assert_eq!(public_name("matti", 0), Some("matti".to_string()));
+        assert_eq!(public_name("matti", 1), Some("anonymous".to_string()));
+        assert_eq!(public_name("matti", 2), None);
+    }
+}
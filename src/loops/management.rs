@@ -1,6 +1,14 @@
+use super::bank;
+use super::cash;
+use super::setting::{print_rfid_tags, select_rfid_tag};
 use super::user;
+use super::user_directory;
 
+use crate::barcode::Barcode;
+use crate::batches::{self, Batch, Batches};
 use crate::input;
+use crate::line_editor::{Action, Echo, LineEditor};
+use crate::password_policy;
 use crate::rv_api;
 use crate::rv_api::get_box_info_admin;
 use crate::rv_api::get_product_info;
@@ -9,21 +17,24 @@ use crate::rv_api::ApiResultValue;
 use crate::rv_api::ProductCategory;
 use crate::rv_api::UserInfo;
 use crate::utils;
+use crate::utils::check_barcode;
 use crate::utils::clear_terminal;
 use crate::utils::print_error_line;
 use crate::utils::print_title;
 use crate::utils::printline;
+use crate::utils::read_barcode;
 use crate::utils::readline_barcode;
+use crate::utils::resolve_barcode;
 use crate::utils::TimeoutResult;
 use crate::TerminalIO;
-use crate::INPUT_TIMEOUT_LONG;
 
+use chrono::{Datelike, NaiveDate};
 use crossterm::{
     cursor,
     event::{Event, KeyCode},
     execute, queue,
     style::{Print, PrintStyledContent, Stylize},
-    terminal, ExecutableCommand,
+    terminal,
 };
 use input::InputEvent;
 use regex::Regex;
@@ -31,21 +42,25 @@ use rv_api::ApiResult;
 use std::{sync::mpsc::RecvTimeoutError, time::Duration};
 use user::search_products;
 
+const DATE_FORMAT: &str = "%d.%m.%Y";
+
 fn buy_in_box(
-    barcode: &str,
+    scanned: &Barcode,
     terminal_io: &mut TerminalIO,
     credentials: &rv_api::AuthenticationResponse,
 ) -> TimeoutResult<()> {
-    let box_ = match rv_api::get_box_info_admin(barcode, credentials).unwrap() {
-        Some(b) => b,
-        None => {
-            print_error_line(
-                terminal_io,
-                &format!("Buy in error: No box found with barcode {}", barcode),
-            );
-            return TimeoutResult::RESULT(());
-        }
-    };
+    let barcode = scanned.code.as_str();
+    let box_ =
+        match rv_api::get_box_info_admin(&terminal_io.config.api, barcode, credentials).unwrap() {
+            Some(b) => b,
+            None => {
+                print_error_line(
+                    terminal_io,
+                    &format!("Buy in error: No box found with barcode {}", barcode),
+                );
+                return TimeoutResult::RESULT(());
+            }
+        };
     utils::printline(
         terminal_io,
         &format!(
@@ -74,7 +89,8 @@ fn buy_in_box(
         );
         utils::printline(terminal_io, "At least one number, followed by period, followed by two numbers. For example: '1.00', '0.01', '14.42'");
 
-        let input_line = match utils::readline(terminal_io, INPUT_TIMEOUT_LONG) {
+        let input_line = match utils::readline(terminal_io, terminal_io.config.input.timeout_long())
+        {
             TimeoutResult::TIMEOUT => return TimeoutResult::TIMEOUT,
             TimeoutResult::RESULT(s) => s,
         };
@@ -107,7 +123,7 @@ fn buy_in_box(
     loop {
         utils::printline(terminal_io, "\r\nEnter item sellprice.");
         if buy_price_changed {
-            let margin = rv_api::get_margin(&credentials).unwrap() as f64;
+            let margin = rv_api::get_margin(&terminal_io.config.api, &credentials).unwrap() as f64;
             let margin_pretty = format!("{}%", (margin * 100.0).ceil());
             sell_price = (buy_price as f64 * (1.0 + margin)).ceil() as i32;
             utils::printline(
@@ -123,7 +139,8 @@ fn buy_in_box(
             terminal_io,
             &format!("Modify or keep [{}]: ", &utils::format_money(&sell_price)),
         );
-        let input_line = match utils::readline(terminal_io, INPUT_TIMEOUT_LONG) {
+        let input_line = match utils::readline(terminal_io, terminal_io.config.input.timeout_long())
+        {
             TimeoutResult::TIMEOUT => return TimeoutResult::TIMEOUT,
             TimeoutResult::RESULT(s) => s,
         };
@@ -148,7 +165,8 @@ fn buy_in_box(
     let box_count = loop {
         utils::printline(terminal_io, "Enter how many boxes to add. Format: [0-9]+");
         utils::printline(terminal_io, &format!("Modify or keep [0]: "));
-        let input_line = match utils::readline(terminal_io, INPUT_TIMEOUT_LONG) {
+        let input_line = match utils::readline(terminal_io, terminal_io.config.input.timeout_long())
+        {
             TimeoutResult::TIMEOUT => return TimeoutResult::TIMEOUT,
             TimeoutResult::RESULT(s) => s,
         };
@@ -165,16 +183,44 @@ fn buy_in_box(
         printline(terminal_io, "Added 0 boxes.");
         return TimeoutResult::RESULT(());
     }
+    let best_before = match input_best_before(terminal_io, scanned.best_before) {
+        TimeoutResult::RESULT(date) => date,
+        TimeoutResult::TIMEOUT => return TimeoutResult::TIMEOUT,
+    };
 
-    match rv_api::buy_in_box(barcode, buy_price, sell_price, box_count, credentials).unwrap() {
-        ApiResult::Success => utils::printline(
-            terminal_io,
-            &format!(
-                "Added {} boxes. Total of {} items.",
-                box_count,
-                box_.items_per_box * box_count
-            ),
-        ),
+    match rv_api::buy_in_box(
+        &terminal_io.config.api,
+        barcode,
+        buy_price,
+        sell_price,
+        box_count,
+        credentials,
+    )
+    .unwrap()
+    {
+        ApiResult::Success => {
+            utils::printline(
+                terminal_io,
+                &format!(
+                    "Added {} boxes. Total of {} items.",
+                    box_count,
+                    box_.items_per_box * box_count
+                ),
+            );
+            if let Some(best_before) = best_before {
+                let batch = Batch {
+                    batch: scanned.batch.clone(),
+                    best_before,
+                    count: box_.items_per_box * box_count,
+                };
+                save_batch(
+                    terminal_io,
+                    &box_.product.barcode,
+                    &box_.product.name,
+                    batch,
+                );
+            }
+        }
         ApiResult::Fail(msg) => print_error_line(terminal_io, &msg),
     }
 
@@ -182,12 +228,13 @@ fn buy_in_box(
 }
 
 fn buy_in_product(
-    barcode: &str,
+    scanned: &Barcode,
     terminal_io: &mut TerminalIO,
     credentials: &rv_api::AuthenticationResponse,
 ) -> TimeoutResult<()> {
+    let barcode = scanned.code.as_str();
     let product;
-    match rv_api::get_product_info_admin(&credentials, barcode).unwrap() {
+    match rv_api::get_product_info_admin(&terminal_io.config.api, &credentials, barcode).unwrap() {
         ApiResultValue::Success(suc) => product = suc,
         ApiResultValue::Fail(msg) => {
             utils::print_error_line(terminal_io, &msg);
@@ -207,7 +254,8 @@ fn buy_in_product(
             terminal_io,
             &format!("Modify or keep [{}]: ", &utils::format_money(&buy_price)),
         );
-        let input_line = match utils::readline(terminal_io, INPUT_TIMEOUT_LONG) {
+        let input_line = match utils::readline(terminal_io, terminal_io.config.input.timeout_long())
+        {
             TimeoutResult::TIMEOUT => return TimeoutResult::TIMEOUT,
             TimeoutResult::RESULT(s) => s,
         };
@@ -231,7 +279,7 @@ fn buy_in_product(
     loop {
         utils::printline(terminal_io, "\r\nEnter item sellprice.");
         if buy_price_changed {
-            let margin = rv_api::get_margin(&credentials).unwrap() as f64;
+            let margin = rv_api::get_margin(&terminal_io.config.api, &credentials).unwrap() as f64;
             let margin_pretty = format!("{}%", (margin * 100.0).ceil());
             sell_price = (buy_price as f64 * (1.0 + margin)).ceil() as i32;
             utils::printline(
@@ -252,7 +300,8 @@ fn buy_in_product(
                 &format!("Modify or keep [{}]: ", &utils::format_money(&sell_price)),
             );
         }
-        let input_line = match utils::readline(terminal_io, INPUT_TIMEOUT_LONG) {
+        let input_line = match utils::readline(terminal_io, terminal_io.config.input.timeout_long())
+        {
             TimeoutResult::TIMEOUT => return TimeoutResult::TIMEOUT,
             TimeoutResult::RESULT(s) => s,
         };
@@ -279,7 +328,8 @@ fn buy_in_product(
             terminal_io,
             "How many products to add? Format: [0-9]+ or [0-9]+\\*[0-9]+",
         );
-        let input_line = match utils::readline(terminal_io, INPUT_TIMEOUT_LONG) {
+        let input_line = match utils::readline(terminal_io, terminal_io.config.input.timeout_long())
+        {
             TimeoutResult::TIMEOUT => return TimeoutResult::TIMEOUT,
             TimeoutResult::RESULT(s) => s,
         };
@@ -302,12 +352,143 @@ fn buy_in_product(
         printline(terminal_io, "Added 0 products to stock.");
         return TimeoutResult::RESULT(());
     }
+    let best_before = match input_best_before(terminal_io, scanned.best_before) {
+        TimeoutResult::RESULT(date) => date,
+        TimeoutResult::TIMEOUT => return TimeoutResult::TIMEOUT,
+    };
 
-    rv_api::buy_in_product(barcode, buy_price, sell_price, count, credentials);
+    rv_api::buy_in_product(
+        &terminal_io.config.api,
+        barcode,
+        buy_price,
+        sell_price,
+        count,
+        credentials,
+    );
     utils::printline(terminal_io, &format!("Added {} products to stock.", count));
+    if let Some(best_before) = best_before {
+        let batch = Batch {
+            batch: scanned.batch.clone(),
+            best_before,
+            count,
+        };
+        save_batch(terminal_io, barcode, &product.name, batch);
+    }
     TimeoutResult::RESULT(())
 }
 
+// Date parsed from a GS1 barcode is offered as the default, None if skipped
+fn input_best_before(
+    terminal_io: &mut TerminalIO,
+    default: Option<NaiveDate>,
+) -> TimeoutResult<Option<NaiveDate>> {
+    loop {
+        match default {
+            Some(date) => utils::printline(
+                terminal_io,
+                &format!(
+                    "Enter best-before date. Format: DD.MM.YYYY, '-' to skip\r\nModify or keep [{}]: ",
+                    date.format(DATE_FORMAT)
+                ),
+            ),
+            None => utils::printline(
+                terminal_io,
+                "Enter best-before date. Format: DD.MM.YYYY, Enter to skip",
+            ),
+        }
+        let input_line = match utils::readline(terminal_io, terminal_io.config.input.timeout_long())
+        {
+            TimeoutResult::TIMEOUT => return TimeoutResult::TIMEOUT,
+            TimeoutResult::RESULT(s) => s,
+        };
+        if input_line.is_empty() {
+            return TimeoutResult::RESULT(default);
+        } else if input_line == "-" {
+            return TimeoutResult::RESULT(None);
+        } else if let Ok(date) = NaiveDate::parse_from_str(&input_line, DATE_FORMAT) {
+            // Two digit years would be parsed as the first century
+            if date.year() >= 2000 {
+                return TimeoutResult::RESULT(Some(date));
+            }
+        }
+        print_error_line(terminal_io, "Invalid date entered, please retry!\n");
+    }
+}
+
+fn save_batch(terminal_io: &mut TerminalIO, barcode: &str, name: &str, batch: Batch) {
+    let dir = &terminal_io.config.store.dir;
+    let result = Batches::load(dir).and_then(|mut batches| {
+        batches.add(barcode, name, batch);
+        batches.save(dir)
+    });
+    if let Err(e) = result {
+        print_error_line(
+            terminal_io,
+            &format!("Saving the best-before date failed: {e}"),
+        );
+    }
+}
+
+fn expiring_report(terminal_io: &mut TerminalIO) -> TimeoutResult<()> {
+    print_title(terminal_io, "Expiring soon");
+    let today = batches::today();
+    let until = today + chrono::Days::new(terminal_io.config.batches.expiring_days as u64);
+    let mut batches = match Batches::load(&terminal_io.config.store.dir) {
+        Ok(batches) => batches,
+        Err(e) => {
+            print_error_line(terminal_io, &format!("Reading the batches failed: {e}"));
+            return TimeoutResult::RESULT(());
+        }
+    };
+    let expiring = batches.expiring(until);
+    if expiring.is_empty() {
+        printline(terminal_io, "No tracked batches expiring soon.");
+        return TimeoutResult::RESULT(());
+    }
+    let mut any_expired = false;
+    for (barcode, name, batch) in expiring {
+        let line = format!(
+            "{} | {:<30} | {:>4} pcs | {} {}",
+            batch.best_before.format(DATE_FORMAT),
+            name.chars().take(30).collect::<String>(),
+            batch.count,
+            barcode,
+            batch.batch.as_deref().unwrap_or("")
+        );
+        if batch.best_before < today {
+            any_expired = true;
+            execute!(
+                terminal_io.writer,
+                PrintStyledContent(line.red()),
+                Print("\r\n")
+            )
+            .unwrap();
+        } else {
+            printline(terminal_io, &line);
+        }
+    }
+    if !any_expired {
+        return TimeoutResult::RESULT(());
+    }
+
+    printline(
+        terminal_io,
+        "\r\nRemove the expired (red) batches after throwing them away? [yN]",
+    );
+    match utils::confirm(terminal_io).unwrap() {
+        utils::ConfirmResult::YES => {
+            let removed = batches.remove_expired(today);
+            match batches.save(&terminal_io.config.store.dir) {
+                Ok(_) => printline(terminal_io, &format!("Removed {removed} expired items.")),
+                Err(e) => print_error_line(terminal_io, &format!("Saving failed: {e}")),
+            }
+            TimeoutResult::RESULT(())
+        }
+        utils::ConfirmResult::NO => TimeoutResult::RESULT(()),
+        utils::ConfirmResult::TIMEOUT => TimeoutResult::TIMEOUT,
+    }
+}
+
 fn new_product(
     barcode: &str,
     terminal_io: &mut TerminalIO,
@@ -320,7 +501,7 @@ fn new_product(
     );
     utils::printline(terminal_io, &format!("Enter product name: "));
 
-    let input_line = match utils::readline(terminal_io, INPUT_TIMEOUT_LONG) {
+    let input_line = match utils::readline(terminal_io, terminal_io.config.input.timeout_long()) {
         TimeoutResult::TIMEOUT => return TimeoutResult::TIMEOUT,
         TimeoutResult::RESULT(s) => s,
     };
@@ -339,7 +520,8 @@ fn new_product(
             "Enter item buyprice. Format: [0-9]+\\.[0-9][0-9]",
         );
         utils::printline(terminal_io, "At least one number, followed by period, followed by two numbers. For example: '1.00', '0.01', '14.42'");
-        let input_line = match utils::readline(terminal_io, INPUT_TIMEOUT_LONG) {
+        let input_line = match utils::readline(terminal_io, terminal_io.config.input.timeout_long())
+        {
             TimeoutResult::TIMEOUT => return TimeoutResult::TIMEOUT,
             TimeoutResult::RESULT(s) => s,
         };
@@ -363,7 +545,7 @@ fn new_product(
             "\r\nEnter item sellprice. Format: [0-9]+\\.[0-9][0-9]",
         );
         utils::printline(terminal_io, "At least one number, followed by period, followed by two numbers. For example: '1.00', '0.01', '14.42'");
-        let margin = rv_api::get_margin(&credentials).unwrap() as f64;
+        let margin = rv_api::get_margin(&terminal_io.config.api, &credentials).unwrap() as f64;
         let margin_pretty = format!("{}%", (margin * 100.0).ceil());
         let suggested_price = (buy_price as f64 * (1.0 + margin)).ceil() as i32;
         utils::printline(
@@ -381,7 +563,8 @@ fn new_product(
                 &utils::format_money(&suggested_price)
             ),
         );
-        let input_line = match utils::readline(terminal_io, INPUT_TIMEOUT_LONG) {
+        let input_line = match utils::readline(terminal_io, terminal_io.config.input.timeout_long())
+        {
             TimeoutResult::TIMEOUT => return TimeoutResult::TIMEOUT,
             TimeoutResult::RESULT(s) => s,
         };
@@ -405,7 +588,8 @@ fn new_product(
             "Enter item stock. Format: [0-9]+ or [0.9]+\\*[0.9]+",
         );
         utils::printline(terminal_io, &format!("Modify or keep [{suggested_stock}]"));
-        let input_line = match utils::readline(terminal_io, INPUT_TIMEOUT_LONG) {
+        let input_line = match utils::readline(terminal_io, terminal_io.config.input.timeout_long())
+        {
             TimeoutResult::TIMEOUT => return TimeoutResult::TIMEOUT,
             TimeoutResult::RESULT(s) => s,
         };
@@ -427,14 +611,15 @@ fn new_product(
     let category = loop {
         utils::printline(terminal_io, "Enter product category id.");
         utils::printline(terminal_io, "Categories available: ");
-        let categories = rv_api::get_categories(&credentials).unwrap();
+        let categories = rv_api::get_categories(&terminal_io.config.api, &credentials).unwrap();
         for category in categories.iter() {
             utils::printline(
                 terminal_io,
                 &format!("{}, id: {}", category.description, category.category_id),
             );
         }
-        let input_line = match utils::readline(terminal_io, INPUT_TIMEOUT_LONG) {
+        let input_line = match utils::readline(terminal_io, terminal_io.config.input.timeout_long())
+        {
             TimeoutResult::TIMEOUT => return TimeoutResult::TIMEOUT,
             TimeoutResult::RESULT(s) => s,
         };
@@ -460,6 +645,7 @@ fn new_product(
         }
     };
     match rv_api::add_product(
+        &terminal_io.config.api,
         barcode,
         &name,
         category.category_id,
@@ -477,14 +663,16 @@ fn new_product(
 }
 
 fn new_box(
-    barcode: &str,
+    scanned: &Barcode,
     terminal_io: &mut TerminalIO,
     credentials: &rv_api::AuthenticationResponse,
 ) -> TimeoutResult<()> {
+    let barcode = scanned.code.as_str();
     utils::printline(terminal_io, "Creating a new box.");
     let product_barcode = loop {
         utils::printline(terminal_io, "Enter product barcode.");
-        let input_line = match utils::readline(terminal_io, INPUT_TIMEOUT_LONG) {
+        let input_line = match utils::readline(terminal_io, terminal_io.config.input.timeout_long())
+        {
             TimeoutResult::TIMEOUT => return TimeoutResult::TIMEOUT,
             TimeoutResult::RESULT(s) => s,
         };
@@ -493,7 +681,7 @@ fn new_box(
             return TimeoutResult::RESULT(());
         } else if Regex::new("^[0-9]+$").expect("").is_match(&input_line) {
             let product_barcode = input_line;
-            match rv_api::get_product_info(credentials, &product_barcode) {
+            match rv_api::get_product_info(&terminal_io.config.api, credentials, &product_barcode) {
                 Some(product_info) => {
                     utils::printline(
                         terminal_io,
@@ -514,7 +702,9 @@ fn new_box(
                     {
                         return TimeoutResult::TIMEOUT;
                     }
-                    if get_product_info(credentials, &product_barcode).is_none() {
+                    if get_product_info(&terminal_io.config.api, credentials, &product_barcode)
+                        .is_none()
+                    {
                         print_error_line(terminal_io, "Adding new product failed!");
                         return TimeoutResult::RESULT(());
                     }
@@ -533,7 +723,8 @@ fn new_box(
             terminal_io,
             "Enter number of products in a box. Format: [0-9]+",
         );
-        let input_line = match utils::readline(terminal_io, INPUT_TIMEOUT_LONG) {
+        let input_line = match utils::readline(terminal_io, terminal_io.config.input.timeout_long())
+        {
             TimeoutResult::TIMEOUT => return TimeoutResult::TIMEOUT,
             TimeoutResult::RESULT(s) => s,
         };
@@ -546,11 +737,19 @@ fn new_box(
     }
     printline(terminal_io, "");
 
-    match rv_api::add_box(barcode, &product_barcode, items_per_box, credentials).unwrap() {
+    match rv_api::add_box(
+        &terminal_io.config.api,
+        barcode,
+        &product_barcode,
+        items_per_box,
+        credentials,
+    )
+    .unwrap()
+    {
         ApiResult::Success => {
             utils::printline(terminal_io, &format!("Box added."));
             utils::printline(terminal_io, &format!(""));
-            return buy_in_box(barcode, terminal_io, credentials);
+            return buy_in_box(scanned, terminal_io, credentials);
         }
         ApiResult::Fail(msg) => print_error_line(terminal_io, &msg),
     }
@@ -558,16 +757,20 @@ fn new_box(
 }
 
 fn new_item(
-    barcode: &str,
+    scanned: &Barcode,
     terminal_io: &mut TerminalIO,
     credentials: &rv_api::AuthenticationResponse,
 ) -> TimeoutResult<()> {
+    let barcode = scanned.code.as_str();
     printline(
         terminal_io,
         &format!("Add a new box or product? [bp] or Enter to cancel."),
     );
     loop {
-        match terminal_io.recv.recv_timeout(INPUT_TIMEOUT_LONG) {
+        match terminal_io
+            .recv
+            .recv_timeout(terminal_io.config.input.timeout_long())
+        {
             Err(RecvTimeoutError::Timeout) => return TimeoutResult::TIMEOUT,
             Ok(input::InputEvent::Terminal(Event::Key(ev))) => match ev.code {
                 KeyCode::Enter => {
@@ -577,7 +780,7 @@ fn new_item(
                 KeyCode::Char(c) => match c.to_ascii_lowercase() {
                     'b' => {
                         utils::printline(terminal_io, "");
-                        return new_box(barcode, terminal_io, credentials);
+                        return new_box(scanned, terminal_io, credentials);
                     }
                     'p' => {
                         utils::printline(terminal_io, "");
@@ -598,32 +801,35 @@ fn change_item_properties(
 ) -> TimeoutResult<()> {
     print_title(terminal_io, "Change item properties");
     utils::printline(terminal_io, "Enter barcode: ");
-    let barcode = match readline_barcode(terminal_io, INPUT_TIMEOUT_LONG) {
-        TimeoutResult::RESULT(s) => {
-            if Regex::new("^[0-9]+$").unwrap().is_match(&s) {
-                s
-            } else {
-                print_error_line(terminal_io, "invalid barcode!");
-                std::thread::sleep(std::time::Duration::from_millis(2000));
-                return TimeoutResult::RESULT(());
-            }
+    let barcode = match read_barcode(terminal_io, terminal_io.config.input.timeout_long()) {
+        TimeoutResult::RESULT(Some(barcode)) => {
+            resolve_barcode(terminal_io, credentials, barcode, true).code
+        }
+        TimeoutResult::RESULT(None) => {
+            std::thread::sleep(std::time::Duration::from_millis(2000));
+            return TimeoutResult::RESULT(());
         }
         TimeoutResult::TIMEOUT => return TimeoutResult::TIMEOUT,
     };
 
-    let product = match rv_api::get_product_info_admin(&credentials, &barcode).unwrap() {
-        ApiResultValue::Success(product) => Some(product),
-        ApiResultValue::Fail(msg) => {
-            if !msg.contains("Product not found") {
-                utils::print_error_line(terminal_io, &msg);
+    let product =
+        match rv_api::get_product_info_admin(&terminal_io.config.api, &credentials, &barcode)
+            .unwrap()
+        {
+            ApiResultValue::Success(product) => Some(product),
+            ApiResultValue::Fail(msg) => {
+                if !msg.contains("Product not found") {
+                    utils::print_error_line(terminal_io, &msg);
+                }
+                None
             }
-            None
-        }
-    };
+        };
     if product.is_some() {
         return change_product_properties(&barcode, terminal_io, credentials);
     }
-    if let Some(b) = rv_api::get_box_info_admin(&barcode, credentials).unwrap() {
+    if let Some(b) =
+        rv_api::get_box_info_admin(&terminal_io.config.api, &barcode, credentials).unwrap()
+    {
         return change_box_properties(b.box_barcode, terminal_io, credentials);
     }
     utils::print_error_line(terminal_io, "No matching box or product found!");
@@ -635,7 +841,13 @@ fn change_box_properties(
     terminal_io: &mut TerminalIO,
     credentials: &rv_api::AuthenticationResponse,
 ) -> TimeoutResult<()> {
-    let box_result = match rv_api::get_box_info_admin(&barcode, &credentials).unwrap() {
+    let box_result = match rv_api::get_box_info_admin(
+        &terminal_io.config.api,
+        &barcode,
+        &credentials,
+    )
+    .unwrap()
+    {
         Some(b) => b,
         None => {
             print_error_line(terminal_io, &format!("No box found with {barcode}"));
@@ -651,14 +863,20 @@ fn change_box_properties(
         terminal_io,
         &format!("Modify or keep [{product_barcode}]: "),
     );
-    let input_line = match readline_barcode(terminal_io, INPUT_TIMEOUT_LONG) {
+    let input_line = match readline_barcode(terminal_io, terminal_io.config.input.timeout_long()) {
         TimeoutResult::TIMEOUT => return TimeoutResult::TIMEOUT,
         TimeoutResult::RESULT(s) => s,
     };
     if input_line.len() > 0 {
-        match get_product_info(credentials, &input_line) {
+        let input_line = match check_barcode(terminal_io, &input_line, false) {
+            Some(barcode) => resolve_barcode(terminal_io, credentials, barcode, true).code,
+            None => return TimeoutResult::RESULT(()),
+        };
+        match get_product_info(&terminal_io.config.api, credentials, &input_line) {
             Some(_) => product_barcode = input_line,
-            None => match get_box_info_admin(&input_line, credentials).unwrap() {
+            None => match get_box_info_admin(&terminal_io.config.api, &input_line, credentials)
+                .unwrap()
+            {
                 Some(_) => {
                     print_error_line(terminal_io, "Box with the given barcode already exists!");
                     return TimeoutResult::RESULT(());
@@ -669,7 +887,8 @@ fn change_box_properties(
                     {
                         return TimeoutResult::TIMEOUT;
                     }
-                    if get_product_info(credentials, &input_line).is_none() {
+                    if get_product_info(&terminal_io.config.api, credentials, &input_line).is_none()
+                    {
                         print_error_line(terminal_io, "Adding new item failed!");
                         return TimeoutResult::RESULT(());
                     }
@@ -691,7 +910,8 @@ fn change_box_properties(
             terminal_io,
             &format!("Modify or keep [{items_per_box}] Format: [0-9]+: "),
         );
-        let input_line = match utils::readline(terminal_io, INPUT_TIMEOUT_LONG) {
+        let input_line = match utils::readline(terminal_io, terminal_io.config.input.timeout_long())
+        {
             TimeoutResult::TIMEOUT => return TimeoutResult::TIMEOUT,
             TimeoutResult::RESULT(s) => s,
         };
@@ -707,7 +927,13 @@ fn change_box_properties(
             }
         }
     }
-    match update_box(&barcode, items_per_box, &product_barcode, credentials) {
+    match update_box(
+        &terminal_io.config.api,
+        &barcode,
+        items_per_box,
+        &product_barcode,
+        credentials,
+    ) {
         ApiResult::Success => printline(terminal_io, "Box modified successfully."),
         ApiResult::Fail(msg) => {
             print_error_line(terminal_io, &format!("Modifying box failed: {msg}"))
@@ -723,25 +949,31 @@ fn change_product_properties(
     terminal_io: &mut TerminalIO,
     credentials: &rv_api::AuthenticationResponse,
 ) -> TimeoutResult<()> {
-    let product = match rv_api::get_product_info_admin(&credentials, &barcode).unwrap() {
-        ApiResultValue::Success(product) => product,
-        ApiResultValue::Fail(msg) => {
-            utils::print_error_line(terminal_io, &msg);
-            return TimeoutResult::RESULT(());
-        }
-    };
+    let product =
+        match rv_api::get_product_info_admin(&terminal_io.config.api, &credentials, &barcode)
+            .unwrap()
+        {
+            ApiResultValue::Success(product) => product,
+            ApiResultValue::Fail(msg) => {
+                utils::print_error_line(terminal_io, &msg);
+                return TimeoutResult::RESULT(());
+            }
+        };
 
     let mut barcode = product.barcode;
     utils::printline(terminal_io, &format!("Current description: '{barcode}'"));
     utils::printline(terminal_io, &format!("Modify or keep [{barcode}]: "));
 
-    let input_line = match utils::readline_barcode(terminal_io, INPUT_TIMEOUT_LONG) {
-        TimeoutResult::TIMEOUT => return TimeoutResult::TIMEOUT,
-        TimeoutResult::RESULT(s) => s,
-    };
+    let input_line =
+        match utils::readline_barcode(terminal_io, terminal_io.config.input.timeout_long()) {
+            TimeoutResult::TIMEOUT => return TimeoutResult::TIMEOUT,
+            TimeoutResult::RESULT(s) => s,
+        };
 
-    if input_line.len() > 0 && Regex::new("^[0-9]+").expect("").is_match(&input_line) {
-        barcode = input_line.to_string();
+    if input_line.len() > 0 {
+        if let Some(new_barcode) = check_barcode(terminal_io, &input_line, false) {
+            barcode = new_barcode.code;
+        }
     } else {
         printline(terminal_io, "Nothing changed.");
     }
@@ -750,7 +982,7 @@ fn change_product_properties(
     utils::printline(terminal_io, &format!("Current description: '{name}'"));
     utils::printline(terminal_io, &format!("Modify or keep [{name}]: "));
 
-    let input_line = match utils::readline(terminal_io, INPUT_TIMEOUT_LONG) {
+    let input_line = match utils::readline(terminal_io, terminal_io.config.input.timeout_long()) {
         TimeoutResult::TIMEOUT => return TimeoutResult::TIMEOUT,
         TimeoutResult::RESULT(s) => s,
     };
@@ -774,7 +1006,8 @@ fn change_product_properties(
             terminal_io,
             &format!("Modify or keep [{}]: ", &utils::format_money(&buy_price)),
         );
-        let input_line = match utils::readline(terminal_io, INPUT_TIMEOUT_LONG) {
+        let input_line = match utils::readline(terminal_io, terminal_io.config.input.timeout_long())
+        {
             TimeoutResult::TIMEOUT => return TimeoutResult::TIMEOUT,
             TimeoutResult::RESULT(s) => s,
         };
@@ -798,7 +1031,7 @@ fn change_product_properties(
     loop {
         utils::printline(terminal_io, "\r\nPlease enter item sellprice.");
         if buy_price_changed {
-            let margin = rv_api::get_margin(&credentials).unwrap() as f64;
+            let margin = rv_api::get_margin(&terminal_io.config.api, &credentials).unwrap() as f64;
             let margin_pretty = format!("{}%", (margin * 100.0).ceil());
             sell_price = (buy_price as f64 * (1.0 + margin)).ceil() as i32;
             utils::printline(
@@ -814,7 +1047,8 @@ fn change_product_properties(
             terminal_io,
             &format!("Modify or keep [{}]: ", &utils::format_money(&sell_price)),
         );
-        let input_line = match utils::readline(terminal_io, INPUT_TIMEOUT_LONG) {
+        let input_line = match utils::readline(terminal_io, terminal_io.config.input.timeout_long())
+        {
             TimeoutResult::TIMEOUT => return TimeoutResult::TIMEOUT,
             TimeoutResult::RESULT(s) => s,
         };
@@ -841,7 +1075,8 @@ fn change_product_properties(
         utils::printline(terminal_io, "Please enter item stock. Format: (+|-)?[0-9]+");
         utils::printline(terminal_io, "No prefix or - means to set stock to negative or positive number given. + prefix means to increment stock e.g. +5 when stock is 2 results in stock of 7.");
         utils::printline(terminal_io, &format!("Modify or keep [{stock}]: "));
-        let input_line = match utils::readline(terminal_io, INPUT_TIMEOUT_LONG) {
+        let input_line = match utils::readline(terminal_io, terminal_io.config.input.timeout_long())
+        {
             TimeoutResult::TIMEOUT => return TimeoutResult::TIMEOUT,
             TimeoutResult::RESULT(s) => s,
         };
@@ -868,7 +1103,7 @@ fn change_product_properties(
     loop {
         utils::printline(terminal_io, "Please enter product category id.");
         utils::printline(terminal_io, "Categories available: ");
-        let categories = rv_api::get_categories(&credentials).unwrap();
+        let categories = rv_api::get_categories(&terminal_io.config.api, &credentials).unwrap();
         for category in categories.iter() {
             utils::printline(
                 terminal_io,
@@ -879,7 +1114,8 @@ fn change_product_properties(
             terminal_io,
             &format!("Modify or keep [{}]: ", category.description),
         );
-        let input_line = match utils::readline(terminal_io, INPUT_TIMEOUT_LONG) {
+        let input_line = match utils::readline(terminal_io, terminal_io.config.input.timeout_long())
+        {
             TimeoutResult::TIMEOUT => return TimeoutResult::TIMEOUT,
             TimeoutResult::RESULT(s) => s,
         };
@@ -906,6 +1142,7 @@ fn change_product_properties(
         }
     }
     rv_api::update_product(
+        &terminal_io.config.api,
         &barcode,
         &name,
         category.category_id,
@@ -932,18 +1169,29 @@ fn change_user_password_admin(
         TimeoutResult::RESULT(s) => username = s,
     }
 
-    let user = match rv_api::get_user_info_by_username(credentials, &username).unwrap() {
-        ApiResultValue::Fail(msg) => {
-            print_error_line(terminal_io, &msg);
-            utils::printline(terminal_io, "");
-            return TimeoutResult::RESULT(());
-        }
-        ApiResultValue::Success(user) => user,
-    };
+    let user =
+        match rv_api::get_user_info_by_username(&terminal_io.config.api, credentials, &username)
+            .unwrap()
+        {
+            ApiResultValue::Fail(msg) => {
+                print_error_line(terminal_io, &msg);
+                utils::printline(terminal_io, "");
+                return TimeoutResult::RESULT(());
+            }
+            ApiResultValue::Success(user) => user,
+        };
+    set_user_password_admin(timeout, terminal_io, credentials, &user)
+}
 
+pub(super) fn set_user_password_admin(
+    timeout: Duration,
+    terminal_io: &mut TerminalIO,
+    credentials: &rv_api::AuthenticationResponse,
+    user: &UserInfo,
+) -> TimeoutResult<()> {
     execute!(terminal_io.writer, Print("Enter new password: ")).unwrap();
     let password1;
-    match utils::readpasswd(terminal_io, timeout) {
+    match utils::read_new_password(terminal_io, timeout, &user.username) {
         TimeoutResult::TIMEOUT => return TimeoutResult::TIMEOUT,
         TimeoutResult::RESULT(s) => password1 = s,
     }
@@ -962,8 +1210,17 @@ fn change_user_password_admin(
             terminal_io,
             "Empty password is not allowed! Password not changed.",
         );
+    } else if let Err(msg) = password_policy::check(&password1, &user.username) {
+        utils::print_error_line(terminal_io, &format!("{msg}. Password not changed."));
     } else if password1 == password2 {
-        match rv_api::change_password_admin(credentials, user.user_id, &password1).unwrap() {
+        match rv_api::change_password_admin(
+            &terminal_io.config.api,
+            credentials,
+            user.user_id,
+            &password1,
+        )
+        .unwrap()
+        {
             rv_api::ApiResult::Success => {
                 utils::printline(terminal_io, "Password successfully changed.");
             }
@@ -993,16 +1250,28 @@ fn generate_temp_password_admin(
         TimeoutResult::RESULT(s) => username = s,
     }
 
-    let user = match rv_api::get_user_info_by_username(credentials, &username).unwrap() {
-        ApiResultValue::Fail(msg) => {
-            print_error_line(terminal_io, &msg);
-            utils::printline(terminal_io, "");
-            return TimeoutResult::RESULT(());
-        }
-        ApiResultValue::Success(user) => user,
-    };
+    let user =
+        match rv_api::get_user_info_by_username(&terminal_io.config.api, credentials, &username)
+            .unwrap()
+        {
+            ApiResultValue::Fail(msg) => {
+                print_error_line(terminal_io, &msg);
+                utils::printline(terminal_io, "");
+                return TimeoutResult::RESULT(());
+            }
+            ApiResultValue::Success(user) => user,
+        };
+    send_temp_password_admin(terminal_io, credentials, &user)
+}
 
-    match rv_api::generate_temp_password(credentials, user.user_id).unwrap() {
+pub(super) fn send_temp_password_admin(
+    terminal_io: &mut TerminalIO,
+    credentials: &rv_api::AuthenticationResponse,
+    user: &UserInfo,
+) -> TimeoutResult<()> {
+    match rv_api::generate_temp_password(&terminal_io.config.api, credentials, user.user_id)
+        .unwrap()
+    {
         rv_api::ApiResult::Success => {
             utils::printline(
                 terminal_io,
@@ -1020,31 +1289,23 @@ fn generate_temp_password_admin(
     TimeoutResult::RESULT(())
 }
 
-fn search_for_user(
+fn revoke_rfid_tag_admin(
     timeout: Duration,
     terminal_io: &mut TerminalIO,
     credentials: &rv_api::AuthenticationResponse,
 ) -> TimeoutResult<()> {
-    print_title(
-        terminal_io,
-        "Search for user whit email or user's real name",
-    );
-    execute!(
-        terminal_io.writer,
-        Print("Enter email or user's real name: ")
-    )
-    .unwrap();
-
-    let input = match utils::readline(terminal_io, timeout) {
+    print_title(terminal_io, "Revoke an RFID tag of an user");
+    execute!(terminal_io.writer, Print("Enter username: ")).unwrap();
+    let username = match utils::readline(terminal_io, timeout) {
         TimeoutResult::TIMEOUT => return TimeoutResult::TIMEOUT,
         TimeoutResult::RESULT(s) => s,
     };
+    utils::printline(terminal_io, "");
 
-    let mut users: Vec<UserInfo> = Vec::new();
-    if input.is_empty() {
-        return TimeoutResult::RESULT(());
-    } else if input.split("@").count() == 2 {
-        let user = match rv_api::get_user_info_by_email(credentials, &input).unwrap() {
+    let user =
+        match rv_api::get_user_info_by_username(&terminal_io.config.api, credentials, &username)
+            .unwrap()
+        {
             ApiResultValue::Fail(msg) => {
                 print_error_line(terminal_io, &msg);
                 utils::printline(terminal_io, "");
@@ -1052,29 +1313,50 @@ fn search_for_user(
             }
             ApiResultValue::Success(user) => user,
         };
-        users.push(user);
-    } else {
-        let user = match rv_api::get_user_info_by_full_name(credentials, &input).unwrap() {
+    let tags =
+        match rv_api::list_rfid_tags_admin(&terminal_io.config.api, credentials, user.user_id)
+            .unwrap()
+        {
             ApiResultValue::Fail(msg) => {
                 print_error_line(terminal_io, &msg);
                 utils::printline(terminal_io, "");
                 return TimeoutResult::RESULT(());
             }
-            ApiResultValue::Success(user) => user,
+            ApiResultValue::Success(tags) => tags,
         };
-        users.push(user);
-    }
-    utils::printline(terminal_io, "");
-    utils::print_title(terminal_io, "Found users");
-    for user in users {
-        utils::printline(
-            terminal_io,
-            &format!(
-                "username: {} email: {} full name: {}",
-                user.username, user.email, user.full_name
-            ),
-        );
+    print_rfid_tags(terminal_io, &tags);
+    if !tags.is_empty() {
+        let tag = match select_rfid_tag(timeout, terminal_io, &tags) {
+            TimeoutResult::TIMEOUT => return TimeoutResult::TIMEOUT,
+            TimeoutResult::RESULT(tag) => tag,
+        };
+        if let Some(tag) = tag {
+            match rv_api::revoke_rfid_tag_admin(
+                &terminal_io.config.api,
+                credentials,
+                user.user_id,
+                tag.tag_id,
+            )
+            .unwrap()
+            {
+                ApiResult::Success => {
+                    log::info!(
+                        "event=rfid_revoke_admin user_id={} tag_id={}",
+                        user.user_id,
+                        tag.tag_id
+                    );
+                    utils::printline(
+                        terminal_io,
+                        &format!("Revoked {} of {}", tag.label, user.username),
+                    );
+                }
+                ApiResult::Fail(msg) => {
+                    print_error_line(terminal_io, &format!("Revoking the tag failed: {msg}"));
+                }
+            }
+        }
     }
+
     utils::printline(terminal_io, "");
     utils::confirm_enter_to_continue(terminal_io);
     utils::printline(terminal_io, "");
@@ -1082,18 +1364,21 @@ fn search_for_user(
 }
 
 fn process_barcode_admin(
-    barcode: &str,
+    scanned: &Barcode,
     terminal_io: &mut TerminalIO,
     credentials: &rv_api::AuthenticationResponse,
 ) -> TimeoutResult<()> {
-    if let Some(_) = rv_api::get_product_info(credentials, barcode) {
-        match buy_in_product(barcode, terminal_io, credentials) {
+    let barcode = scanned.code.as_str();
+    if let Some(_) = rv_api::get_product_info(&terminal_io.config.api, credentials, barcode) {
+        match buy_in_product(scanned, terminal_io, credentials) {
             TimeoutResult::RESULT(_) => return TimeoutResult::RESULT(()),
             TimeoutResult::TIMEOUT => return TimeoutResult::TIMEOUT,
         }
     }
-    if let Some(_) = rv_api::get_box_info_admin(barcode, credentials).unwrap() {
-        match buy_in_box(barcode, terminal_io, credentials) {
+    if let Some(_) =
+        rv_api::get_box_info_admin(&terminal_io.config.api, barcode, credentials).unwrap()
+    {
+        match buy_in_box(scanned, terminal_io, credentials) {
             TimeoutResult::RESULT(_) => return TimeoutResult::RESULT(()),
             TimeoutResult::TIMEOUT => return TimeoutResult::TIMEOUT,
         }
@@ -1102,7 +1387,7 @@ fn process_barcode_admin(
         terminal_io,
         &format!("No box or product found with barcode {barcode}"),
     );
-    new_item(barcode, terminal_io, credentials);
+    new_item(scanned, terminal_io, credentials);
     return TimeoutResult::RESULT(());
 }
 
@@ -1118,11 +1403,19 @@ fn print_management_loop_instructions(terminal_io: &mut TerminalIO) {
         PrintStyledContent("I".dark_green().bold()),
         Print(" - update all item/box properties\r\n"),
         PrintStyledContent("S".dark_green().bold()),
-        Print(" - search for an username\r\n"),
+        Print(" - search users and open their accounts\r\n"),
         PrintStyledContent("P".dark_green().bold()),
         Print(" - change password of an user\r\n"),
         PrintStyledContent("E".dark_green().bold()),
         Print(" - generate temppasword and send it to user\r\n"),
+        PrintStyledContent("R".dark_green().bold()),
+        Print(" - revoke an RFID tag of an user\r\n"),
+        PrintStyledContent("D".dark_green().bold()),
+        Print(" - pending bank transfer deposits and bank statement import\r\n"),
+        PrintStyledContent("K".dark_green().bold()),
+        Print(" - cash box reconciliation\r\n"),
+        PrintStyledContent("X".dark_green().bold()),
+        Print(" - list products expiring soon\r\n"),
         PrintStyledContent("C".dark_green().bold()),
         Print(" - clear terminal\r\n"),
         PrintStyledContent("<enter>".dark_green().bold()),
@@ -1144,15 +1437,71 @@ fn print_management_loop_banner(terminal_io: &mut TerminalIO, logo: bool) {
     printline(terminal_io, "");
 }
 
+// Admins who have enrolled TOTP need a valid code to enter management mode. The
+// code is checked by the backend, management mode is refused if it cannot be.
+fn verify_totp(
+    terminal_io: &mut TerminalIO,
+    credentials: &rv_api::AuthenticationResponse,
+    user_id: i32,
+) -> TimeoutResult<bool> {
+    match rv_api::get_totp_enabled(&terminal_io.config.api, credentials) {
+        Ok(ApiResultValue::Success(true)) => (),
+        Ok(ApiResultValue::Success(false)) => return TimeoutResult::RESULT(true),
+        Ok(ApiResultValue::Fail(msg)) => {
+            print_error_line(
+                terminal_io,
+                &format!("Checking two-factor authentication failed: {msg}"),
+            );
+            return TimeoutResult::RESULT(false);
+        }
+        Err(e) => {
+            print_error_line(
+                terminal_io,
+                &format!("Checking two-factor authentication failed: {e}"),
+            );
+            return TimeoutResult::RESULT(false);
+        }
+    }
+    execute!(terminal_io.writer, Print("Enter your authenticator code: ")).unwrap();
+    let code = match utils::readline(terminal_io, terminal_io.config.input.timeout_short()) {
+        TimeoutResult::TIMEOUT => return TimeoutResult::TIMEOUT,
+        TimeoutResult::RESULT(s) => s,
+    };
+    printline(terminal_io, "");
+    match rv_api::verify_totp(&terminal_io.config.api, credentials, code.trim()) {
+        Ok(ApiResult::Success) => TimeoutResult::RESULT(true),
+        Ok(ApiResult::Fail(msg)) => {
+            log::warn!("event=totp_failed user_id={user_id}");
+            print_error_line(terminal_io, &msg);
+            TimeoutResult::RESULT(false)
+        }
+        Err(e) => {
+            print_error_line(
+                terminal_io,
+                &format!("Checking two-factor authentication failed: {e}"),
+            );
+            TimeoutResult::RESULT(false)
+        }
+    }
+}
+
 pub fn management_mode_loop(
     terminal_io: &mut TerminalIO,
     credentials: &rv_api::AuthenticationResponse,
 ) -> TimeoutResult<()> {
+    let user_id = rv_api::get_user_info(&terminal_io.config.api, credentials)
+        .unwrap()
+        .user_id;
+    match verify_totp(terminal_io, credentials, user_id) {
+        TimeoutResult::RESULT(true) => (),
+        TimeoutResult::RESULT(false) => return TimeoutResult::RESULT(()),
+        TimeoutResult::TIMEOUT => return TimeoutResult::TIMEOUT,
+    }
     let mut logo: bool = true;
     'main: loop {
         print_management_loop_banner(terminal_io, logo);
         logo = false;
-        let user_info = rv_api::get_user_info(&credentials).unwrap();
+        let user_info = rv_api::get_user_info(&terminal_io.config.api, &credentials).unwrap();
         execute!(
             terminal_io.writer,
             Print(&format!(
@@ -1163,12 +1512,15 @@ pub fn management_mode_loop(
         )
         .unwrap();
 
-        let mut command = String::new();
+        let mut editor = LineEditor::new(Echo::Plain).accepting(|c| c.is_ascii_digit());
         loop {
-            match terminal_io.recv.recv_timeout(INPUT_TIMEOUT_LONG) {
+            match terminal_io
+                .recv
+                .recv_timeout(terminal_io.config.input.timeout_long())
+            {
                 Err(RecvTimeoutError::Timeout) => return TimeoutResult::TIMEOUT,
                 Ok(InputEvent::Terminal(Event::Key(ev))) => match ev.code {
-                    KeyCode::Char(c) => match c.to_ascii_lowercase() {
+                    KeyCode::Char(c) if c.is_ascii_alphabetic() => match c.to_ascii_lowercase() {
                         'f' => {
                             printline(terminal_io, "\n");
                             match search_products(terminal_io, credentials) {
@@ -1189,7 +1541,11 @@ pub fn management_mode_loop(
                         }
                         's' => {
                             printline(terminal_io, "\n");
-                            match search_for_user(INPUT_TIMEOUT_LONG, terminal_io, &credentials) {
+                            match user_directory::user_directory(
+                                terminal_io.config.input.timeout_long(),
+                                terminal_io,
+                                &credentials,
+                            ) {
                                 TimeoutResult::TIMEOUT => return TimeoutResult::TIMEOUT,
                                 TimeoutResult::RESULT(_) => (),
                             }
@@ -1199,7 +1555,7 @@ pub fn management_mode_loop(
                         'p' => {
                             printline(terminal_io, "\n");
                             match change_user_password_admin(
-                                INPUT_TIMEOUT_LONG,
+                                terminal_io.config.input.timeout_long(),
                                 terminal_io,
                                 &credentials,
                             ) {
@@ -1212,7 +1568,7 @@ pub fn management_mode_loop(
                         'e' => {
                             printline(terminal_io, "\n");
                             match generate_temp_password_admin(
-                                INPUT_TIMEOUT_LONG,
+                                terminal_io.config.input.timeout_long(),
                                 terminal_io,
                                 &credentials,
                             ) {
@@ -1222,57 +1578,102 @@ pub fn management_mode_loop(
                             printline(terminal_io, "");
                             break;
                         }
-                        'c' => {
-                            logo = true;
+                        'r' => {
+                            printline(terminal_io, "\n");
+                            match revoke_rfid_tag_admin(
+                                terminal_io.config.input.timeout_long(),
+                                terminal_io,
+                                credentials,
+                            ) {
+                                TimeoutResult::TIMEOUT => return TimeoutResult::TIMEOUT,
+                                TimeoutResult::RESULT(_) => (),
+                            }
+                            printline(terminal_io, "");
                             break;
                         }
-                        '0'..='9' => {
-                            terminal_io.writer.execute(Print(c)).unwrap();
-                            command.push(c);
-                        }
-                        _ => {}
-                    },
-                    KeyCode::Backspace => {
-                        if !command.is_empty() {
-                            execute!(
-                                terminal_io.writer,
-                                cursor::MoveLeft(1),
-                                Print(" "),
-                                cursor::MoveLeft(1)
-                            )
-                            .unwrap();
-                            command.pop();
+                        'd' => {
+                            printline(terminal_io, "\n");
+                            match bank::pending_deposits(
+                                terminal_io.config.input.timeout_long(),
+                                terminal_io,
+                                credentials,
+                            ) {
+                                TimeoutResult::TIMEOUT => return TimeoutResult::TIMEOUT,
+                                TimeoutResult::RESULT(_) => (),
+                            }
+                            printline(terminal_io, "");
+                            break;
                         }
-                    }
-                    KeyCode::Enter => {
-                        command = command.trim().to_string();
-                        utils::printline(terminal_io, "\r\n");
-                        if command.is_empty() {
-                            clear_terminal(terminal_io);
-                            break 'main;
-                        } else if Regex::new("^[0-9]+$").expect("").is_match(&command) {
-                            match process_barcode_admin(&command, terminal_io, credentials) {
+                        'k' => {
+                            printline(terminal_io, "\n");
+                            match cash::cash_reconciliation(
+                                terminal_io.config.input.timeout_long(),
+                                terminal_io,
+                                credentials,
+                            ) {
+                                TimeoutResult::TIMEOUT => return TimeoutResult::TIMEOUT,
                                 TimeoutResult::RESULT(_) => (),
+                            }
+                            printline(terminal_io, "");
+                            break;
+                        }
+                        'x' => {
+                            printline(terminal_io, "\n");
+                            match expiring_report(terminal_io) {
                                 TimeoutResult::TIMEOUT => return TimeoutResult::TIMEOUT,
+                                TimeoutResult::RESULT(_) => (),
                             }
+                            printline(terminal_io, "");
                             break;
-                        } else {
-                            utils::print_error_line(
-                                terminal_io,
-                                &format!("unknown command: {}\r\n", &command),
-                            );
+                        }
+                        'c' => {
+                            logo = true;
                             break;
                         }
-                    }
+                        _ => {}
+                    },
                     KeyCode::F(5) => {
                         break;
                     }
-                    _ => (),
+                    _ => match editor.handle_key(&ev) {
+                        Action::Submit => {
+                            let command = editor.text();
+                            utils::printline(terminal_io, "\r\n");
+                            if command.is_empty() {
+                                clear_terminal(terminal_io);
+                                break 'main;
+                            } else if Regex::new("^[0-9]+$").expect("").is_match(&command) {
+                                if let Some(barcode) = check_barcode(terminal_io, &command, false) {
+                                    let barcode =
+                                        resolve_barcode(terminal_io, credentials, barcode, true);
+                                    match process_barcode_admin(&barcode, terminal_io, credentials)
+                                    {
+                                        TimeoutResult::RESULT(_) => (),
+                                        TimeoutResult::TIMEOUT => return TimeoutResult::TIMEOUT,
+                                    }
+                                }
+                                break;
+                            } else {
+                                utils::print_error_line(
+                                    terminal_io,
+                                    &format!("unknown command: {}\r\n", &command),
+                                );
+                                break;
+                            }
+                        }
+                        Action::Edited => editor.render(&mut terminal_io.writer, None).unwrap(),
+                        Action::Ignored => (),
+                    },
                 },
+                Ok(InputEvent::Terminal(Event::Paste(text))) => {
+                    if let Action::Edited = editor.insert_str(&text) {
+                        editor.render(&mut terminal_io.writer, None).unwrap();
+                    }
+                }
                 Ok(InputEvent::Barcode(barcode)) => {
-                    let trimmed_barcode = barcode.trim();
-                    if Regex::new("^[0-9]+$").expect("").is_match(trimmed_barcode) {
-                        match process_barcode_admin(trimmed_barcode, terminal_io, credentials) {
+                    if let Some(barcode) = check_barcode(terminal_io, &barcode, true) {
+                        let barcode = resolve_barcode(terminal_io, credentials, barcode, true);
+                        match process_barcode_admin(&barcode, terminal_io, credentials) {
                             TimeoutResult::RESULT(_) => (),
                             TimeoutResult::TIMEOUT => return TimeoutResult::TIMEOUT,
                         }
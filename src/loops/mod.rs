@@ -1,21 +1,35 @@
+mod bank;
+mod cash;
+mod idle;
+mod leaderboard;
 mod management;
+mod onboarding;
+mod pin_gate;
 mod setting;
+mod stats;
 mod user;
+mod user_directory;
 
+use crate::email;
 use crate::input;
+use crate::line_editor::{Action, Echo, LineEditor};
+use crate::login_guard::{Blocked, LoginGuard};
+use crate::password_policy;
+use crate::prefs::Prefs;
 use crate::rv_api;
 use crate::utils;
 use crate::utils::clear_terminal;
 use crate::TerminalIO;
-use crate::DEVELOPMENT_MODE;
-use crate::INPUT_TIMEOUT_LONG;
-use crate::INPUT_TIMEOUT_SHORT;
+use pin_gate::PinGate;
 
+use chrono::Utc;
+use chrono_tz::Europe::Helsinki;
 use crossterm::{
-    cursor::{self, RestorePosition, SavePosition},
+    cursor,
     event::{Event, KeyCode},
-    execute, queue,
+    execute,
     style::Print,
+    terminal::{Clear, ClearType},
 };
 
 use rv_api::{login_rfid, ApiResult, ApiResultValue};
@@ -29,7 +43,7 @@ use utils::{ConfirmResult, TimeoutResult};
 fn register(username: &str, terminal_io: &mut TerminalIO) -> TimeoutResult<()> {
     utils::printline(
         terminal_io,
-        &format!("\r\nuser {username} does not exist, create a new user? [yN]"),
+        &format!("\r\ncreate a new user {username}? [yN]"),
     );
     match utils::confirm_with_default(terminal_io, ConfirmResult::NO).unwrap() {
         ConfirmResult::YES => (),
@@ -71,12 +85,19 @@ fn register(username: &str, terminal_io: &mut TerminalIO) -> TimeoutResult<()> {
     execute!(
         terminal_io.writer,
         Print(&format!(
-            "\r\nCreating a new user: {username}\r\nenter password: "
+            "\r\nCreating a new user: {username}\r\n\
+             The password needs at least {} characters and must not be your username or a common password\r\n\
+             enter password: ",
+            password_policy::MIN_LENGTH
         ))
     )
     .unwrap();
 
-    let password1 = match utils::readpasswd(terminal_io, INPUT_TIMEOUT_LONG) {
+    let password1 = match utils::read_new_password(
+        terminal_io,
+        terminal_io.config.input.timeout_long(),
+        username,
+    ) {
         TimeoutResult::TIMEOUT => {
             utils::printline(terminal_io, "Timed out!");
             std::thread::sleep(std::time::Duration::from_millis(2000));
@@ -86,7 +107,7 @@ fn register(username: &str, terminal_io: &mut TerminalIO) -> TimeoutResult<()> {
     };
 
     execute!(terminal_io.writer, Print("\r\nEnter password again: ")).unwrap();
-    let password2 = match utils::readpasswd(terminal_io, INPUT_TIMEOUT_LONG) {
+    let password2 = match utils::readpasswd(terminal_io, terminal_io.config.input.timeout_long()) {
         TimeoutResult::TIMEOUT => {
             utils::printline(terminal_io, "Timed out!");
             std::thread::sleep(std::time::Duration::from_millis(2000));
@@ -95,6 +116,12 @@ fn register(username: &str, terminal_io: &mut TerminalIO) -> TimeoutResult<()> {
         TimeoutResult::RESULT(s) => s,
     };
 
+    if let Err(msg) = password_policy::check(&password1, username) {
+        utils::print_error_line(terminal_io, &format!("\r\n{msg}, aborting."));
+        std::thread::sleep(std::time::Duration::from_millis(2000));
+        return TimeoutResult::RESULT(());
+    }
+
     if password1 != password2 {
         utils::printline(terminal_io, "Given passwords do not match, aborting.");
         std::thread::sleep(std::time::Duration::from_millis(2000));
@@ -103,7 +130,7 @@ fn register(username: &str, terminal_io: &mut TerminalIO) -> TimeoutResult<()> {
 
     execute!(terminal_io.writer, Print("\r\nEnter your FULL name: ")).unwrap();
 
-    let full_name = match utils::readline(terminal_io, INPUT_TIMEOUT_LONG) {
+    let full_name = match utils::readline(terminal_io, terminal_io.config.input.timeout_long()) {
         TimeoutResult::TIMEOUT => {
             utils::printline(terminal_io, "Timed out!");
             std::thread::sleep(std::time::Duration::from_millis(2000));
@@ -112,7 +139,7 @@ fn register(username: &str, terminal_io: &mut TerminalIO) -> TimeoutResult<()> {
         TimeoutResult::RESULT(s) => s,
     };
 
-    let email = match input_email(terminal_io, INPUT_TIMEOUT_LONG) {
+    let email = match input_email(terminal_io, terminal_io.config.input.timeout_long()) {
         TimeoutResult::TIMEOUT => {
             utils::printline(terminal_io, "Timed out!");
             std::thread::sleep(std::time::Duration::from_millis(2000));
@@ -121,9 +148,24 @@ fn register(username: &str, terminal_io: &mut TerminalIO) -> TimeoutResult<()> {
         TimeoutResult::RESULT(email) => email,
     };
 
-    match rv_api::register(&username, &password1, &full_name, &email).unwrap() {
+    match rv_api::register(
+        &terminal_io.config.api,
+        &username,
+        &password1,
+        &full_name,
+        &email,
+    )
+    .unwrap()
+    {
         rv_api::ApiResult::Success => {
-            utils::printline(terminal_io, &format!("{username} registered successfully"));
+            utils::printline(
+                terminal_io,
+                &format!("{username} registered successfully, log in to get started"),
+            );
+            utils::printline(
+                terminal_io,
+                &format!("A verification code was sent to {email}, you will be asked for it when you log in"),
+            );
             utils::confirm_enter_to_continue(terminal_io);
         }
         rv_api::ApiResult::Fail(msg) => {
@@ -147,7 +189,7 @@ fn input_email(terminal_io: &mut TerminalIO, timeout: Duration) -> TimeoutResult
             TimeoutResult::TIMEOUT => {
                 return TimeoutResult::TIMEOUT;
             }
-            TimeoutResult::RESULT(s) => s,
+            TimeoutResult::RESULT(s) => s.trim().to_string(),
         };
 
         execute!(
@@ -160,7 +202,7 @@ fn input_email(terminal_io: &mut TerminalIO, timeout: Duration) -> TimeoutResult
             TimeoutResult::TIMEOUT => {
                 return TimeoutResult::TIMEOUT;
             }
-            TimeoutResult::RESULT(s) => s,
+            TimeoutResult::RESULT(s) => s.trim().to_string(),
         };
         utils::printline(terminal_io, "");
 
@@ -168,7 +210,7 @@ fn input_email(terminal_io: &mut TerminalIO, timeout: Duration) -> TimeoutResult
             return TimeoutResult::TIMEOUT;
         }
 
-        if email1.split("@").count() != 2 {
+        if !email::is_valid(&email1) {
             utils::printline(terminal_io, "Given emails are not valid, try again.");
             std::thread::sleep(std::time::Duration::from_millis(3000));
             for _ in 0..6 {
@@ -190,16 +232,68 @@ fn input_email(terminal_io: &mut TerminalIO, timeout: Duration) -> TimeoutResult
     }
 }
 
+const EMAIL_CODE_ATTEMPTS: usize = 5;
+
+// Asks for the code the backend emailed, empty postpones it to the next login.
+// Returns true when the address was verified.
+pub(super) fn verify_email(
+    timeout: Duration,
+    terminal_io: &mut TerminalIO,
+    credentials: &rv_api::AuthenticationResponse,
+    email: &str,
+) -> TimeoutResult<bool> {
+    for _ in 0..EMAIL_CODE_ATTEMPTS {
+        execute!(
+            terminal_io.writer,
+            Print(format!(
+                "\r\nEnter the verification code sent to {email},\r\n\
+                 R to send a new code, or leave empty to verify at your next login: "
+            ))
+        )
+        .unwrap();
+        let input = match utils::readline(terminal_io, timeout) {
+            TimeoutResult::TIMEOUT => return TimeoutResult::TIMEOUT,
+            TimeoutResult::RESULT(s) => s.trim().to_string(),
+        };
+        utils::printline(terminal_io, "");
+        match input.as_str() {
+            "" => return TimeoutResult::RESULT(false),
+            "r" | "R" => {
+                match rv_api::request_email_verification(&terminal_io.config.api, credentials) {
+                    Ok(ApiResult::Success) => utils::printline(terminal_io, "A new code was sent"),
+                    Ok(ApiResult::Fail(msg)) => utils::print_error_line(terminal_io, &msg),
+                    Err(e) => utils::print_error_line(
+                        terminal_io,
+                        &format!("Sending a new code failed: {e}"),
+                    ),
+                }
+            }
+            code => match rv_api::verify_email(&terminal_io.config.api, credentials, code) {
+                Ok(ApiResult::Success) => {
+                    log::info!("event=email_verified");
+                    utils::printline(terminal_io, "Email address verified");
+                    return TimeoutResult::RESULT(true);
+                }
+                Ok(ApiResult::Fail(msg)) => utils::print_error_line(terminal_io, &msg),
+                Err(e) => {
+                    utils::print_error_line(terminal_io, &format!("Verification failed: {e}"))
+                }
+            },
+        }
+    }
+    TimeoutResult::RESULT(false)
+}
+
 fn set_valid_email(
     terminal_io: &mut TerminalIO,
     credentials: &rv_api::AuthenticationResponse,
-) -> Option<()> {
+) -> Option<String> {
     utils::printline(terminal_io, "\n");
     utils::printline(
         terminal_io,
         "To continue using RV you need to provide valid email",
     );
-    let email = match input_email(terminal_io, INPUT_TIMEOUT_LONG) {
+    let email = match input_email(terminal_io, terminal_io.config.input.timeout_long()) {
         TimeoutResult::TIMEOUT => {
             utils::printline(terminal_io, "Timed out!");
             std::thread::sleep(std::time::Duration::from_millis(2000));
@@ -208,7 +302,7 @@ fn set_valid_email(
         TimeoutResult::RESULT(email) => email,
     };
 
-    match rv_api::change_email(credentials, &email) {
+    match rv_api::change_email(&terminal_io.config.api, credentials, &email) {
         Ok(apiresult) => {
             if let ApiResult::Fail(e) = apiresult {
                 if e == "Email taken" {
@@ -235,7 +329,7 @@ fn set_valid_email(
             return None;
         }
     }
-    Some(())
+    Some(email)
 }
 
 fn set_valid_full_name(
@@ -251,7 +345,7 @@ fn set_valid_full_name(
 
     execute!(terminal_io.writer, Print("Enter your FULL name: ")).unwrap();
 
-    let full_name = match utils::readline(terminal_io, INPUT_TIMEOUT_LONG) {
+    let full_name = match utils::readline(terminal_io, terminal_io.config.input.timeout_long()) {
         TimeoutResult::TIMEOUT => {
             utils::printline(terminal_io, "Timed out!");
             std::thread::sleep(std::time::Duration::from_millis(2000));
@@ -264,7 +358,7 @@ fn set_valid_full_name(
         return None;
     }
 
-    match rv_api::change_full_name(credentials, &full_name) {
+    match rv_api::change_full_name(&terminal_io.config.api, credentials, &full_name) {
         Ok(apiresult) => {
             if let ApiResult::Fail(_) = apiresult {
                 utils::printline(
@@ -287,74 +381,220 @@ fn set_valid_full_name(
     Some(())
 }
 
-pub fn main_loop(terminal_io: &mut TerminalIO) -> io::Result<()> {
-    'main: loop {
-        clear_terminal(terminal_io);
-        let leaderboard = match rv_api::get_leaderboard().unwrap() {
-            ApiResultValue::Success(v) => v,
-            ApiResultValue::Fail(err) => {
-                utils::print_error_line(terminal_io, &err);
-                continue;
-            }
-        };
-        execute!(terminal_io.writer, SavePosition).unwrap();
-        leaderboard
-            .iter()
-            .take(20)
-            .enumerate()
-            .for_each(|(idx, val)| {
-                queue!(
+// Asks for a valid email address and full name if the account lacks them, and
+// for the email verification code. The PIN of an RFID login comes first, a found
+// tag must not change the account. None if the login must not continue.
+fn check_account_details(
+    terminal_io: &mut TerminalIO,
+    credentials: &rv_api::AuthenticationResponse,
+    pin_gate: &mut PinGate,
+) -> Option<rv_api::UserInfo> {
+    let user = match rv_api::get_user_info(&terminal_io.config.api, credentials) {
+        Err(_) => {
+            utils::printline(
+                terminal_io,
+                "error encountered when connecting to backend, try again",
+            );
+            std::thread::sleep(std::time::Duration::from_millis(2000));
+            return None;
+        }
+        Ok(u) => u,
+    };
+    let missing_details = !email::is_valid(&user.email)
+        || !user.email_verified
+        || user.full_name.to_lowercase().contains("no name");
+    let pin = match missing_details {
+        true => pin_gate.check(terminal_io, user.user_id),
+        false => pin_gate.check_login(terminal_io, user.user_id),
+    };
+    if !matches!(pin, TimeoutResult::RESULT(true)) {
+        std::thread::sleep(std::time::Duration::from_millis(2000));
+        return None;
+    }
+
+    let (email, email_verified) = match email::is_valid(&user.email) {
+        true => (user.email.clone(), user.email_verified),
+        false => (set_valid_email(terminal_io, credentials)?, false),
+    };
+    if !email_verified {
+        if let TimeoutResult::TIMEOUT = verify_email(
+            terminal_io.config.input.timeout_long(),
+            terminal_io,
+            credentials,
+            &email,
+        ) {
+            return None;
+        }
+    }
+
+    if user.full_name.to_lowercase().contains("no name") {
+        set_valid_full_name(terminal_io, credentials)?;
+    }
+    Some(user)
+}
+
+// Settings stored on this terminal. None, after telling the user, when the file
+// cannot be read: the caller must then refuse anything that depends on them.
+pub(super) fn load_prefs(terminal_io: &mut TerminalIO) -> Option<Prefs> {
+    match Prefs::load(&terminal_io.config.store.dir) {
+        Ok(prefs) => Some(prefs),
+        Err(_) => {
+            utils::print_error_line(
+                terminal_io,
+                "The settings stored on this terminal could not be read, ask an admin for help",
+            );
+            None
+        }
+    }
+}
+
+// Counts down the backoff delay of the username, any key cancels back to the
+// login screen. Returns false if the password must not be asked now.
+fn wait_for_login(terminal_io: &mut TerminalIO, username: &str) -> bool {
+    let Ok(guard) = LoginGuard::load(&terminal_io.config.store.dir) else {
+        utils::print_error_line(
+            terminal_io,
+            "\r\nThe failed login attempts on this terminal could not be read, log in with RFID",
+        );
+        let _ = terminal_io
+            .recv
+            .recv_timeout(terminal_io.config.input.timeout_short());
+        return false;
+    };
+    loop {
+        let now = Utc::now();
+        match guard.check(username, now, &terminal_io.config.login) {
+            None => return true,
+            Some(Blocked::Wait(until)) => {
+                let secs = (until - now).num_seconds() + 1;
+                execute!(
                     terminal_io.writer,
-                    cursor::MoveTo(50, idx as u16 + 5),
+                    cursor::MoveToColumn(0),
+                    Clear(ClearType::CurrentLine),
                     Print(format!(
-                        "{:<20} | {:>6}",
-                        val.name.chars().take(20).collect::<String>(),
-                        utils::format_money(&val.saldo)
+                        "too many failed attempts, try again in {secs}s (any key cancels)"
                     ))
                 )
                 .unwrap();
-            });
-        execute!(terminal_io.writer, RestorePosition).unwrap();
+                match terminal_io.recv.recv_timeout(Duration::from_secs(1)) {
+                    Err(RecvTimeoutError::Timeout) => (),
+                    _ => return false,
+                }
+            }
+            Some(blocked) => {
+                let (what, until) = match blocked {
+                    Blocked::TerminalLocked(until) => ("this terminal", until),
+                    Blocked::Locked(until) | Blocked::Wait(until) => ("this username", until),
+                };
+                utils::print_error_line(
+                    terminal_io,
+                    &format!(
+                        "\r\ntoo many failed attempts, password logins to {what} are locked until {}",
+                        until.with_timezone(&Helsinki).format("%H:%M")
+                    ),
+                );
+                let _ = terminal_io
+                    .recv
+                    .recv_timeout(terminal_io.config.input.timeout_short());
+                return false;
+            }
+        }
+    }
+}
+
+fn record_login_failure(terminal_io: &TerminalIO, username: &str) {
+    let config = &terminal_io.config.login;
+    let dir = &terminal_io.config.store.dir;
+    let now = Utc::now();
+    let Ok(mut guard) = LoginGuard::load(dir) else {
+        return;
+    };
+    guard.prune(now, config);
+    let failures = guard.record_failure(username, now, config);
+    if let Err(e) = guard.save(dir) {
+        log::error!("event=login_guard_save error={:?}", e.to_string());
+    }
+    if failures == config.lockout_after {
+        log::warn!("event=login_lockout failures={failures}");
+        if config.notify_owner {
+            match rv_api::notify_failed_logins(&terminal_io.config.api, username, failures) {
+                Ok(ApiResult::Success) => (),
+                Ok(ApiResult::Fail(msg)) => log::error!("event=notify_failed_logins error={msg:?}"),
+                Err(e) => log::error!("event=notify_failed_logins error={:?}", e.to_string()),
+            }
+        }
+    }
+}
+
+fn record_login_success(terminal_io: &TerminalIO, username: &str) {
+    let dir = &terminal_io.config.store.dir;
+    let Ok(mut guard) = LoginGuard::load(dir) else {
+        return;
+    };
+    guard.record_success(username);
+    if let Err(e) = guard.save(dir) {
+        log::error!("event=login_guard_save error={:?}", e.to_string());
+    }
+}
+
+pub fn main_loop(terminal_io: &mut TerminalIO) -> io::Result<()> {
+    // Input that woke up the attract screen, handled as if typed on the login prompt
+    let mut pending_event: Option<input::InputEvent> = None;
+    let mut login_stats = leaderboard::LoginStats::new();
+    'main: loop {
+        clear_terminal(terminal_io);
+        login_stats.draw(terminal_io);
         execute!(
             terminal_io.writer,
-            Print("to log in or register\r\n"),
+            Print("to log in, or leave the password empty to register\r\n"),
             Print("enter username: "),
         )?;
         utils::print_rv_logo(terminal_io);
-        let mut username = String::new();
+        let mut editor = LineEditor::new(Echo::Plain);
         loop {
-            match &terminal_io.recv.recv().unwrap() {
-                input::InputEvent::Terminal(Event::Key(ev)) => match ev.code {
-                    KeyCode::Char(c) => {
-                        username.push(c);
-                        execute!(terminal_io.writer, Print(c))?;
-                    }
-                    KeyCode::Backspace => {
-                        if !username.is_empty() {
-                            execute!(
-                                terminal_io.writer,
-                                cursor::MoveLeft(1),
-                                Print(" "),
-                                cursor::MoveLeft(1)
-                            )
-                            .expect("fail");
-                            username.pop();
-                        }
-                    }
-                    KeyCode::Enter => {
-                        if !username.is_empty() {
-                            break;
+            let event = match pending_event.take() {
+                Some(event) => event,
+                None => match terminal_io
+                    .recv
+                    .recv_timeout(terminal_io.config.idle.timeout())
+                {
+                    Ok(event) => event,
+                    Err(RecvTimeoutError::Timeout) => {
+                        if editor.is_empty() {
+                            match idle::idle_loop(terminal_io) {
+                                Some(event) => pending_event = Some(event),
+                                None => return Ok(()),
+                            }
+                            continue 'main;
                         }
+                        continue;
                     }
+                    Err(RecvTimeoutError::Disconnected) => return Ok(()),
+                },
+            };
+            match &event {
+                input::InputEvent::Terminal(Event::Key(ev)) => match editor.handle_key(ev) {
+                    Action::Submit if !editor.is_empty() => break,
+                    Action::Edited => editor.render(&mut terminal_io.writer, None)?,
                     _ => (),
                 },
-                input::InputEvent::Rfid(rfid) => match login_rfid(&rfid) {
+                input::InputEvent::Terminal(Event::Paste(text)) => {
+                    if let Action::Edited = editor.insert_str(text) {
+                        editor.render(&mut terminal_io.writer, None)?;
+                    }
+                }
+                input::InputEvent::Rfid(rfid) => match login_rfid(&terminal_io.config.api, &rfid) {
                     Some(credentials) => {
-                        user::user_loop(terminal_io, &credentials);
-                        (&terminal_io, &credentials);
+                        log::info!("event=login method=rfid ok=true");
+                        let mut pin_gate = PinGate::rfid();
+                        if check_account_details(terminal_io, &credentials, &mut pin_gate).is_some()
+                        {
+                            user::user_loop(terminal_io, &credentials, pin_gate);
+                        }
                         continue 'main;
                     }
                     None => {
+                        log::warn!("event=login method=rfid ok=false");
                         utils::printline(terminal_io, "No matching users found for rfid");
                         std::thread::sleep(std::time::Duration::from_millis(2000));
                         continue 'main;
@@ -363,117 +603,100 @@ pub fn main_loop(terminal_io: &mut TerminalIO) -> io::Result<()> {
                 _ => (),
             }
         }
+        let username = editor.text();
 
-        if username == "quit" && *DEVELOPMENT_MODE {
+        if username == "quit" && terminal_io.config.development {
             return Ok(());
         }
 
-        if !rv_api::user_exists(&username).unwrap() {
-            register(&username, terminal_io);
+        if !wait_for_login(terminal_io, &username) {
             continue 'main;
         }
 
         execute!(terminal_io.writer, Print("\r\nenter password: ")).expect("fail");
-        let mut password = String::new();
+        let mut editor = LineEditor::new(Echo::Masked);
         loop {
-            match &terminal_io.recv.recv_timeout(INPUT_TIMEOUT_SHORT) {
+            match &terminal_io
+                .recv
+                .recv_timeout(terminal_io.config.input.timeout_short())
+            {
                 Err(RecvTimeoutError::Timeout) => {
                     utils::printline(terminal_io, "Timed out!");
                     std::thread::sleep(std::time::Duration::from_millis(2000));
                     continue 'main;
                 }
-                Ok(input::InputEvent::Terminal(Event::Key(ev))) => match ev.code {
-                    KeyCode::Char(c) => {
-                        password.push(c);
-                    }
-                    KeyCode::Backspace => {
-                        if !username.is_empty() {
-                            password.pop();
-                        }
-                    }
-                    KeyCode::Enter => {
-                        break;
-                    }
-                    KeyCode::F(5) => {
-                        continue 'main;
-                    }
-                    _ => (),
+                Ok(input::InputEvent::Terminal(Event::Key(ev))) if ev.code == KeyCode::F(5) => {
+                    continue 'main;
+                }
+                Ok(input::InputEvent::Terminal(Event::Key(ev))) => match editor.handle_key(ev) {
+                    Action::Submit => break,
+                    Action::Edited => editor.render(&mut terminal_io.writer, None)?,
+                    Action::Ignored => (),
                 },
-                Ok(input::InputEvent::Rfid(rfid)) => match login_rfid(&rfid) {
-                    Some(credentials) => {
-                        let user = match rv_api::get_user_info(&credentials) {
-                            Err(_) => {
-                                utils::printline(
-                                    terminal_io,
-                                    "error encountered when connecting to backend, try again",
-                                );
-                                std::thread::sleep(std::time::Duration::from_millis(2000));
-                                continue 'main;
-                            }
-                            Ok(u) => u,
-                        };
-
-                        if user.email.split("@").count() != 2 {
-                            if let None = set_valid_email(terminal_io, &credentials) {
+                Ok(input::InputEvent::Terminal(Event::Paste(text))) => {
+                    if let Action::Edited = editor.insert_str(text) {
+                        editor.render(&mut terminal_io.writer, None)?;
+                    }
+                }
+                Ok(input::InputEvent::Rfid(rfid)) => {
+                    match login_rfid(&terminal_io.config.api, &rfid) {
+                        Some(credentials) => {
+                            log::info!("event=login method=rfid ok=true");
+                            let mut pin_gate = PinGate::rfid();
+                            if check_account_details(terminal_io, &credentials, &mut pin_gate)
+                                .is_none()
+                            {
                                 continue 'main;
                             }
+                            user::user_loop(terminal_io, &credentials, pin_gate);
+                            continue 'main;
                         }
-
-                        if user.full_name == "no name" {
-                            if let None = set_valid_full_name(terminal_io, &credentials) {
-                                continue 'main;
-                            }
+                        None => {
+                            log::warn!("event=login method=rfid ok=false");
+                            utils::printline(terminal_io, "no matching users found for rfid");
+                            std::thread::sleep(std::time::Duration::from_millis(2000));
+                            continue 'main;
                         }
-
-                        user::user_loop(terminal_io, &credentials);
-                        continue 'main;
                     }
-                    None => {
-                        utils::printline(terminal_io, "no matching users found for rfid");
-                        std::thread::sleep(std::time::Duration::from_millis(2000));
-                        continue 'main;
-                    }
-                },
+                }
                 _ => (),
             }
         }
-        let credentials = match rv_api::login(&username, &password) {
-            ApiResultValue::Success(v) => v,
+        let password = editor.text();
+        // Registered usernames are not revealed, registering starts with an empty password
+        if password.is_empty() {
+            register(&username, terminal_io);
+            continue 'main;
+        }
+        let credentials = match rv_api::login(&terminal_io.config.api, &username, &password) {
+            ApiResultValue::Success(v) => {
+                log::info!("event=login method=password user={username:?} ok=true");
+                record_login_success(terminal_io, &username);
+                v
+            }
             ApiResultValue::Fail(_) => {
+                // The username is left out, a password is often typed there by mistake
+                log::warn!("event=login method=password ok=false");
+                record_login_failure(terminal_io, &username);
                 utils::printline(terminal_io, "error: invalid username or password!");
                 std::thread::sleep(std::time::Duration::from_millis(2000));
                 continue;
             }
         };
 
-        let user = match rv_api::get_user_info(&credentials) {
-            Err(_) => {
-                utils::printline(
-                    terminal_io,
-                    "error encountered when connecting to backend, try again",
-                );
-                std::thread::sleep(std::time::Duration::from_millis(2000));
-                continue 'main;
-            }
-            Ok(u) => u,
+        let mut pin_gate = PinGate::password();
+        let Some(user) = check_account_details(terminal_io, &credentials, &mut pin_gate) else {
+            continue 'main;
         };
 
-        if user.email.split("@").count() != 2 {
-            if let None = set_valid_email(terminal_io, &credentials) {
-                continue 'main;
-            }
-        }
-
-        if user.full_name.to_lowercase().contains("no name") {
-            if let None = set_valid_full_name(terminal_io, &credentials) {
-                continue 'main;
-            }
-        }
-
         if credentials.password_reset {
             execute!(terminal_io.writer, Print("Enter new password: ")).unwrap();
 
-            let password1 = match utils::readpasswd(terminal_io, INPUT_TIMEOUT_LONG) {
+            let password1 = match utils::read_new_password(
+                terminal_io,
+                terminal_io.config.input.timeout_long(),
+                &user.username,
+            ) {
                 TimeoutResult::TIMEOUT => continue 'main,
                 TimeoutResult::RESULT(s) => s,
             };
@@ -481,10 +704,11 @@ pub fn main_loop(terminal_io: &mut TerminalIO) -> io::Result<()> {
             utils::printline(terminal_io, "");
             execute!(terminal_io.writer, Print("Enter new password again: ")).unwrap();
 
-            let password2 = match utils::readpasswd(terminal_io, INPUT_TIMEOUT_LONG) {
-                TimeoutResult::TIMEOUT => continue 'main,
-                TimeoutResult::RESULT(s) => s,
-            };
+            let password2 =
+                match utils::readpasswd(terminal_io, terminal_io.config.input.timeout_long()) {
+                    TimeoutResult::TIMEOUT => continue 'main,
+                    TimeoutResult::RESULT(s) => s,
+                };
 
             utils::printline(terminal_io, "");
 
@@ -494,8 +718,14 @@ pub fn main_loop(terminal_io: &mut TerminalIO) -> io::Result<()> {
                     "Empty password is not allowed! Password not changed.",
                 );
                 continue 'main;
+            } else if let Err(msg) = password_policy::check(&password1, &user.username) {
+                utils::print_error_line(terminal_io, &format!("{msg}. Password not changed."));
+                std::thread::sleep(std::time::Duration::from_millis(2000));
+                continue 'main;
             } else if password1 == password2 {
-                match rv_api::change_password(&credentials, &password1).unwrap() {
+                match rv_api::change_password(&terminal_io.config.api, &credentials, &password1)
+                    .unwrap()
+                {
                     rv_api::ApiResult::Success => {
                         utils::printline(terminal_io, "New password successfully changed.");
                     }
@@ -513,6 +743,6 @@ pub fn main_loop(terminal_io: &mut TerminalIO) -> io::Result<()> {
             }
         }
 
-        user::user_loop(terminal_io, &credentials);
+        user::user_loop(terminal_io, &credentials, pin_gate);
     }
 }
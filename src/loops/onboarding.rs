@@ -0,0 +1,190 @@
+use super::pin_gate::PinGate;
+use super::{setting, user};
+use crate::config::ApiConfig;
+use crate::prefs::Prefs;
+use crate::rv_api::{self, UserInfo};
+use crate::utils::{self, clear_terminal, print_title, printline, ConfirmResult, TimeoutResult};
+use crate::TerminalIO;
+
+const STEPS: usize = 4;
+
+// Members who have not bought or deposited anything yet
+fn is_new_account(
+    api: &ApiConfig,
+    credentials: &rv_api::AuthenticationResponse,
+    user: &UserInfo,
+) -> bool {
+    user.money_balance == 0
+        && rv_api::deposit_history(api, credentials).is_ok_and(|deposits| deposits.is_empty())
+        && rv_api::purchase_history(api, credentials).is_empty()
+}
+
+fn mark_onboarded(terminal_io: &mut TerminalIO, user_id: i32) {
+    let dir = terminal_io.config.store.dir.clone();
+    let Ok(mut prefs) = Prefs::load(&dir) else {
+        return;
+    };
+    prefs.user_mut(user_id).onboarded = true;
+    if let Err(e) = prefs.save(&dir) {
+        log::error!("event=prefs_save error={:?}", e.to_string());
+    }
+}
+
+// Explains a step and asks whether to do it now
+fn step(
+    terminal_io: &mut TerminalIO,
+    number: usize,
+    title: &str,
+    explanation: &str,
+    question: &str,
+) -> TimeoutResult<bool> {
+    printline(terminal_io, "");
+    print_title(terminal_io, &format!("Step {number}/{STEPS}: {title}"));
+    printline(terminal_io, explanation);
+    printline(terminal_io, &format!("{question} [yN]"));
+    match utils::confirm_with_default(terminal_io, ConfirmResult::NO).unwrap() {
+        ConfirmResult::YES => TimeoutResult::RESULT(true),
+        ConfirmResult::NO => TimeoutResult::RESULT(false),
+        ConfirmResult::TIMEOUT => TimeoutResult::TIMEOUT,
+    }
+}
+
+// Steps that change the account need the PIN like the commands of the user
+// loop, a wrong PIN skips the step
+fn with_pin(
+    terminal_io: &mut TerminalIO,
+    pin_gate: &mut PinGate,
+    user_id: i32,
+    action: impl FnOnce(&mut TerminalIO) -> TimeoutResult<()>,
+) -> TimeoutResult<()> {
+    match pin_gate.check(terminal_io, user_id) {
+        TimeoutResult::RESULT(true) => action(terminal_io),
+        TimeoutResult::RESULT(false) => TimeoutResult::RESULT(()),
+        TimeoutResult::TIMEOUT => TimeoutResult::TIMEOUT,
+    }
+}
+
+// Shown once, on the first login of a new member. Every step can be skipped,
+// a timeout shows the tour again on the next login.
+pub(super) fn onboarding_tour(
+    terminal_io: &mut TerminalIO,
+    credentials: &rv_api::AuthenticationResponse,
+    user: &UserInfo,
+    pin_gate: &mut PinGate,
+) -> TimeoutResult<()> {
+    // Not shown when the settings cannot be read, it would be shown on every login
+    if Prefs::load(&terminal_io.config.store.dir)
+        .map_or(true, |prefs| prefs.user(user.user_id).onboarded)
+    {
+        return TimeoutResult::RESULT(());
+    }
+    if !is_new_account(&terminal_io.config.api, credentials, user) {
+        mark_onboarded(terminal_io, user.user_id);
+        return TimeoutResult::RESULT(());
+    }
+
+    clear_terminal(terminal_io);
+    print_title(terminal_io, &format!("Welcome to RV, {}!", user.username));
+    printline(
+        terminal_io,
+        "Buy products by scanning their barcode with the scanner, or by typing the barcode\r\n\
+         at the prompt followed by ENTER. The price is taken from your balance, so deposit\r\n\
+         money first. The commands are listed on the screen after logging in.",
+    );
+    printline(terminal_io, "\r\nTake a quick tour of the basics? [Yn]");
+    match utils::confirm_with_default(terminal_io, ConfirmResult::YES).unwrap() {
+        ConfirmResult::YES => (),
+        ConfirmResult::NO => {
+            mark_onboarded(terminal_io, user.user_id);
+            return TimeoutResult::RESULT(());
+        }
+        ConfirmResult::TIMEOUT => return TimeoutResult::TIMEOUT,
+    }
+
+    let result = match step(
+        terminal_io,
+        1,
+        "Deposit money",
+        "Deposit cash into the box or pay by bank transfer. Later on, press D to deposit.",
+        "Deposit money now?",
+    ) {
+        TimeoutResult::RESULT(true) => with_pin(
+            terminal_io,
+            pin_gate,
+            user.user_id,
+            |terminal_io| match user::deposit(terminal_io, credentials) {
+                TimeoutResult::TIMEOUT => TimeoutResult::TIMEOUT,
+                TimeoutResult::RESULT(_) => TimeoutResult::RESULT(()),
+            },
+        ),
+        TimeoutResult::RESULT(false) => TimeoutResult::RESULT(()),
+        TimeoutResult::TIMEOUT => TimeoutResult::TIMEOUT,
+    };
+    if let TimeoutResult::TIMEOUT = result {
+        return TimeoutResult::TIMEOUT;
+    }
+
+    let result = match step(
+        terminal_io,
+        2,
+        "Log in with an RFID tag",
+        "A registered student card or other RFID tag logs you in with a single tap.\r\n\
+         Tags are managed in the settings (S) later on.",
+        "Register an RFID tag now?",
+    ) {
+        TimeoutResult::RESULT(true) => {
+            with_pin(terminal_io, pin_gate, user.user_id, |terminal_io| {
+                setting::add_rfid_tag(terminal_io, credentials)
+            })
+        }
+        TimeoutResult::RESULT(false) => TimeoutResult::RESULT(()),
+        TimeoutResult::TIMEOUT => TimeoutResult::TIMEOUT,
+    };
+    if let TimeoutResult::TIMEOUT = result {
+        return TimeoutResult::TIMEOUT;
+    }
+
+    let result = match step(
+        terminal_io,
+        3,
+        "Privacy",
+        "Your username is shown on the leaderboards and statistics unless you hide it.",
+        "Choose your privacy level now?",
+    ) {
+        TimeoutResult::RESULT(true) => {
+            with_pin(terminal_io, pin_gate, user.user_id, |terminal_io| {
+                setting::change_privacy(
+                    terminal_io.config.input.timeout_short(),
+                    terminal_io,
+                    credentials,
+                )
+            })
+        }
+        TimeoutResult::RESULT(false) => TimeoutResult::RESULT(()),
+        TimeoutResult::TIMEOUT => TimeoutResult::TIMEOUT,
+    };
+    if let TimeoutResult::TIMEOUT = result {
+        return TimeoutResult::TIMEOUT;
+    }
+
+    let result = match step(
+        terminal_io,
+        4,
+        "Find products",
+        "Search for products by name to see their prices and stock. Later on, press F.",
+        "Try a product search now?",
+    ) {
+        TimeoutResult::RESULT(true) => user::search_products(terminal_io, credentials),
+        TimeoutResult::RESULT(false) => TimeoutResult::RESULT(()),
+        TimeoutResult::TIMEOUT => TimeoutResult::TIMEOUT,
+    };
+    if let TimeoutResult::TIMEOUT = result {
+        return TimeoutResult::TIMEOUT;
+    }
+
+    mark_onboarded(terminal_io, user.user_id);
+    log::info!("event=onboarding user_id={}", user.user_id);
+    printline(terminal_io, "\r\nThat's it, enjoy!");
+    utils::confirm_enter_to_continue(terminal_io);
+    TimeoutResult::RESULT(())
+}
@@ -0,0 +1,100 @@
+use super::load_prefs;
+use crate::pin::{PinMode, PinResult};
+use crate::utils::{self, printline, TimeoutResult};
+use crate::TerminalIO;
+
+use chrono::Utc;
+use chrono_tz::Europe::Helsinki;
+use crossterm::{execute, style::Print};
+
+// PIN state of a session, only sessions started with an RFID tag need the PIN
+pub(super) struct PinGate {
+    pending: bool,
+}
+
+impl PinGate {
+    pub(super) fn rfid() -> Self {
+        Self { pending: true }
+    }
+
+    pub(super) fn password() -> Self {
+        Self { pending: false }
+    }
+
+    // Right after the login, asks the PIN only if the user wants it always.
+    // Unreadable settings end an RFID session, the PIN may be in them.
+    pub(super) fn check_login(
+        &mut self,
+        terminal_io: &mut TerminalIO,
+        user_id: i32,
+    ) -> TimeoutResult<bool> {
+        if !self.pending {
+            return TimeoutResult::RESULT(true);
+        }
+        let Some(prefs) = load_prefs(terminal_io) else {
+            return TimeoutResult::RESULT(false);
+        };
+        match prefs.user(user_id).pin.map(|pin| pin.mode) {
+            Some(PinMode::Always) => self.check(terminal_io, user_id),
+            _ => TimeoutResult::RESULT(true),
+        }
+    }
+
+    // Before a sensitive action, the PIN is asked once per session
+    pub(super) fn check(
+        &mut self,
+        terminal_io: &mut TerminalIO,
+        user_id: i32,
+    ) -> TimeoutResult<bool> {
+        if !self.pending {
+            return TimeoutResult::RESULT(true);
+        }
+        let dir = terminal_io.config.store.dir.clone();
+        let Some(mut prefs) = load_prefs(terminal_io) else {
+            return TimeoutResult::RESULT(false);
+        };
+        let Some(mut pin) = prefs.user(user_id).pin else {
+            self.pending = false;
+            return TimeoutResult::RESULT(true);
+        };
+
+        execute!(terminal_io.writer, Print("Enter your PIN: ")).unwrap();
+        let input = match utils::readpasswd(terminal_io, terminal_io.config.input.timeout_short()) {
+            TimeoutResult::TIMEOUT => return TimeoutResult::TIMEOUT,
+            TimeoutResult::RESULT(s) => s,
+        };
+        printline(terminal_io, "");
+        let config = &terminal_io.config.pin;
+        let result = pin.verify(&input, Utc::now(), config.max_attempts, config.lockout());
+        prefs.user_mut(user_id).pin = Some(pin);
+        if let Err(e) = prefs.save(&dir) {
+            log::error!("event=prefs_save error={:?}", e.to_string());
+        }
+
+        match result {
+            PinResult::Correct => {
+                self.pending = false;
+                TimeoutResult::RESULT(true)
+            }
+            PinResult::Wrong { attempts_left } => {
+                log::warn!("event=pin_wrong user_id={user_id} attempts_left={attempts_left}");
+                utils::print_error_line(
+                    terminal_io,
+                    &format!("Wrong PIN, {attempts_left} attempts left"),
+                );
+                TimeoutResult::RESULT(false)
+            }
+            PinResult::Locked(until) => {
+                log::warn!("event=pin_locked user_id={user_id} until={until:?}");
+                utils::print_error_line(
+                    terminal_io,
+                    &format!(
+                        "PIN locked until {}, log in with your password instead",
+                        until.with_timezone(&Helsinki).format("%H:%M")
+                    ),
+                );
+                TimeoutResult::RESULT(false)
+            }
+        }
+    }
+}
@@ -1,25 +1,38 @@
+use super::{load_prefs, user, verify_email};
+use crate::barcode;
+use crate::data_export::{self, DataExport, Privacy, TerminalSettings};
+use crate::email;
 use crate::input;
+use crate::password_policy;
+use crate::pin::{self, PinMode, PinSettings};
+use crate::prefs::{Prefs, ProductLimit, SpendingLimits};
 use crate::rv_api;
+use crate::rv_api::UserInfoTrait;
+use crate::rv_api::{ApiResultValue, RfidTag};
+use crate::totp::TotpSettings;
 use crate::utils;
 use crate::utils::clear_terminal;
 use crate::utils::print_title;
 use crate::utils::printline;
 use crate::utils::TimeoutResult;
 use crate::TerminalIO;
-use crate::INPUT_TIMEOUT_LONG;
-use crate::INPUT_TIMEOUT_SHORT;
 
+use chrono::{DateTime, Utc};
+use chrono_tz::Europe::Helsinki;
 use crossterm::{
     cursor,
-    event::{Event, KeyCode},
+    event::{Event, KeyCode, KeyEvent},
     execute, queue,
-    style::{Print, PrintStyledContent, Stylize},
+    style::{Color, Print, PrintStyledContent, Stylize},
     terminal,
 };
 use input::InputEvent;
 use std::sync::mpsc::RecvTimeoutError;
 use std::time::Duration;
 
+// Bytes of the document per QR code, small enough to fit the screen
+const QR_CHUNK_BYTES: usize = 600;
+
 fn change_username(
     timeout: Duration,
     terminal_io: &mut TerminalIO,
@@ -34,7 +47,7 @@ fn change_username(
         TimeoutResult::RESULT(s) => s,
     };
 
-    match rv_api::change_username(credentials, &username).unwrap() {
+    match rv_api::change_username(&terminal_io.config.api, credentials, &username).unwrap() {
         rv_api::ApiResult::Success => {
             utils::printline(terminal_io, "Username successfully changed.");
         }
@@ -60,7 +73,7 @@ fn change_real_name(
         TimeoutResult::RESULT(s) => s,
     };
 
-    match rv_api::change_full_name(credentials, &full_name).unwrap() {
+    match rv_api::change_full_name(&terminal_io.config.api, credentials, &full_name).unwrap() {
         rv_api::ApiResult::Success => {
             utils::printline(terminal_io, "Name successfully changed.");
         }
@@ -82,7 +95,7 @@ fn change_user_email(
     let email1;
     match utils::readline(terminal_io, timeout) {
         TimeoutResult::TIMEOUT => return TimeoutResult::TIMEOUT,
-        TimeoutResult::RESULT(s) => email1 = s,
+        TimeoutResult::RESULT(s) => email1 = s.trim().to_string(),
     }
 
     utils::printline(terminal_io, "");
@@ -90,7 +103,7 @@ fn change_user_email(
     let email2;
     match utils::readline(terminal_io, timeout) {
         TimeoutResult::TIMEOUT => return TimeoutResult::TIMEOUT,
-        TimeoutResult::RESULT(s) => email2 = s,
+        TimeoutResult::RESULT(s) => email2 = s.trim().to_string(),
     }
     utils::printline(terminal_io, "");
 
@@ -99,12 +112,18 @@ fn change_user_email(
             terminal_io,
             "Empty email is not allowed! Email not changed.",
         );
-    } else if email1.split("@").count() != 2 {
+    } else if !email::is_valid(&email1) {
         utils::printline(terminal_io, "You did not provide valid email address");
     } else if email1 == email2 {
-        match rv_api::change_email(credentials, &email1).unwrap() {
+        match rv_api::change_email(&terminal_io.config.api, credentials, &email1).unwrap() {
             rv_api::ApiResult::Success => {
-                utils::printline(terminal_io, "Email successfully changed.");
+                log::info!("event=email_change verified=false");
+                utils::printline(terminal_io, "Email changed, it has to be verified next.");
+                if let TimeoutResult::TIMEOUT =
+                    verify_email(timeout, terminal_io, credentials, &email1)
+                {
+                    return TimeoutResult::TIMEOUT;
+                }
             }
             rv_api::ApiResult::Fail(msg) => {
                 utils::print_error_line(terminal_io, &format!("Email change failed: {msg}"));
@@ -119,7 +138,7 @@ fn change_user_email(
     TimeoutResult::RESULT(())
 }
 
-fn change_privacy(
+pub(super) fn change_privacy(
     timeout: Duration,
     terminal_io: &mut TerminalIO,
     credentials: &rv_api::AuthenticationResponse,
@@ -141,17 +160,20 @@ fn change_privacy(
             Ok(input::InputEvent::Terminal(Event::Key(ev))) => match ev.code {
                 KeyCode::Char(c) => match c {
                     '0' => {
-                        rv_api::change_privacy_level(credentials, 0).unwrap();
+                        rv_api::change_privacy_level(&terminal_io.config.api, credentials, 0)
+                            .unwrap();
                         printline(terminal_io, "Changed privacy level to 0");
                         return TimeoutResult::RESULT(());
                     }
                     '1' => {
-                        rv_api::change_privacy_level(credentials, 1).unwrap();
+                        rv_api::change_privacy_level(&terminal_io.config.api, credentials, 1)
+                            .unwrap();
                         printline(terminal_io, "Changed privacy level to 1");
                         return TimeoutResult::RESULT(());
                     }
                     '2' => {
-                        rv_api::change_privacy_level(credentials, 2).unwrap();
+                        rv_api::change_privacy_level(&terminal_io.config.api, credentials, 2)
+                            .unwrap();
                         printline(terminal_io, "Changed privacy level to 2");
                         return TimeoutResult::RESULT(());
                     }
@@ -172,10 +194,20 @@ fn change_user_password_user(
     credentials: &rv_api::AuthenticationResponse,
 ) -> TimeoutResult<()> {
     print_title(terminal_io, "Change password");
+    let username = rv_api::get_user_info(&terminal_io.config.api, credentials)
+        .unwrap()
+        .username;
+    printline(
+        terminal_io,
+        &format!(
+            "At least {} characters, not your username or a common password",
+            password_policy::MIN_LENGTH
+        ),
+    );
 
     execute!(terminal_io.writer, Print("Enter new password: ")).unwrap();
     let password1;
-    match utils::readpasswd(terminal_io, timeout) {
+    match utils::read_new_password(terminal_io, timeout, &username) {
         TimeoutResult::TIMEOUT => return TimeoutResult::TIMEOUT,
         TimeoutResult::RESULT(s) => password1 = s,
     }
@@ -194,8 +226,10 @@ fn change_user_password_user(
             terminal_io,
             "Empty password is not allowed! Password not changed.",
         );
+    } else if let Err(msg) = password_policy::check(&password1, &username) {
+        utils::print_error_line(terminal_io, &format!("{msg}. Password not changed."));
     } else if password1 == password2 {
-        match rv_api::change_password(credentials, &password1).unwrap() {
+        match rv_api::change_password(&terminal_io.config.api, credentials, &password1).unwrap() {
             rv_api::ApiResult::Success => {
                 utils::printline(terminal_io, "Password successfully changed.");
             }
@@ -212,42 +246,962 @@ fn change_user_password_user(
     TimeoutResult::RESULT(())
 }
 
-fn change_user_rfid(
+pub(super) fn print_rfid_tags(terminal_io: &mut TerminalIO, tags: &[RfidTag]) {
+    if tags.is_empty() {
+        printline(terminal_io, "No RFID tags registered");
+        return;
+    }
+    for (idx, tag) in tags.iter().enumerate() {
+        let last_used = tag
+            .last_used
+            .as_deref()
+            .and_then(|time| DateTime::parse_from_rfc3339(time).ok())
+            .map(|time| {
+                time.with_timezone(&Helsinki)
+                    .format("%d.%m.%Y %H:%M")
+                    .to_string()
+            })
+            .unwrap_or("never".to_string());
+        printline(
+            terminal_io,
+            &format!(
+                "{:>2}. {:<30} last used {}",
+                idx + 1,
+                tag.label.chars().take(30).collect::<String>(),
+                last_used
+            ),
+        );
+    }
+}
+
+// None if the input is empty or not a number of a listed tag
+pub(super) fn select_rfid_tag<'a>(
+    timeout: Duration,
+    terminal_io: &mut TerminalIO,
+    tags: &'a [RfidTag],
+) -> TimeoutResult<Option<&'a RfidTag>> {
+    execute!(terminal_io.writer, Print("Number of the tag to revoke: ")).unwrap();
+    let input = match utils::readline(terminal_io, timeout) {
+        TimeoutResult::TIMEOUT => return TimeoutResult::TIMEOUT,
+        TimeoutResult::RESULT(s) => s,
+    };
+    printline(terminal_io, "");
+    let tag = input
+        .trim()
+        .parse::<usize>()
+        .ok()
+        .and_then(|number| tags.get(number.checked_sub(1)?));
+    if tag.is_none() && !input.is_empty() {
+        utils::print_error_line(terminal_io, "No such tag");
+    }
+    TimeoutResult::RESULT(tag)
+}
+
+pub(super) fn add_rfid_tag(
     terminal_io: &mut TerminalIO,
     credentials: &rv_api::AuthenticationResponse,
 ) -> TimeoutResult<()> {
-    print_title(terminal_io, "Set login RFID");
-    utils::printline(
-        terminal_io,
-        "Scan RFID to use for logging in. ENTER to cancel.",
-    );
+    execute!(
+        terminal_io.writer,
+        Print("Label for the tag, e.g. student card: ")
+    )
+    .unwrap();
+    let label = match utils::readline(terminal_io, terminal_io.config.input.timeout_short()) {
+        TimeoutResult::TIMEOUT => return TimeoutResult::TIMEOUT,
+        TimeoutResult::RESULT(s) => s.trim().to_string(),
+    };
+    printline(terminal_io, "");
+    if label.is_empty() {
+        printline(terminal_io, "Adding the tag cancelled");
+        return TimeoutResult::RESULT(());
+    }
+    printline(terminal_io, "Tap the tag to register it. ENTER to cancel.");
     loop {
-        match terminal_io.recv.recv_timeout(INPUT_TIMEOUT_SHORT) {
+        match terminal_io
+            .recv
+            .recv_timeout(terminal_io.config.input.timeout_short())
+        {
             Err(RecvTimeoutError::Timeout) => return TimeoutResult::TIMEOUT,
-            Ok(input::InputEvent::Terminal(Event::Key(ev))) => match ev.code {
-                KeyCode::Enter => {
-                    utils::printline(terminal_io, "RFID change cancelled");
-                    return TimeoutResult::RESULT(());
+            Ok(input::InputEvent::Terminal(Event::Key(ev))) if ev.code == KeyCode::Enter => {
+                printline(terminal_io, "Adding the tag cancelled");
+                return TimeoutResult::RESULT(());
+            }
+            Ok(input::InputEvent::Rfid(rfid)) => {
+                match rv_api::add_rfid_tag(&terminal_io.config.api, credentials, &rfid, &label)
+                    .unwrap()
+                {
+                    rv_api::ApiResult::Success => {
+                        log::info!("event=rfid_add label={label:?}");
+                        printline(terminal_io, "RFID tag added successfully");
+                    }
+                    rv_api::ApiResult::Fail(msg) => utils::print_error_line(
+                        terminal_io,
+                        &format!("Adding the tag failed: {msg}"),
+                    ),
                 }
-                _ => (),
+                return TimeoutResult::RESULT(());
+            }
+            _ => (),
+        }
+    }
+}
+
+fn manage_rfid_tags(
+    terminal_io: &mut TerminalIO,
+    credentials: &rv_api::AuthenticationResponse,
+) -> TimeoutResult<()> {
+    let timeout = terminal_io.config.input.timeout_short();
+    loop {
+        print_title(terminal_io, "Login RFID tags");
+        let tags = match rv_api::list_rfid_tags(&terminal_io.config.api, credentials).unwrap() {
+            ApiResultValue::Success(tags) => tags,
+            ApiResultValue::Fail(msg) => {
+                utils::print_error_line(terminal_io, &msg);
+                return TimeoutResult::RESULT(());
+            }
+        };
+        print_rfid_tags(terminal_io, &tags);
+        printline(terminal_io, "");
+        queue!(
+            terminal_io.writer,
+            PrintStyledContent("A".dark_green().bold()),
+            Print(" - add a new tag\r\n"),
+            PrintStyledContent("R".dark_green().bold()),
+            Print(" - revoke a tag, e.g. a lost card\r\n"),
+            PrintStyledContent("<enter>".dark_green().bold()),
+            Print(" - return\r\n"),
+        )
+        .unwrap();
+        execute!(terminal_io.writer, Print("> ")).unwrap();
+        let result = match terminal_io.recv.recv_timeout(timeout) {
+            Ok(InputEvent::Terminal(Event::Key(ev))) => match ev.code {
+                KeyCode::Char(c) => match c.to_ascii_lowercase() {
+                    'a' => {
+                        printline(terminal_io, "");
+                        add_rfid_tag(terminal_io, credentials)
+                    }
+                    'r' => {
+                        printline(terminal_io, "");
+                        match select_rfid_tag(timeout, terminal_io, &tags) {
+                            TimeoutResult::RESULT(Some(tag)) => {
+                                match rv_api::revoke_rfid_tag(
+                                    &terminal_io.config.api,
+                                    credentials,
+                                    tag.tag_id,
+                                )
+                                .unwrap()
+                                {
+                                    rv_api::ApiResult::Success => {
+                                        log::info!("event=rfid_revoke tag_id={}", tag.tag_id);
+                                        printline(terminal_io, &format!("Revoked {}", tag.label));
+                                    }
+                                    rv_api::ApiResult::Fail(msg) => utils::print_error_line(
+                                        terminal_io,
+                                        &format!("Revoking the tag failed: {msg}"),
+                                    ),
+                                }
+                                TimeoutResult::RESULT(())
+                            }
+                            TimeoutResult::RESULT(None) => TimeoutResult::RESULT(()),
+                            TimeoutResult::TIMEOUT => TimeoutResult::TIMEOUT,
+                        }
+                    }
+                    _ => continue,
+                },
+                KeyCode::Enter => return TimeoutResult::RESULT(()),
+                _ => continue,
             },
-            Ok(input::InputEvent::Rfid(rfid)) => {
-                rv_api::change_rfid(credentials, &rfid).unwrap();
-                utils::printline(terminal_io, "RFID changed successfully");
+            Err(RecvTimeoutError::Timeout) => TimeoutResult::TIMEOUT,
+            _ => continue,
+        };
+        if let TimeoutResult::TIMEOUT = result {
+            return TimeoutResult::TIMEOUT;
+        }
+        printline(terminal_io, "");
+    }
+}
+
+fn print_spending_limits(terminal_io: &mut TerminalIO, limits: &SpendingLimits) {
+    let cap = |cents: Option<i32>| match cents {
+        Some(cents) => format!("{}€", utils::format_money(&cents)),
+        None => "not set".to_string(),
+    };
+    printline(
+        terminal_io,
+        &format!("Daily limit: {}", cap(limits.daily_cents)),
+    );
+    printline(
+        terminal_io,
+        &format!("Weekly limit: {}", cap(limits.weekly_cents)),
+    );
+    for limit in limits.products.values() {
+        printline(
+            terminal_io,
+            &format!("{}: at most {} per day", limit.name, limit.count),
+        );
+    }
+}
+
+// Empty input removes the limit
+fn input_money_limit(
+    timeout: Duration,
+    terminal_io: &mut TerminalIO,
+) -> TimeoutResult<Option<i32>> {
+    loop {
+        execute!(
+            terminal_io.writer,
+            Print("Limit in euros, empty to remove: ")
+        )
+        .unwrap();
+        let input = match utils::readline(terminal_io, timeout) {
+            TimeoutResult::TIMEOUT => return TimeoutResult::TIMEOUT,
+            TimeoutResult::RESULT(s) => s,
+        };
+        printline(terminal_io, "");
+        if input.is_empty() {
+            return TimeoutResult::RESULT(None);
+        }
+        match utils::parse_money(&input) {
+            Some(cents) if cents > 0 => return TimeoutResult::RESULT(Some(cents as i32)),
+            _ => utils::print_error_line(terminal_io, "Invalid amount, try again"),
+        }
+    }
+}
+
+fn input_product_limit(
+    timeout: Duration,
+    terminal_io: &mut TerminalIO,
+    credentials: &rv_api::AuthenticationResponse,
+    limits: &mut SpendingLimits,
+) -> TimeoutResult<()> {
+    printline(terminal_io, "Scan or enter the product barcode");
+    let barcode = match utils::read_barcode(terminal_io, timeout) {
+        TimeoutResult::RESULT(Some(barcode)) => {
+            utils::resolve_barcode(terminal_io, credentials, barcode, false).code
+        }
+        TimeoutResult::RESULT(None) => return TimeoutResult::RESULT(()),
+        TimeoutResult::TIMEOUT => return TimeoutResult::TIMEOUT,
+    };
+    printline(terminal_io, "");
+    let Some(product) = rv_api::get_product_info(&terminal_io.config.api, credentials, &barcode)
+    else {
+        utils::print_error_line(terminal_io, "Product not found");
+        return TimeoutResult::RESULT(());
+    };
+    execute!(
+        terminal_io.writer,
+        Print(format!("Max {} per day, 0 to remove: ", product.name))
+    )
+    .unwrap();
+    let count = match utils::readline(terminal_io, timeout) {
+        TimeoutResult::TIMEOUT => return TimeoutResult::TIMEOUT,
+        TimeoutResult::RESULT(s) => s,
+    };
+    printline(terminal_io, "");
+    match count.trim().parse::<i32>() {
+        Ok(0) => {
+            limits.products.remove(&barcode);
+        }
+        Ok(count) if count > 0 => {
+            limits.products.insert(
+                barcode,
+                ProductLimit {
+                    name: product.name,
+                    count,
+                },
+            );
+        }
+        _ => utils::print_error_line(terminal_io, "Invalid count, limit not changed"),
+    }
+    TimeoutResult::RESULT(())
+}
+
+fn change_pin(timeout: Duration, terminal_io: &mut TerminalIO, user_id: i32) -> TimeoutResult<()> {
+    let dir = terminal_io.config.store.dir.clone();
+    print_title(terminal_io, "PIN code");
+    printline(
+        terminal_io,
+        "The PIN is asked after logging in with an RFID tag, e.g. if you lose your card",
+    );
+    execute!(
+        terminal_io.writer,
+        Print("New PIN (4-8 digits), empty to remove the PIN: ")
+    )
+    .unwrap();
+    let pin1 = match utils::readpasswd(terminal_io, timeout) {
+        TimeoutResult::TIMEOUT => return TimeoutResult::TIMEOUT,
+        TimeoutResult::RESULT(s) => s,
+    };
+    printline(terminal_io, "");
+
+    let pin = if pin1.is_empty() {
+        None
+    } else {
+        if !pin::is_valid_pin(&pin1) {
+            utils::print_error_line(terminal_io, "The PIN must be 4-8 digits. PIN not changed.");
+            return TimeoutResult::RESULT(());
+        }
+        execute!(terminal_io.writer, Print("Enter the PIN again: ")).unwrap();
+        let pin2 = match utils::readpasswd(terminal_io, timeout) {
+            TimeoutResult::TIMEOUT => return TimeoutResult::TIMEOUT,
+            TimeoutResult::RESULT(s) => s,
+        };
+        printline(terminal_io, "");
+        if pin1 != pin2 {
+            utils::print_error_line(terminal_io, "PINs do not match! PIN not changed.");
+            return TimeoutResult::RESULT(());
+        }
+        printline(terminal_io, "Ask the PIN always after an RFID login? [yN]");
+        printline(
+            terminal_io,
+            "Otherwise it is asked before deposits, settings, management mode and large purchases.",
+        );
+        let mode = match utils::confirm_with_default(terminal_io, utils::ConfirmResult::NO).unwrap()
+        {
+            utils::ConfirmResult::YES => PinMode::Always,
+            utils::ConfirmResult::NO => PinMode::Sensitive,
+            utils::ConfirmResult::TIMEOUT => return TimeoutResult::TIMEOUT,
+        };
+        Some(PinSettings::new(&pin1, mode))
+    };
+
+    let Some(mut prefs) = load_prefs(terminal_io) else {
+        return TimeoutResult::RESULT(());
+    };
+    let removed = pin.is_none();
+    prefs.user_mut(user_id).pin = pin;
+    match prefs.save(&dir) {
+        Ok(()) => {
+            log::info!("event=pin_change user_id={user_id} removed={removed}");
+            printline(
+                terminal_io,
+                match removed {
+                    true => "PIN removed",
+                    false => "PIN changed",
+                },
+            );
+        }
+        Err(e) => {
+            log::error!("event=prefs_save error={:?}", e.to_string());
+            utils::print_error_line(terminal_io, "Saving the PIN failed");
+        }
+    }
+    TimeoutResult::RESULT(())
+}
+
+fn read_totp_code(timeout: Duration, terminal_io: &mut TerminalIO) -> TimeoutResult<String> {
+    execute!(
+        terminal_io.writer,
+        Print("Enter the code shown by the app: ")
+    )
+    .unwrap();
+    let code = utils::readline(terminal_io, timeout);
+    printline(terminal_io, "");
+    code
+}
+
+fn enroll_totp(
+    timeout: Duration,
+    terminal_io: &mut TerminalIO,
+    credentials: &rv_api::AuthenticationResponse,
+    user_id: i32,
+    username: &str,
+) -> TimeoutResult<()> {
+    let mut totp = TotpSettings::generate();
+    // The QR code does not fit on the screen with the big font
+    utils::set_small_font(&terminal_io.config.font);
+    clear_terminal(terminal_io);
+    print_title(terminal_io, "Two-factor authentication");
+    printline(
+        terminal_io,
+        "Scan the QR code with an authenticator app or enter the secret manually:",
+    );
+    utils::print_qr(terminal_io, &totp.uri(username));
+    let grouped: Vec<String> = totp
+        .secret
+        .as_bytes()
+        .chunks(4)
+        .map(|chunk| String::from_utf8_lossy(chunk).to_string())
+        .collect();
+    printline(terminal_io, &grouped.join(" "));
+    printline(terminal_io, "");
+    let code = read_totp_code(timeout, terminal_io);
+    utils::set_big_font(&terminal_io.config.font);
+    let code = match code {
+        TimeoutResult::TIMEOUT => return TimeoutResult::TIMEOUT,
+        TimeoutResult::RESULT(code) => code,
+    };
+    // Typos are caught here, the backend checks the code again before enabling
+    if !totp.verify(&code, Utc::now().timestamp() as u64) {
+        utils::print_error_line(
+            terminal_io,
+            "The code did not match, two-factor authentication not enabled",
+        );
+        return TimeoutResult::RESULT(());
+    }
+
+    match rv_api::enroll_totp(
+        &terminal_io.config.api,
+        credentials,
+        &totp.secret,
+        code.trim(),
+    ) {
+        Ok(rv_api::ApiResult::Success) => {
+            log::info!("event=totp_enroll user_id={user_id}");
+            printline(
+                terminal_io,
+                "Two-factor authentication enabled, management mode now asks for a code",
+            );
+        }
+        Ok(rv_api::ApiResult::Fail(msg)) => utils::print_error_line(
+            terminal_io,
+            &format!("Enabling two-factor authentication failed: {msg}"),
+        ),
+        Err(e) => utils::print_error_line(
+            terminal_io,
+            &format!("Enabling two-factor authentication failed: {e}"),
+        ),
+    }
+    TimeoutResult::RESULT(())
+}
+
+fn change_two_factor(
+    timeout: Duration,
+    terminal_io: &mut TerminalIO,
+    credentials: &rv_api::AuthenticationResponse,
+    user_id: i32,
+    username: &str,
+) -> TimeoutResult<()> {
+    match rv_api::get_totp_enabled(&terminal_io.config.api, credentials) {
+        Ok(ApiResultValue::Success(true)) => (),
+        Ok(ApiResultValue::Success(false)) => {
+            return enroll_totp(timeout, terminal_io, credentials, user_id, username)
+        }
+        Ok(ApiResultValue::Fail(msg)) => {
+            utils::print_error_line(terminal_io, &msg);
+            return TimeoutResult::RESULT(());
+        }
+        Err(e) => {
+            utils::print_error_line(terminal_io, &e.to_string());
+            return TimeoutResult::RESULT(());
+        }
+    }
+    print_title(terminal_io, "Two-factor authentication");
+    printline(
+        terminal_io,
+        "Two-factor authentication is enabled. Disable it? [yN]",
+    );
+    match utils::confirm_with_default(terminal_io, utils::ConfirmResult::NO).unwrap() {
+        utils::ConfirmResult::YES => (),
+        utils::ConfirmResult::NO => return TimeoutResult::RESULT(()),
+        utils::ConfirmResult::TIMEOUT => return TimeoutResult::TIMEOUT,
+    }
+    let code = match read_totp_code(timeout, terminal_io) {
+        TimeoutResult::TIMEOUT => return TimeoutResult::TIMEOUT,
+        TimeoutResult::RESULT(code) => code,
+    };
+    match rv_api::delete_totp(&terminal_io.config.api, credentials, code.trim()) {
+        Ok(rv_api::ApiResult::Success) => {
+            log::info!("event=totp_remove user_id={user_id}");
+            printline(terminal_io, "Two-factor authentication disabled");
+        }
+        Ok(rv_api::ApiResult::Fail(msg)) => utils::print_error_line(
+            terminal_io,
+            &format!("Disabling two-factor authentication failed: {msg}"),
+        ),
+        Err(e) => utils::print_error_line(
+            terminal_io,
+            &format!("Disabling two-factor authentication failed: {e}"),
+        ),
+    }
+    TimeoutResult::RESULT(())
+}
+
+fn change_low_balance_warning(
+    timeout: Duration,
+    terminal_io: &mut TerminalIO,
+    user_id: i32,
+) -> TimeoutResult<()> {
+    let dir = terminal_io.config.store.dir.clone();
+    let default = terminal_io.config.balance.low_warning_cents;
+    print_title(terminal_io, "Low balance warning");
+    printline(
+        terminal_io,
+        "You will be warned after a purchase when your balance drops below this",
+    );
+    printline(
+        terminal_io,
+        &format!(
+            "Empty uses the default of {}€, 0 turns the warning off",
+            utils::format_money(&default)
+        ),
+    );
+    execute!(terminal_io.writer, Print("Warning threshold in euros: ")).unwrap();
+    let input = match utils::readline(terminal_io, timeout) {
+        TimeoutResult::TIMEOUT => return TimeoutResult::TIMEOUT,
+        TimeoutResult::RESULT(s) => s,
+    };
+    printline(terminal_io, "");
+    let threshold = match input.is_empty() {
+        true => None,
+        false => match utils::parse_money(&input) {
+            Some(cents) => Some(cents as i32),
+            None => {
+                utils::print_error_line(terminal_io, "Invalid amount, threshold not changed");
                 return TimeoutResult::RESULT(());
             }
-            _ => return TimeoutResult::RESULT(()),
+        },
+    };
+
+    let Some(mut prefs) = load_prefs(terminal_io) else {
+        return TimeoutResult::RESULT(());
+    };
+    prefs.user_mut(user_id).low_balance_cents = threshold;
+    match prefs.save(&dir) {
+        Ok(()) => printline(terminal_io, "Low balance warning changed"),
+        Err(e) => {
+            log::error!("event=prefs_save error={:?}", e.to_string());
+            utils::print_error_line(terminal_io, "Saving the low balance warning failed");
         }
     }
+    TimeoutResult::RESULT(())
 }
 
-pub fn settings_loop(
+fn change_spending_limits(
+    timeout: Duration,
+    terminal_io: &mut TerminalIO,
+    credentials: &rv_api::AuthenticationResponse,
+    user_id: i32,
+) -> TimeoutResult<()> {
+    let dir = terminal_io.config.store.dir.clone();
+    let Some(mut prefs) = load_prefs(terminal_io) else {
+        return TimeoutResult::RESULT(());
+    };
+    let mut limits = prefs.user(user_id).limits;
+    print_title(terminal_io, "Spending limits");
+    printline(
+        terminal_io,
+        "Purchases over a limit have to be confirmed separately",
+    );
+    loop {
+        print_spending_limits(terminal_io, &limits);
+        printline(terminal_io, "");
+        queue!(
+            terminal_io.writer,
+            PrintStyledContent("D".dark_green().bold()),
+            Print(" - set the daily limit\r\n"),
+            PrintStyledContent("W".dark_green().bold()),
+            Print(" - set the weekly limit\r\n"),
+            PrintStyledContent("P".dark_green().bold()),
+            Print(" - set a daily limit for a product\r\n"),
+            PrintStyledContent("<enter>".dark_green().bold()),
+            Print(" - save and return\r\n"),
+        )
+        .unwrap();
+        execute!(terminal_io.writer, Print("> ")).unwrap();
+        let result = match terminal_io.recv.recv_timeout(timeout) {
+            Ok(InputEvent::Terminal(Event::Key(ev))) => match ev.code {
+                KeyCode::Char(c) => match c.to_ascii_lowercase() {
+                    'd' => {
+                        printline(terminal_io, "");
+                        match input_money_limit(timeout, terminal_io) {
+                            TimeoutResult::RESULT(cap) => {
+                                limits.daily_cents = cap;
+                                TimeoutResult::RESULT(())
+                            }
+                            TimeoutResult::TIMEOUT => TimeoutResult::TIMEOUT,
+                        }
+                    }
+                    'w' => {
+                        printline(terminal_io, "");
+                        match input_money_limit(timeout, terminal_io) {
+                            TimeoutResult::RESULT(cap) => {
+                                limits.weekly_cents = cap;
+                                TimeoutResult::RESULT(())
+                            }
+                            TimeoutResult::TIMEOUT => TimeoutResult::TIMEOUT,
+                        }
+                    }
+                    'p' => {
+                        printline(terminal_io, "");
+                        input_product_limit(timeout, terminal_io, credentials, &mut limits)
+                    }
+                    _ => continue,
+                },
+                KeyCode::Enter => break,
+                _ => continue,
+            },
+            Err(RecvTimeoutError::Timeout) => TimeoutResult::TIMEOUT,
+            _ => continue,
+        };
+        if let TimeoutResult::TIMEOUT = result {
+            return TimeoutResult::TIMEOUT;
+        }
+        printline(terminal_io, "");
+    }
+
+    prefs.user_mut(user_id).limits = limits;
+    match prefs.save(&dir) {
+        Ok(()) => printline(terminal_io, "\r\nSpending limits saved"),
+        Err(e) => {
+            log::error!("event=prefs_save error={:?}", e.to_string());
+            utils::print_error_line(terminal_io, "\r\nSaving the spending limits failed");
+        }
+    }
+    TimeoutResult::RESULT(())
+}
+
+pub(super) fn print_anonymization_notice(terminal_io: &mut TerminalIO) {
+    printline(terminal_io, "When the account is closed:");
+    for line in [
+        "- the username, full name and email address are replaced with anonymous values",
+        "- purchase and deposit history is kept for bookkeeping without your name",
+        "- RFID tags are revoked and the password stops working",
+        "- spending limits, PIN and other settings on this terminal are deleted",
+        "- a closed account cannot be restored",
+    ] {
+        printline(terminal_io, line);
+    }
+}
+
+// Typing the phrase back, as in the deposit confirmation. Empty or a mismatch cancels.
+pub(super) fn confirm_phrase(
+    timeout: Duration,
+    terminal_io: &mut TerminalIO,
+    phrase: &str,
+) -> TimeoutResult<bool> {
+    execute!(
+        terminal_io.writer,
+        Print("PLEASE TYPE '"),
+        PrintStyledContent(phrase.with(Color::Black).on(Color::White)),
+        Print("' FOLLOWED BY <ENTER>: ")
+    )
+    .unwrap();
+    let input = match utils::readline(terminal_io, timeout) {
+        TimeoutResult::TIMEOUT => return TimeoutResult::TIMEOUT,
+        TimeoutResult::RESULT(s) => s,
+    };
+    printline(terminal_io, "");
+    if input.trim() != phrase {
+        utils::print_error_line(terminal_io, "Cancelled, the text does not match");
+        return TimeoutResult::RESULT(false);
+    }
+    TimeoutResult::RESULT(true)
+}
+
+// Settings of closed accounts are not kept on the terminal
+pub(super) fn forget_local_data(terminal_io: &mut TerminalIO, user_id: i32) {
+    let dir = terminal_io.config.store.dir.clone();
+    let Ok(mut prefs) = Prefs::load(&dir) else {
+        return;
+    };
+    if prefs.users.remove(&user_id).is_some() {
+        if let Err(e) = prefs.save(&dir) {
+            log::error!("event=prefs_save error={:?}", e.to_string());
+        }
+    }
+}
+
+// Returns true when the account was closed
+fn close_account(
+    timeout: Duration,
     terminal_io: &mut TerminalIO,
     credentials: &rv_api::AuthenticationResponse,
+) -> TimeoutResult<bool> {
+    print_title(terminal_io, "Close my account");
+    print_anonymization_notice(terminal_io);
+    printline(terminal_io, "");
+
+    let mut user_info = rv_api::get_user_info(&terminal_io.config.api, credentials).unwrap();
+    if user_info.money_balance < 0 {
+        printline(
+            terminal_io,
+            &format!(
+                "Your balance is {}€, it has to be cleared with a deposit before closing the account.\r\nDeposit now? [yN]",
+                utils::format_money(&user_info.money_balance)
+            ),
+        );
+        match utils::confirm_with_default(terminal_io, utils::ConfirmResult::NO).unwrap() {
+            utils::ConfirmResult::YES => (),
+            utils::ConfirmResult::NO => return TimeoutResult::RESULT(false),
+            utils::ConfirmResult::TIMEOUT => return TimeoutResult::TIMEOUT,
+        }
+        if let TimeoutResult::TIMEOUT = user::deposit(terminal_io, credentials) {
+            return TimeoutResult::TIMEOUT;
+        }
+        printline(terminal_io, "");
+        user_info = rv_api::get_user_info(&terminal_io.config.api, credentials).unwrap();
+        if user_info.money_balance < 0 {
+            utils::print_error_line(
+                terminal_io,
+                &format!(
+                    "Your balance is still {}€, account not closed. Bank transfers are credited once they have been confirmed.",
+                    utils::format_money(&user_info.money_balance)
+                ),
+            );
+            return TimeoutResult::RESULT(false);
+        }
+    }
+    let donate = user_info.money_balance > 0;
+    if donate {
+        printline(
+            terminal_io,
+            &format!(
+                "Your balance is {}€ and withdrawing money is not possible.\r\nDonate the remaining balance to TKO-äly? [yN]",
+                utils::format_money(&user_info.money_balance)
+            ),
+        );
+        match utils::confirm_with_default(terminal_io, utils::ConfirmResult::NO).unwrap() {
+            utils::ConfirmResult::YES => (),
+            utils::ConfirmResult::NO => return TimeoutResult::RESULT(false),
+            utils::ConfirmResult::TIMEOUT => return TimeoutResult::TIMEOUT,
+        }
+    }
+
+    execute!(terminal_io.writer, Print("Enter your password: ")).unwrap();
+    let password = match utils::readpasswd(terminal_io, timeout) {
+        TimeoutResult::TIMEOUT => return TimeoutResult::TIMEOUT,
+        TimeoutResult::RESULT(s) => s,
+    };
+    printline(terminal_io, "");
+    if password.is_empty() {
+        printline(terminal_io, "Cancelled");
+        return TimeoutResult::RESULT(false);
+    }
+    match confirm_phrase(
+        timeout,
+        terminal_io,
+        &format!("close {}", user_info.username),
+    ) {
+        TimeoutResult::TIMEOUT => return TimeoutResult::TIMEOUT,
+        TimeoutResult::RESULT(false) => return TimeoutResult::RESULT(false),
+        TimeoutResult::RESULT(true) => (),
+    }
+
+    match rv_api::deactivate_account(&terminal_io.config.api, credentials, &password, donate)
+        .unwrap()
+    {
+        rv_api::ApiResult::Success => {
+            log::info!(
+                "event=account_closure user_id={} donated={}",
+                user_info.user_id,
+                user_info.money_balance
+            );
+            forget_local_data(terminal_io, user_info.user_id);
+            printline(terminal_io, "Your account has been closed. Goodbye!");
+            utils::confirm_enter_to_continue(terminal_io);
+            TimeoutResult::RESULT(true)
+        }
+        rv_api::ApiResult::Fail(msg) => {
+            utils::print_error_line(terminal_io, &format!("Closing the account failed: {msg}"));
+            TimeoutResult::RESULT(false)
+        }
+    }
+}
+
+fn collect_data_export(
+    terminal_io: &mut TerminalIO,
+    credentials: &rv_api::AuthenticationResponse,
+) -> Result<DataExport, String> {
+    let user =
+        rv_api::get_user_info(&terminal_io.config.api, credentials).map_err(|e| e.to_string())?;
+    let deposits =
+        rv_api::deposit_history(&terminal_io.config.api, credentials).map_err(|e| e.to_string())?;
+    let rfid_tags = match rv_api::list_rfid_tags(&terminal_io.config.api, credentials)
+        .map_err(|e| e.to_string())?
+    {
+        ApiResultValue::Success(tags) => tags,
+        ApiResultValue::Fail(msg) => return Err(msg),
+    };
+    let two_factor_enabled = match rv_api::get_totp_enabled(&terminal_io.config.api, credentials)
+        .map_err(|e| e.to_string())?
+    {
+        ApiResultValue::Success(enabled) => enabled,
+        ApiResultValue::Fail(msg) => return Err(msg),
+    };
+    let prefs = Prefs::load(&terminal_io.config.store.dir)
+        .map_err(|e| format!("Reading the settings on this terminal failed: {e}"))?
+        .user(user.user_id);
+    Ok(DataExport {
+        generated: Utc::now(),
+        privacy: Privacy::new(user.privacy_level),
+        purchases: rv_api::purchase_history(&terminal_io.config.api, credentials),
+        deposits,
+        rfid_tags,
+        two_factor_enabled,
+        terminal_settings: TerminalSettings::from(&prefs),
+        user,
+    })
+}
+
+fn show_data_qr(
+    timeout: Duration,
+    terminal_io: &mut TerminalIO,
+    document: &str,
 ) -> TimeoutResult<()> {
+    let chunks = data_export::qr_chunks(document, QR_CHUNK_BYTES);
+    // The QR codes do not fit on the screen with the big font
+    utils::set_small_font(&terminal_io.config.font);
+    let mut result = TimeoutResult::RESULT(());
+    for (i, chunk) in chunks.iter().enumerate() {
+        clear_terminal(terminal_io);
+        print_title(
+            terminal_io,
+            &format!("Your data, part {}/{}", i + 1, chunks.len()),
+        );
+        utils::print_qr(terminal_io, chunk);
+        printline(
+            terminal_io,
+            "Scan the codes in order and join the parts after the 'RVDATA i/n:' prefix",
+        );
+        printline(
+            terminal_io,
+            "Press ENTER for the next part, any other key to stop",
+        );
+        match terminal_io.recv.recv_timeout(timeout) {
+            Ok(InputEvent::Terminal(Event::Key(KeyEvent {
+                code: KeyCode::Enter,
+                ..
+            }))) => (),
+            Err(RecvTimeoutError::Timeout) => {
+                result = TimeoutResult::TIMEOUT;
+                break;
+            }
+            _ => break,
+        }
+    }
+    utils::set_big_font(&terminal_io.config.font);
+    clear_terminal(terminal_io);
+    result
+}
+
+fn download_my_data(
+    timeout: Duration,
+    terminal_io: &mut TerminalIO,
+    credentials: &rv_api::AuthenticationResponse,
+) -> TimeoutResult<()> {
+    print_title(terminal_io, "Download my data");
+    printline(
+        terminal_io,
+        "Your account details, purchase and deposit history, RFID tags and the settings\r\n\
+         stored on this terminal are collected into a JSON document.",
+    );
+    let export = match collect_data_export(terminal_io, credentials) {
+        Ok(export) => export,
+        Err(e) => {
+            utils::print_error_line(terminal_io, &format!("Collecting your data failed: {e}"));
+            return TimeoutResult::RESULT(());
+        }
+    };
+    let document = export.to_json();
+    printline(
+        terminal_io,
+        &format!(
+            "{} purchases, {} deposits and {} RFID tags, {} kB",
+            export.purchases.len(),
+            export.deposits.len(),
+            export.rfid_tags.len(),
+            document.len().div_ceil(1024)
+        ),
+    );
+    queue!(
+        terminal_io.writer,
+        PrintStyledContent("Q".dark_green().bold()),
+        Print(format!(
+            " - show as {} QR codes\r\n",
+            data_export::qr_chunks(&document, QR_CHUNK_BYTES).len()
+        )),
+        PrintStyledContent("M".dark_green().bold()),
+        Print(format!(" - email to {}\r\n", export.user.email)),
+        PrintStyledContent("<enter>".dark_green().bold()),
+        Print(" - cancel\r\n"),
+    )
+    .unwrap();
+    execute!(terminal_io.writer, Print("> ")).unwrap();
+    loop {
+        match terminal_io.recv.recv_timeout(timeout) {
+            Ok(InputEvent::Terminal(Event::Key(ev))) => match ev.code {
+                KeyCode::Char(c) => match c.to_ascii_lowercase() {
+                    'q' => {
+                        log::info!(
+                            "event=data_export user_id={} method=qr",
+                            export.user.user_id
+                        );
+                        return show_data_qr(timeout, terminal_io, &document);
+                    }
+                    'm' => {
+                        printline(terminal_io, "");
+                        if !export.user.email_verified {
+                            utils::print_error_line(
+                                terminal_io,
+                                "Verify your email address first, see W in the settings",
+                            );
+                            return TimeoutResult::RESULT(());
+                        }
+                        match rv_api::email_data_export(
+                            &terminal_io.config.api,
+                            credentials,
+                            &document,
+                        ) {
+                            Ok(rv_api::ApiResult::Success) => {
+                                log::info!(
+                                    "event=data_export user_id={} method=email",
+                                    export.user.user_id
+                                );
+                                printline(
+                                    terminal_io,
+                                    &format!("Your data was sent to {}", export.user.email),
+                                );
+                            }
+                            Ok(rv_api::ApiResult::Fail(msg)) => {
+                                utils::print_error_line(terminal_io, &msg)
+                            }
+                            Err(e) => utils::print_error_line(
+                                terminal_io,
+                                &format!("Sending the email failed: {e}"),
+                            ),
+                        }
+                        return TimeoutResult::RESULT(());
+                    }
+                    _ => (),
+                },
+                KeyCode::Enter => return TimeoutResult::RESULT(()),
+                _ => (),
+            },
+            Err(RecvTimeoutError::Timeout) => return TimeoutResult::TIMEOUT,
+            _ => (),
+        }
+    }
+}
+
+// Returns true when the account was closed and the user has to be logged out
+pub fn settings_loop(
+    terminal_io: &mut TerminalIO,
+    credentials: &rv_api::AuthenticationResponse,
+) -> TimeoutResult<bool> {
     clear_terminal(terminal_io);
     'main: loop {
-        let user_info = rv_api::get_user_info(&credentials).unwrap();
+        let user_info = rv_api::get_user_info(&terminal_io.config.api, &credentials).unwrap();
+        let user_prefs =
+            Prefs::load(&terminal_io.config.store.dir).map(|prefs| prefs.user(user_info.user_id));
+        let (low_balance, pin) = match &user_prefs {
+            Ok(user_prefs) => (
+                match user_prefs.low_balance_threshold(terminal_io.config.balance.low_warning_cents)
+                {
+                    0 => "off".to_string(),
+                    cents => format!("{}€", utils::format_money(&cents)),
+                },
+                match user_prefs.pin.as_ref().map(|pin| pin.mode) {
+                    None => "not set",
+                    Some(PinMode::Always) => "asked after every RFID login",
+                    Some(PinMode::Sensitive) => "asked before sensitive actions",
+                },
+            ),
+            Err(_) => (
+                "could not be read".to_string(),
+                "could not be read, ask an admin for help",
+            ),
+        };
 
         clear_terminal(terminal_io);
 
@@ -258,13 +1212,22 @@ pub fn settings_loop(
             PrintStyledContent("Name: ".dark_green().bold()),
             Print(format!("{}\r\n", user_info.full_name)),
             PrintStyledContent("Email: ".dark_green().bold()),
-            Print(format!("{}\r\n", user_info.email)),
+            Print(format!("{} ", user_info.email)),
+            PrintStyledContent(match user_info.email_verified {
+                true => "".stylize(),
+                false => "(not verified)".red().bold(),
+            }),
+            Print("\r\n"),
             PrintStyledContent("Privacy level: ".dark_green().bold()),
             Print(format!("{}\r\n", user_info.privacy_level)),
+            PrintStyledContent("Low balance warning: ".dark_green().bold()),
+            Print(format!("{low_balance}\r\n")),
+            PrintStyledContent("PIN: ".dark_green().bold()),
+            Print(format!("{pin}\r\n")),
             Print("\r\n"),
             Print("Available commands (press key to select):\r\n"),
             PrintStyledContent("R".dark_green().bold()),
-            Print(" - manage your rfid\r\n"),
+            Print(" - manage your RFID tags\r\n"),
             PrintStyledContent("P".dark_green().bold()),
             Print(" - change your password\r\n"),
             PrintStyledContent("E".dark_green().bold()),
@@ -273,10 +1236,38 @@ pub fn settings_loop(
             Print(" - change your FULL name\r\n"),
             PrintStyledContent("V".dark_green().bold()),
             Print(" - change your privacy level\r\n"),
+            PrintStyledContent("L".dark_green().bold()),
+            Print(" - set your spending limits\r\n"),
+            PrintStyledContent("B".dark_green().bold()),
+            Print(" - set your low balance warning\r\n"),
+            PrintStyledContent("K".dark_green().bold()),
+            Print(" - set your PIN code for RFID logins\r\n"),
+            PrintStyledContent("D".dark_green().bold()),
+            Print(" - download my data\r\n"),
+            PrintStyledContent("C".dark_green().bold()),
+            Print(" - close my account\r\n"),
         )
         .unwrap();
 
-        if utils::is_barcode(&user_info.username) {
+        if user_info.is_admin() {
+            queue!(
+                terminal_io.writer,
+                PrintStyledContent("T".dark_green().bold()),
+                Print(" - two-factor authentication for management mode\r\n"),
+            )
+            .unwrap();
+        }
+
+        if !user_info.email_verified {
+            queue!(
+                terminal_io.writer,
+                PrintStyledContent("W".dark_green().bold()),
+                Print(" - verify your email address\r\n"),
+            )
+            .unwrap();
+        }
+
+        if barcode::is_barcode(&user_info.username) {
             queue!(
                 terminal_io.writer,
                 PrintStyledContent("U".dark_green().bold()),
@@ -302,15 +1293,17 @@ pub fn settings_loop(
         )
         .unwrap();
 
-        let mut command = String::new();
         loop {
-            match terminal_io.recv.recv_timeout(INPUT_TIMEOUT_LONG) {
+            match terminal_io
+                .recv
+                .recv_timeout(terminal_io.config.input.timeout_long())
+            {
                 Err(RecvTimeoutError::Timeout) => return TimeoutResult::TIMEOUT,
                 Ok(InputEvent::Terminal(Event::Key(ev))) => match ev.code {
                     KeyCode::Char(c) => match c.to_ascii_lowercase() {
                         'r' => {
                             printline(terminal_io, "");
-                            match change_user_rfid(terminal_io, &credentials) {
+                            match manage_rfid_tags(terminal_io, credentials) {
                                 TimeoutResult::TIMEOUT => return TimeoutResult::TIMEOUT,
                                 TimeoutResult::RESULT(_) => (),
                             }
@@ -319,7 +1312,95 @@ pub fn settings_loop(
                         }
                         'v' => {
                             printline(terminal_io, "");
-                            match change_privacy(INPUT_TIMEOUT_SHORT, terminal_io, &credentials) {
+                            match change_privacy(
+                                terminal_io.config.input.timeout_short(),
+                                terminal_io,
+                                &credentials,
+                            ) {
+                                TimeoutResult::TIMEOUT => return TimeoutResult::TIMEOUT,
+                                TimeoutResult::RESULT(_) => (),
+                            }
+                            printline(terminal_io, "");
+                            break;
+                        }
+                        't' => {
+                            if user_info.is_admin() {
+                                printline(terminal_io, "");
+                                match change_two_factor(
+                                    terminal_io.config.input.timeout_long(),
+                                    terminal_io,
+                                    credentials,
+                                    user_info.user_id,
+                                    &user_info.username,
+                                ) {
+                                    TimeoutResult::TIMEOUT => return TimeoutResult::TIMEOUT,
+                                    TimeoutResult::RESULT(_) => (),
+                                }
+                                printline(terminal_io, "");
+                            }
+                            break;
+                        }
+                        'k' => {
+                            printline(terminal_io, "");
+                            match change_pin(
+                                terminal_io.config.input.timeout_long(),
+                                terminal_io,
+                                user_info.user_id,
+                            ) {
+                                TimeoutResult::TIMEOUT => return TimeoutResult::TIMEOUT,
+                                TimeoutResult::RESULT(_) => (),
+                            }
+                            printline(terminal_io, "");
+                            break;
+                        }
+                        'd' => {
+                            printline(terminal_io, "");
+                            match download_my_data(
+                                terminal_io.config.input.timeout_long(),
+                                terminal_io,
+                                credentials,
+                            ) {
+                                TimeoutResult::TIMEOUT => return TimeoutResult::TIMEOUT,
+                                TimeoutResult::RESULT(_) => (),
+                            }
+                            printline(terminal_io, "");
+                            break;
+                        }
+                        'c' => {
+                            printline(terminal_io, "");
+                            match close_account(
+                                terminal_io.config.input.timeout_long(),
+                                terminal_io,
+                                credentials,
+                            ) {
+                                TimeoutResult::TIMEOUT => return TimeoutResult::TIMEOUT,
+                                TimeoutResult::RESULT(true) => return TimeoutResult::RESULT(true),
+                                TimeoutResult::RESULT(false) => (),
+                            }
+                            printline(terminal_io, "");
+                            break;
+                        }
+                        'b' => {
+                            printline(terminal_io, "");
+                            match change_low_balance_warning(
+                                terminal_io.config.input.timeout_long(),
+                                terminal_io,
+                                user_info.user_id,
+                            ) {
+                                TimeoutResult::TIMEOUT => return TimeoutResult::TIMEOUT,
+                                TimeoutResult::RESULT(_) => (),
+                            }
+                            printline(terminal_io, "");
+                            break;
+                        }
+                        'l' => {
+                            printline(terminal_io, "");
+                            match change_spending_limits(
+                                terminal_io.config.input.timeout_long(),
+                                terminal_io,
+                                credentials,
+                                user_info.user_id,
+                            ) {
                                 TimeoutResult::TIMEOUT => return TimeoutResult::TIMEOUT,
                                 TimeoutResult::RESULT(_) => (),
                             }
@@ -329,7 +1410,7 @@ pub fn settings_loop(
                         'p' => {
                             printline(terminal_io, "\n");
                             match change_user_password_user(
-                                INPUT_TIMEOUT_LONG,
+                                terminal_io.config.input.timeout_long(),
                                 terminal_io,
                                 &credentials,
                             ) {
@@ -341,7 +1422,11 @@ pub fn settings_loop(
                         }
                         'n' => {
                             printline(terminal_io, "");
-                            match change_real_name(INPUT_TIMEOUT_LONG, terminal_io, &credentials) {
+                            match change_real_name(
+                                terminal_io.config.input.timeout_long(),
+                                terminal_io,
+                                &credentials,
+                            ) {
                                 TimeoutResult::TIMEOUT => return TimeoutResult::TIMEOUT,
                                 TimeoutResult::RESULT(_) => (),
                             }
@@ -350,18 +1435,41 @@ pub fn settings_loop(
                         }
                         'e' => {
                             printline(terminal_io, "\n");
-                            match change_user_email(INPUT_TIMEOUT_LONG, terminal_io, &credentials) {
+                            match change_user_email(
+                                terminal_io.config.input.timeout_long(),
+                                terminal_io,
+                                &credentials,
+                            ) {
                                 TimeoutResult::TIMEOUT => return TimeoutResult::TIMEOUT,
                                 TimeoutResult::RESULT(_) => (),
                             }
                             printline(terminal_io, "");
                             break;
                         }
+                        'w' => {
+                            if !user_info.email_verified {
+                                printline(terminal_io, "");
+                                match verify_email(
+                                    terminal_io.config.input.timeout_long(),
+                                    terminal_io,
+                                    credentials,
+                                    &user_info.email,
+                                ) {
+                                    TimeoutResult::TIMEOUT => return TimeoutResult::TIMEOUT,
+                                    TimeoutResult::RESULT(_) => (),
+                                }
+                                printline(terminal_io, "");
+                            }
+                            break;
+                        }
                         'u' => {
-                            if utils::is_barcode(&user_info.username) {
+                            if barcode::is_barcode(&user_info.username) {
                                 printline(terminal_io, "");
-                                match change_username(INPUT_TIMEOUT_LONG, terminal_io, &credentials)
-                                {
+                                match change_username(
+                                    terminal_io.config.input.timeout_long(),
+                                    terminal_io,
+                                    &credentials,
+                                ) {
                                     TimeoutResult::TIMEOUT => return TimeoutResult::TIMEOUT,
                                     TimeoutResult::RESULT(_) => (),
                                 }
@@ -376,31 +1484,11 @@ pub fn settings_loop(
                         }
                         _ => {}
                     },
-                    KeyCode::Backspace => {
-                        if !command.is_empty() {
-                            execute!(
-                                terminal_io.writer,
-                                cursor::MoveLeft(1),
-                                Print(" "),
-                                cursor::MoveLeft(1)
-                            )
-                            .unwrap();
-                            command.pop();
-                        }
-                    }
                     KeyCode::Enter => {
-                        command = command.trim().to_string();
+                        // There are no typed commands, only the hotkeys above
                         utils::printline(terminal_io, "\r\n");
-                        if command.is_empty() {
-                            clear_terminal(terminal_io);
-                            break 'main;
-                        } else {
-                            utils::print_error_line(
-                                terminal_io,
-                                &format!("unknown command: {}\r\n", &command),
-                            );
-                            break;
-                        }
+                        clear_terminal(terminal_io);
+                        break 'main;
                     }
                     KeyCode::F(5) => {
                         break;
@@ -411,5 +1499,5 @@ pub fn settings_loop(
             }
         }
     }
-    TimeoutResult::RESULT(())
+    TimeoutResult::RESULT(false)
 }
@@ -0,0 +1,288 @@
+use super::user::print_user_loop_banner;
+use crate::rv_api::{self, PurchaseHistoryEvent};
+use crate::utils::{self, printline, TimeoutResult};
+use crate::TerminalIO;
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Timelike, Utc};
+use chrono_tz::{Europe::Helsinki, Tz};
+use crossterm::{
+    execute,
+    style::{Print, PrintStyledContent, Stylize},
+};
+use std::collections::HashMap;
+
+const WEEKS: usize = 8;
+const MONTHS: usize = 6;
+const TOP_PRODUCTS: usize = 5;
+const BAR_WIDTH: usize = 30;
+
+struct Purchase {
+    time: DateTime<Tz>,
+    name: String,
+    price: i32,
+}
+
+struct Period {
+    label: String,
+    spent: i32,
+}
+
+struct TopProduct {
+    name: String,
+    count: usize,
+    spent: i32,
+}
+
+struct Stats {
+    weeks: Vec<Period>,
+    months: Vec<Period>,
+    top_products: Vec<TopProduct>,
+    coffee_count: usize,
+    // (hour, purchase count)
+    busiest_hour: Option<(u32, usize)>,
+}
+
+// Returned purchases are not counted
+fn purchases(events: &[PurchaseHistoryEvent]) -> Vec<Purchase> {
+    events
+        .iter()
+        .filter(|event| !event.returned)
+        .filter_map(|event| {
+            Some(Purchase {
+                time: DateTime::parse_from_rfc3339(&event.time)
+                    .ok()?
+                    .with_timezone(&Helsinki),
+                name: event.product.name.clone(),
+                price: event.price,
+            })
+        })
+        .collect()
+}
+
+fn month_start(date: NaiveDate, months_back: u32) -> NaiveDate {
+    let months = date.year() * 12 + date.month0() as i32 - months_back as i32;
+    NaiveDate::from_ymd_opt(months.div_euclid(12), months.rem_euclid(12) as u32 + 1, 1).unwrap()
+}
+
+// Periods are listed oldest first, the last one is the current one
+fn compute(purchases: &[Purchase], now: DateTime<Tz>) -> Stats {
+    let today = now.date_naive();
+    let week_start = today - Duration::days(today.weekday().num_days_from_monday() as i64);
+
+    let weeks = (0..WEEKS)
+        .rev()
+        .map(|weeks_back| {
+            let start = week_start - Duration::weeks(weeks_back as i64);
+            let end = start + Duration::weeks(1);
+            Period {
+                label: format!("week {:>2}", start.iso_week().week()),
+                spent: spent_between(purchases, start, end),
+            }
+        })
+        .collect();
+
+    let months = (0..MONTHS as u32)
+        .rev()
+        .map(|months_back| {
+            let start = month_start(today, months_back);
+            let end = start.checked_add_months(chrono::Months::new(1)).unwrap();
+            Period {
+                label: start.format("%b %Y").to_string(),
+                spent: spent_between(purchases, start, end),
+            }
+        })
+        .collect();
+
+    let mut products: HashMap<&str, TopProduct> = HashMap::new();
+    let mut hours = [0usize; 24];
+    for purchase in purchases {
+        let product = products.entry(&purchase.name).or_insert(TopProduct {
+            name: purchase.name.clone(),
+            count: 0,
+            spent: 0,
+        });
+        product.count += 1;
+        product.spent += purchase.price;
+        hours[purchase.time.hour() as usize] += 1;
+    }
+    let mut top_products: Vec<TopProduct> = products.into_values().collect();
+    top_products.sort_by(|a, b| b.count.cmp(&a.count).then(a.name.cmp(&b.name)));
+    top_products.truncate(TOP_PRODUCTS);
+
+    let busiest_hour = hours
+        .iter()
+        .enumerate()
+        .filter(|(_, &count)| count > 0)
+        // The earliest hour wins a tie
+        .max_by(|a, b| a.1.cmp(b.1).then(b.0.cmp(&a.0)))
+        .map(|(hour, &count)| (hour as u32, count));
+
+    Stats {
+        weeks,
+        months,
+        top_products,
+        coffee_count: purchases
+            .iter()
+            .filter(|p| p.name.to_lowercase().contains("coffee"))
+            .count(),
+        busiest_hour,
+    }
+}
+
+fn spent_between(purchases: &[Purchase], start: NaiveDate, end: NaiveDate) -> i32 {
+    purchases
+        .iter()
+        .filter(|p| p.time.date_naive() >= start && p.time.date_naive() < end)
+        .map(|p| p.price)
+        .sum()
+}
+
+fn trend(current: i32, previous: i32) -> String {
+    match previous {
+        0 => "no purchases in the previous period".to_string(),
+        _ => {
+            let change = (current - previous) as f64 / previous as f64 * 100.0;
+            format!("{change:+.0}% compared with the previous period")
+        }
+    }
+}
+
+fn print_bar_chart(terminal_io: &mut TerminalIO, periods: &[Period]) {
+    let max = periods.iter().map(|p| p.spent).max().unwrap_or(0).max(1);
+    for period in periods {
+        let width = (period.spent as usize * BAR_WIDTH).div_ceil(max as usize);
+        execute!(
+            terminal_io.writer,
+            Print(format!("{:<9} ", period.label)),
+            PrintStyledContent(format!("{:<BAR_WIDTH$}", "█".repeat(width)).dark_green()),
+            Print(format!(" {:>7}€\r\n", utils::format_money(&period.spent))),
+        )
+        .unwrap();
+    }
+}
+
+fn print_trend(terminal_io: &mut TerminalIO, name: &str, periods: &[Period]) {
+    if let [.., previous, current] = periods {
+        printline(
+            terminal_io,
+            &format!(
+                "This {name} so far: {}",
+                trend(current.spent, previous.spent)
+            ),
+        );
+    }
+}
+
+pub fn stats_screen(
+    terminal_io: &mut TerminalIO,
+    credentials: &rv_api::AuthenticationResponse,
+) -> TimeoutResult<()> {
+    let purchases = purchases(&rv_api::purchase_history(
+        &terminal_io.config.api,
+        credentials,
+    ));
+    let stats = compute(&purchases, Utc::now().with_timezone(&Helsinki));
+
+    // The charts need the small font to fit on the screen
+    utils::set_small_font(&terminal_io.config.font);
+    utils::clear_terminal(terminal_io);
+    utils::print_title(terminal_io, "Your statistics");
+    if purchases.is_empty() {
+        printline(terminal_io, "No purchases yet.");
+    } else {
+        printline(terminal_io, "Spent per week:");
+        print_bar_chart(terminal_io, &stats.weeks);
+        print_trend(terminal_io, "week", &stats.weeks);
+        printline(terminal_io, "");
+
+        printline(terminal_io, "Spent per month:");
+        print_bar_chart(terminal_io, &stats.months);
+        print_trend(terminal_io, "month", &stats.months);
+        printline(terminal_io, "");
+
+        printline(terminal_io, "Top products:");
+        for (idx, product) in stats.top_products.iter().enumerate() {
+            printline(
+                terminal_io,
+                &format!(
+                    "{}. {:<30} {:>4}x {:>8}€",
+                    idx + 1,
+                    product.name.chars().take(30).collect::<String>(),
+                    product.count,
+                    utils::format_money(&product.spent)
+                ),
+            );
+        }
+        printline(terminal_io, "");
+        printline(
+            terminal_io,
+            &format!("Coffees bought: {}", stats.coffee_count),
+        );
+        if let Some((hour, count)) = stats.busiest_hour {
+            printline(
+                terminal_io,
+                &format!("Busiest hour: {hour:02}:00-{hour:02}:59 ({count} purchases)"),
+            );
+        }
+    }
+    printline(terminal_io, "");
+    let result = utils::confirm_enter_to_continue(terminal_io);
+    utils::set_big_font(&terminal_io.config.font);
+    print_user_loop_banner(terminal_io, credentials);
+    match result {
+        utils::ConfirmResult::TIMEOUT => TimeoutResult::TIMEOUT,
+        _ => TimeoutResult::RESULT(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn purchase(y: i32, m: u32, d: u32, hour: u32, name: &str, price: i32) -> Purchase {
+        Purchase {
+            time: Helsinki.with_ymd_and_hms(y, m, d, hour, 0, 0).unwrap(),
+            name: name.to_string(),
+            price,
+        }
+    }
+
+    #[test]
+    fn stats_compute_works() {
+        let purchases = vec![
+            purchase(2025, 1, 15, 9, "Coffee", 50),
+            purchase(2025, 1, 15, 9, "Coffee", 50),
+            purchase(2025, 2, 3, 14, "Sandwich", 300),
+            purchase(2025, 2, 5, 9, "Coffee", 50),
+            purchase(2024, 12, 31, 23, "Candy", 120),
+        ];
+        // Wednesday, week 6
+        let stats = compute(
+            &purchases,
+            Helsinki.with_ymd_and_hms(2025, 2, 5, 12, 0, 0).unwrap(),
+        );
+
+        assert_eq!(stats.weeks.len(), WEEKS);
+        assert_eq!(stats.weeks.last().unwrap().label, "week  6");
+        assert_eq!(stats.weeks.last().unwrap().spent, 350);
+        assert_eq!(stats.weeks[WEEKS - 4].spent, 100);
+
+        let months: Vec<(&str, i32)> = stats
+            .months
+            .iter()
+            .map(|m| (m.label.as_str(), m.spent))
+            .collect();
+        assert_eq!(
+            months[MONTHS - 3..],
+            [("Dec 2024", 120), ("Jan 2025", 100), ("Feb 2025", 350)]
+        );
+
+        assert_eq!(stats.top_products[0].name, "Coffee");
+        assert_eq!(stats.top_products[0].count, 3);
+        assert_eq!(stats.top_products[0].spent, 150);
+        assert_eq!(stats.coffee_count, 3);
+        assert_eq!(stats.busiest_hour, Some((9, 3)));
+        assert_eq!(trend(350, 100), "+250% compared with the previous period");
+    }
+}
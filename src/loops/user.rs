@@ -1,7 +1,16 @@
+use super::load_prefs;
 use super::management;
+use super::onboarding;
+use super::pin_gate::PinGate;
 use super::setting;
+use super::stats;
 
+use crate::batches::{self, Batches};
 use crate::input;
+use crate::line_editor::{Action, Echo, LineEditor};
+use crate::prefs::Spent;
+use crate::receipt;
+use crate::reference;
 use crate::rv_api;
 use crate::rv_api::get_product_info;
 use crate::rv_api::get_user_info;
@@ -10,28 +19,29 @@ use crate::rv_api::ApiResultPurchaseItem;
 use crate::rv_api::ApiResultPurchaseItemFailType;
 use crate::rv_api::UserInfoTrait;
 use crate::utils;
+use crate::utils::check_barcode;
 use crate::utils::load_ascii;
 use crate::utils::print_error_line;
 use crate::utils::print_title;
 use crate::utils::printline;
 use crate::utils::purchase_fail_bell;
+use crate::utils::read_barcode;
 use crate::utils::readline;
 use crate::utils::readline_barcode;
+use crate::utils::resolve_barcode;
 use crate::utils::TimeoutResult;
 use crate::TerminalIO;
-use crate::INPUT_TIMEOUT_LONG;
-use crate::INPUT_TIMEOUT_SHORT;
 
-use chrono::{DateTime, Local};
+use chrono::{DateTime, Local, Utc};
 use crossterm::{
     cursor,
     event::{Event, KeyCode},
     execute, queue,
     style::{Color, Print, PrintStyledContent, Stylize},
     terminal::{self, disable_raw_mode},
-    ExecutableCommand,
 };
 use input::InputEvent;
+use receipt::{Receipt, Transaction};
 use regex::Regex;
 use rv_api::ApiResult;
 use std::process::exit;
@@ -41,7 +51,7 @@ use std::thread::sleep;
 use std::time::Duration;
 static PURCHASE_FAILED_MSG1: LazyLock<String> = load_ascii!("../../ascii/purchase_failed.txt");
 static PURCHASE_FAILED_MSG2: LazyLock<String> = load_ascii!("../../ascii/purchase_failed2.txt");
-static COFFEE_MSG: LazyLock<String> = load_ascii!("../../ascii/netlight.txt");
+pub(super) static COFFEE_MSG: LazyLock<String> = load_ascii!("../../ascii/netlight.txt");
 
 fn return_purchase(
     terminal_io: &mut TerminalIO,
@@ -50,23 +60,20 @@ fn return_purchase(
     utils::print_title(terminal_io, "Return recent purchase");
 
     utils::printline(terminal_io, "Enter product barcode: ");
-    let barcode = match readline_barcode(terminal_io, INPUT_TIMEOUT_SHORT) {
-        TimeoutResult::RESULT(s) => {
-            if Regex::new("^[0-9]+$").unwrap().is_match(&s) {
-                s
-            } else {
-                print_error_line(terminal_io, "Invalid barcode!");
-
-                std::thread::sleep(std::time::Duration::from_millis(2000));
-                return TimeoutResult::RESULT(());
-            }
+    let barcode = match read_barcode(terminal_io, terminal_io.config.input.timeout_short()) {
+        TimeoutResult::RESULT(Some(barcode)) => {
+            resolve_barcode(terminal_io, credentials, barcode, false).code
+        }
+        TimeoutResult::RESULT(None) => {
+            std::thread::sleep(std::time::Duration::from_millis(2000));
+            return TimeoutResult::RESULT(());
         }
         TimeoutResult::TIMEOUT => return TimeoutResult::TIMEOUT,
     };
 
-    match return_product(credentials, &barcode).unwrap() {
+    match return_product(&terminal_io.config.api, credentials, &barcode).unwrap() {
         ApiResult::Success => {
-            let product = get_product_info(credentials, &barcode).unwrap();
+            let product = get_product_info(&terminal_io.config.api, credentials, &barcode).unwrap();
             printline(
                 terminal_io,
                 &format!("\nReturned product: {} successfully", product.name),
@@ -80,38 +87,42 @@ fn return_purchase(
 fn multibuy(
     terminal_io: &mut TerminalIO,
     credentials: &rv_api::AuthenticationResponse,
-) -> TimeoutResult<()> {
+    pin_gate: &mut PinGate,
+) -> TimeoutResult<Option<Transaction>> {
     print_title(terminal_io, "Multibuy");
 
     utils::printline(terminal_io, "Enter item barcode: ");
-    let barcode = match readline_barcode(terminal_io, INPUT_TIMEOUT_LONG) {
-        TimeoutResult::RESULT(s) => {
-            if Regex::new("^[0-9]+$").unwrap().is_match(&s) {
-                s
-            } else {
-                print_error_line(terminal_io, "Invalid barcode!");
-                std::thread::sleep(std::time::Duration::from_millis(2000));
-                return TimeoutResult::RESULT(());
-            }
+    let barcode = match read_barcode(terminal_io, terminal_io.config.input.timeout_long()) {
+        TimeoutResult::RESULT(Some(barcode)) => {
+            resolve_barcode(terminal_io, credentials, barcode, false).code
+        }
+        TimeoutResult::RESULT(None) => {
+            std::thread::sleep(std::time::Duration::from_millis(2000));
+            return TimeoutResult::RESULT(None);
         }
         TimeoutResult::TIMEOUT => return TimeoutResult::TIMEOUT,
     };
 
     utils::printline(terminal_io, "Enter item count to buy: ");
-    let count: i32 = match readline(terminal_io, INPUT_TIMEOUT_LONG) {
+    let count: i32 = match readline(terminal_io, terminal_io.config.input.timeout_long()) {
         TimeoutResult::RESULT(s) => {
             if Regex::new("^[1-9][0-9]*$").unwrap().is_match(&s) {
                 s.parse().unwrap()
             } else {
                 print_error_line(terminal_io, "Invalid count!");
                 std::thread::sleep(std::time::Duration::from_millis(2000));
-                return TimeoutResult::RESULT(());
+                return TimeoutResult::RESULT(None);
             }
         }
         TimeoutResult::TIMEOUT => return TimeoutResult::TIMEOUT,
     };
-    purchase_items(&barcode, count, terminal_io, credentials);
-    TimeoutResult::RESULT(())
+    TimeoutResult::RESULT(purchase_items(
+        &barcode,
+        count,
+        terminal_io,
+        credentials,
+        pin_gate,
+    ))
 }
 
 fn purchase_items(
@@ -119,14 +130,26 @@ fn purchase_items(
     count: i32,
     terminal_io: &mut TerminalIO,
     credentials: &rv_api::AuthenticationResponse,
-) {
-    match rv_api::purchase_item(&credentials, &barcode, &count).unwrap() {
+    pin_gate: &mut PinGate,
+) -> Option<Transaction> {
+    let notes = match check_before_purchase(barcode, count, terminal_io, credentials, pin_gate) {
+        PurchaseCheck::Allowed(notes) => notes,
+        PurchaseCheck::Blocked => return None,
+    };
+    match rv_api::purchase_item(&terminal_io.config.api, &credentials, &barcode, &count).unwrap() {
         ApiResultPurchaseItem::Success => {
-            let product_info = rv_api::get_product_info(&credentials, &barcode).unwrap();
+            let product_info =
+                rv_api::get_product_info(&terminal_io.config.api, &credentials, &barcode).unwrap();
+            log::info!(
+                "event=purchase barcode={barcode:?} product={:?} count={count} price={}",
+                product_info.name,
+                product_info.price
+            );
             if product_info.name == "Coffee" {
                 // Coffee purchase shill
                 utils::printline(terminal_io, &COFFEE_MSG);
             }
+            consume_batches(terminal_io, barcode, count);
             utils::printline(
                 terminal_io,
                 &format!(
@@ -137,11 +160,19 @@ fn purchase_items(
                     utils::format_money(&(count * product_info.price))
                 ),
             );
+            if let Some(notes) = &notes {
+                print_purchase_notes(terminal_io, notes);
+            }
+            Some(Transaction::Purchase(count as usize))
         }
         ApiResultPurchaseItem::Fail(x) => {
+            log::warn!(
+                "event=purchase_failed barcode={barcode:?} count={count} error={:?}",
+                x.message
+            );
             purchase_fail_bell();
-            let user_info = get_user_info(credentials).unwrap();
-            utils::set_small_font();
+            let user_info = get_user_info(&terminal_io.config.api, credentials).unwrap();
+            utils::set_small_font(&terminal_io.config.font);
             execute!(
                 terminal_io.writer,
                 PrintStyledContent(PURCHASE_FAILED_MSG1.to_string().green()),
@@ -164,8 +195,162 @@ fn purchase_items(
                 // Discard all input until channel is empty
             }
             utils::confirm_enter_to_continue(terminal_io);
-            utils::set_big_font();
+            utils::set_big_font(&terminal_io.config.font);
+            None
+        }
+    }
+}
+
+// Known before the purchase, shown after it has succeeded
+struct PurchaseNotes {
+    // Remaining allowance if the user has set spending limits
+    remaining: Option<String>,
+    balance_after: i32,
+    low_balance: i32,
+}
+
+enum PurchaseCheck {
+    // None if the product is unknown, the purchase itself will fail then
+    Allowed(Option<PurchaseNotes>),
+    Blocked,
+}
+
+// Purchases exceeding the user's spending limits, taking the balance below zero or the
+// low balance limit, and products whose oldest batch has expired need a confirmation,
+// large ones the PIN after an RFID login
+fn check_before_purchase(
+    barcode: &str,
+    count: i32,
+    terminal_io: &mut TerminalIO,
+    credentials: &rv_api::AuthenticationResponse,
+    pin_gate: &mut PinGate,
+) -> PurchaseCheck {
+    let Some(product_info) = get_product_info(&terminal_io.config.api, credentials, barcode) else {
+        return PurchaseCheck::Allowed(None);
+    };
+    let user_info = get_user_info(&terminal_io.config.api, credentials).unwrap();
+    // Unreadable settings need the same confirmation as exceeding a limit
+    let user_prefs = load_prefs(terminal_io).map(|prefs| prefs.user(user_info.user_id));
+    let limits_unknown = user_prefs.is_none();
+    let user_prefs = user_prefs.unwrap_or_default();
+    let total = count * product_info.price;
+    if total > terminal_io.config.pin.purchase_threshold_cents {
+        match pin_gate.check(terminal_io, user_info.user_id) {
+            TimeoutResult::RESULT(true) => (),
+            _ => return PurchaseCheck::Blocked,
+        }
+    }
+    let mut notes = PurchaseNotes {
+        remaining: None,
+        balance_after: user_info.money_balance - total,
+        low_balance: user_prefs.low_balance_threshold(terminal_io.config.balance.low_warning_cents),
+    };
+
+    let mut warnings = Vec::new();
+    if !user_prefs.limits.is_empty() {
+        let spent = Spent::from_history(
+            &rv_api::purchase_history(&terminal_io.config.api, credentials),
+            batches::today(),
+        );
+        notes.remaining = user_prefs.limits.remaining(&spent, barcode, count, total);
+        warnings = user_prefs.limits.check(&spent, barcode, count, total);
+    }
+    if limits_unknown {
+        warnings.push("Your spending limits could not be checked".to_string());
+    }
+    if let Some(oldest) = Batches::load(&terminal_io.config.store.dir)
+        .ok()
+        .and_then(|batches| batches.oldest(barcode).cloned())
+        .filter(|oldest| oldest.best_before < batches::today())
+    {
+        log::warn!(
+            "event=expired_purchase barcode={barcode:?} best_before={}",
+            oldest.best_before
+        );
+        warnings.push(format!(
+            "The oldest {} in stock was best before {}, check the package before eating!",
+            product_info.name,
+            oldest.best_before.format("%d.%m.%Y")
+        ));
+    }
+    if notes.balance_after < 0 {
+        warnings.push(format!(
+            "Your balance is {}€, this purchase of {}€ would leave it at {}€",
+            utils::format_money(&user_info.money_balance),
+            utils::format_money(&total),
+            utils::format_money(&notes.balance_after)
+        ));
+    } else if notes.balance_after < notes.low_balance {
+        warnings.push(format!(
+            "This purchase of {}€ would leave your balance at {}€, below your limit of {}€",
+            utils::format_money(&total),
+            utils::format_money(&notes.balance_after),
+            utils::format_money(&notes.low_balance)
+        ));
+    }
+    if warnings.is_empty() {
+        return PurchaseCheck::Allowed(Some(notes));
+    }
+
+    purchase_fail_bell();
+    for message in &warnings {
+        print_error_line(terminal_io, message);
+    }
+    printline(terminal_io, "Buy anyway? [yN]");
+    match utils::confirm_with_default(terminal_io, utils::ConfirmResult::NO).unwrap() {
+        utils::ConfirmResult::YES => {
+            log::info!(
+                "event=purchase_override user_id={} barcode={barcode:?} count={count} balance_after={}",
+                user_info.user_id,
+                notes.balance_after
+            );
+            PurchaseCheck::Allowed(Some(notes))
         }
+        _ => {
+            printline(terminal_io, "Purchase cancelled.");
+            PurchaseCheck::Blocked
+        }
+    }
+}
+
+fn print_purchase_notes(terminal_io: &mut TerminalIO, notes: &PurchaseNotes) {
+    if let Some(remaining) = &notes.remaining {
+        printline(terminal_io, remaining);
+    }
+    if notes.balance_after < 0 {
+        print_error_line(
+            terminal_io,
+            &format!(
+                "Your balance is negative ({}€), please deposit money!",
+                utils::format_money(&notes.balance_after)
+            ),
+        );
+    } else if notes.balance_after < notes.low_balance {
+        execute!(
+            terminal_io.writer,
+            PrintStyledContent("NOTICE".yellow()),
+            Print(format!(
+                ": your balance is below {}€ ({}€), deposit soon\r\n",
+                utils::format_money(&notes.low_balance),
+                utils::format_money(&notes.balance_after)
+            ))
+        )
+        .unwrap();
+    }
+}
+
+// Sold items are taken from the oldest tracked batches
+fn consume_batches(terminal_io: &TerminalIO, barcode: &str, count: i32) {
+    let dir = &terminal_io.config.store.dir;
+    let Ok(mut batches) = Batches::load(dir) else {
+        return;
+    };
+    if batches.oldest(barcode).is_none() {
+        return;
+    }
+    batches.consume(barcode, count);
+    if let Err(e) = batches.save(dir) {
+        log::error!("event=batches_save error={:?}", e.to_string());
     }
 }
 
@@ -175,14 +360,15 @@ pub fn search_products(
 ) -> TimeoutResult<()> {
     print_title(terminal_io, "Product search");
     printline(terminal_io, "Enter name or barcode");
-    let query = match readline_barcode(terminal_io, INPUT_TIMEOUT_SHORT) {
+    let query = match readline_barcode(terminal_io, terminal_io.config.input.timeout_short()) {
         TimeoutResult::RESULT(s) => s,
         TimeoutResult::TIMEOUT => return TimeoutResult::TIMEOUT,
     };
-    let product_results = rv_api::search_products(credentials, &query).unwrap();
-    let user_info = get_user_info(credentials).unwrap();
+    let product_results =
+        rv_api::search_products(&terminal_io.config.api, credentials, &query).unwrap();
+    let user_info = get_user_info(&terminal_io.config.api, credentials).unwrap();
     let box_results = match user_info.is_admin() {
-        true => rv_api::search_boxes(credentials, &query).unwrap(),
+        true => rv_api::search_boxes(&terminal_io.config.api, credentials, &query).unwrap(),
         false => Vec::new(),
     };
     if product_results.is_empty() && box_results.is_empty() {
@@ -235,44 +421,37 @@ pub fn search_products(
     TimeoutResult::RESULT(())
 }
 
-fn deposit(
+pub(super) fn deposit(
     terminal_io: &mut TerminalIO,
     credentials: &rv_api::AuthenticationResponse,
-) -> TimeoutResult<()> {
+) -> TimeoutResult<Option<Transaction>> {
     print_title(terminal_io, "Deposit money");
     utils::printline(
         terminal_io,
         "How much to deposit? Format: [0-9]+((\\.|,)[0-9][0-9])?",
     );
     utils::printline(terminal_io, "At least one number, optionally followed by a period or comma followed by two numbers. For example: '1', '0.10', '14,42'");
-    let input_line = match utils::readline(terminal_io, INPUT_TIMEOUT_LONG) {
+    let input_line = match utils::readline(terminal_io, terminal_io.config.input.timeout_long()) {
         TimeoutResult::TIMEOUT => return TimeoutResult::TIMEOUT,
         TimeoutResult::RESULT(s) => s,
     };
-    if !Regex::new("^[0-9]+((\\.|,)[0-9][0-9])?$")
-        .unwrap()
-        .is_match(&input_line)
-    {
+    let Some(amount) = utils::parse_money(&input_line) else {
         printline(terminal_io, "");
         utils::print_error_line(terminal_io, "Invalid input. Deposit aborted!");
-        return TimeoutResult::RESULT(());
-    }
-    let amount: u32 = if input_line.contains(".") {
-        input_line.replace(".", "").parse().unwrap()
-    } else if input_line.contains(",") {
-        input_line.replace(",", "").parse().unwrap()
-    } else {
-        // No decimals specified, multiply by 100 to get cents
-        input_line.parse::<u32>().unwrap() * 100
+        return TimeoutResult::RESULT(None);
     };
 
-    if amount > 25000 {
+    let max_amount = terminal_io.config.deposit.max_cents;
+    if amount > max_amount {
         printline(terminal_io, "");
         utils::print_error_line(
             terminal_io,
-            "You can deposit at most 250 EUR at once. Deposit aborted!",
+            &format!(
+                "You can deposit at most {} EUR at once. Deposit aborted!",
+                utils::format_money(&(max_amount as i32))
+            ),
         );
-        return TimeoutResult::RESULT(());
+        return TimeoutResult::RESULT(None);
     }
 
     let amount_formatted = utils::format_money(&(amount as i32));
@@ -303,7 +482,7 @@ fn deposit(
     )
     .unwrap();
 
-    match utils::readline(terminal_io, INPUT_TIMEOUT_SHORT) {
+    match utils::readline(terminal_io, terminal_io.config.input.timeout_short()) {
         TimeoutResult::TIMEOUT => {
             utils::printline(terminal_io, "\r\nTimed out!");
             std::thread::sleep(std::time::Duration::from_millis(2000));
@@ -312,13 +491,13 @@ fn deposit(
         TimeoutResult::RESULT(s) => {
             if s.len() == 0 {
                 utils::printline(terminal_io, "\r\nDeposit aborted! Cancelled by user.");
-                return TimeoutResult::RESULT(());
+                return TimeoutResult::RESULT(None);
             } else if s.replace(",", ".") != amount_formatted {
                 utils::print_error_line(
                     terminal_io,
                     "\r\nDeposit aborted! Given amounts do not match.",
                 );
-                return TimeoutResult::RESULT(());
+                return TimeoutResult::RESULT(None);
             }
         }
     };
@@ -327,7 +506,7 @@ fn deposit(
         execute!(
             terminal_io.writer,
             Print("\r\n"),
-            Print("Did you deposit money as cash or via banktransfer?\r\n"),
+            Print("Did you deposit money as cash, or will you pay it via bank transfer?\r\n"),
             Print("PLEASE TYPE EITHER '"),
             PrintStyledContent("cash".with(Color::Black).on(Color::White)),
             Print("' OR '"),
@@ -335,7 +514,7 @@ fn deposit(
             Print("' FOLLOWED BY <ENTER>:\r\n"),
         )
         .unwrap();
-        match utils::readline(terminal_io, INPUT_TIMEOUT_SHORT) {
+        match utils::readline(terminal_io, terminal_io.config.input.timeout_short()) {
             TimeoutResult::TIMEOUT => {
                 utils::printline(terminal_io, "\r\nTimed out!");
                 std::thread::sleep(std::time::Duration::from_millis(2000));
@@ -344,9 +523,11 @@ fn deposit(
             TimeoutResult::RESULT(s) => {
                 if s.len() == 0 {
                     utils::printline(terminal_io, "\r\nDeposit aborted! Cancelled by user.");
-                    return TimeoutResult::RESULT(());
+                    return TimeoutResult::RESULT(None);
                 } else if s == "cash" {
-                    rv_api::deposit(&credentials, &amount, "cash").unwrap();
+                    rv_api::deposit(&terminal_io.config.api, &credentials, &amount, "cash")
+                        .unwrap();
+                    log::info!("event=deposit type=cash amount={amount}");
                     utils::printline(terminal_io, "Remember to put cash in an envelope or send an email immediately to rahastonhoitaja@tko-aly.fi to explain a non-envelope deposit.");
                     utils::printline(
                         terminal_io,
@@ -356,8 +537,7 @@ fn deposit(
                     utils::confirm_enter_to_continue(terminal_io);
                     break;
                 } else if s == "bank" {
-                    rv_api::deposit(&credentials, &amount, "banktransfer").unwrap();
-                    break;
+                    return bank_transfer_deposit(terminal_io, credentials, amount);
                 } else {
                     print_error_line(terminal_io, "Invalid deposit type entered!");
                 }
@@ -372,14 +552,138 @@ fn deposit(
             utils::format_money(&(amount as i32))
         ),
     );
-    TimeoutResult::RESULT(())
+    TimeoutResult::RESULT(Some(Transaction::Deposit))
+}
+
+// The amount is credited once an admin imports the bank statement with the
+// transfer, see loops::bank
+fn bank_transfer_deposit(
+    terminal_io: &mut TerminalIO,
+    credentials: &rv_api::AuthenticationResponse,
+    amount: u32,
+) -> TimeoutResult<Option<Transaction>> {
+    let user_id = get_user_info(&terminal_io.config.api, credentials)
+        .unwrap()
+        .user_id;
+    let reference = reference::deposit_reference(user_id, Utc::now().timestamp());
+    match rv_api::create_pending_deposit(&terminal_io.config.api, credentials, amount, &reference)
+        .unwrap()
+    {
+        ApiResult::Success => (),
+        ApiResult::Fail(msg) => {
+            print_error_line(terminal_io, &format!("Deposit failed: {msg}"));
+            return TimeoutResult::RESULT(None);
+        }
+    }
+    log::info!("event=deposit type=banktransfer amount={amount} pending=true");
+    let config = &terminal_io.config.deposit;
+    let account = match config.iban.is_empty() {
+        true => String::new(),
+        false => format!(
+            "Recipient: {}\r\nAccount: {}\r\n",
+            config.recipient, config.iban
+        ),
+    };
+    execute!(
+        terminal_io.writer,
+        Print("\r\n"),
+        Print(account),
+        Print(format!(
+            "Amount: {} EUR\r\nReference: ",
+            utils::format_money(&(amount as i32))
+        )),
+        PrintStyledContent(
+            reference::display(&reference)
+                .with(Color::Black)
+                .on(Color::White)
+        ),
+        Print("\r\n\r\n"),
+    )
+    .unwrap();
+    printline(
+        terminal_io,
+        "Pay the transfer with exactly this reference and amount. Your balance is credited once the payment shows up on the bank statement.",
+    );
+    utils::confirm_enter_to_continue(terminal_io);
+    TimeoutResult::RESULT(None)
+}
+
+fn show_receipt(
+    terminal_io: &mut TerminalIO,
+    credentials: &rv_api::AuthenticationResponse,
+    transaction: &Option<Transaction>,
+) -> TimeoutResult<()> {
+    print_title(terminal_io, "Receipt");
+    let Some(transaction) = transaction else {
+        printline(
+            terminal_io,
+            "No purchase or deposit made during this session.",
+        );
+        return TimeoutResult::RESULT(());
+    };
+    let Some(receipt) = Receipt::fetch(&terminal_io.config.api, credentials, transaction) else {
+        print_error_line(
+            terminal_io,
+            "Could not fetch the transaction for the receipt",
+        );
+        return TimeoutResult::RESULT(());
+    };
+
+    if let Some(printer) = terminal_io.config.receipt.printer.clone() {
+        printline(
+            terminal_io,
+            "Print the receipt or show it as a QR code? [pq] or Enter to cancel.",
+        );
+        loop {
+            match terminal_io
+                .recv
+                .recv_timeout(terminal_io.config.input.timeout_short())
+            {
+                Err(RecvTimeoutError::Timeout) => return TimeoutResult::TIMEOUT,
+                Ok(InputEvent::Terminal(Event::Key(ev))) => match ev.code {
+                    KeyCode::Enter => return TimeoutResult::RESULT(()),
+                    KeyCode::Char(c) => match c.to_ascii_lowercase() {
+                        'p' => {
+                            match receipt::print(&printer, &receipt) {
+                                Ok(_) => printline(terminal_io, "Receipt printed."),
+                                Err(e) => print_error_line(
+                                    terminal_io,
+                                    &format!("Printing the receipt failed: {e}"),
+                                ),
+                            }
+                            return TimeoutResult::RESULT(());
+                        }
+                        'q' => break,
+                        _ => (),
+                    },
+                    _ => (),
+                },
+                _ => (),
+            }
+        }
+    }
+
+    // QR code needs the small font to fit on the screen
+    utils::set_small_font(&terminal_io.config.font);
+    utils::clear_terminal(terminal_io);
+    let text = receipt.to_text();
+    printline(terminal_io, &text.replace("\n", "\r\n"));
+    printline(terminal_io, "");
+    utils::print_qr(terminal_io, &text);
+    let result = utils::confirm_enter_to_continue(terminal_io);
+    utils::set_big_font(&terminal_io.config.font);
+    print_user_loop_banner(terminal_io, credentials);
+    match result {
+        utils::ConfirmResult::TIMEOUT => TimeoutResult::TIMEOUT,
+        _ => TimeoutResult::RESULT(()),
+    }
 }
 
 fn print_user_loop_instructions(
     terminal_io: &mut TerminalIO,
     credentials: &rv_api::AuthenticationResponse,
 ) {
-    let user_info = rv_api::get_user_info(&credentials).unwrap();
+    let user_info = rv_api::get_user_info(&terminal_io.config.api, &credentials).unwrap();
     queue!(
         terminal_io.writer,
         cursor::MoveTo(0, terminal::size()?.1),
@@ -394,8 +698,12 @@ fn print_user_loop_instructions(
         Print(" - list matching products\r\n"),
         PrintStyledContent("H".dark_green().bold()),
         Print(" - show purchase history\r\n"),
+        PrintStyledContent("T".dark_green().bold()),
+        Print(" - show your spending statistics\r\n"),
         PrintStyledContent("U".dark_green().bold()),
         Print(" - undo a recent purchase\r\n"),
+        PrintStyledContent("R".dark_green().bold()),
+        Print(" - get a receipt of your last purchase or deposit\r\n"),
         PrintStyledContent("S".dark_green().bold()),
         Print(" - change settings\r\n"),
         PrintStyledContent("C".dark_green().bold()),
@@ -415,7 +723,7 @@ fn print_user_loop_instructions(
     }
 }
 
-fn print_user_loop_banner(
+pub(super) fn print_user_loop_banner(
     terminal_io: &mut TerminalIO,
     credentials: &rv_api::AuthenticationResponse,
 ) {
@@ -424,11 +732,23 @@ fn print_user_loop_banner(
     printline(terminal_io, "");
 }
 
-pub fn user_loop(terminal_io: &mut TerminalIO, credentials: &rv_api::AuthenticationResponse) {
+pub(super) fn user_loop(
+    terminal_io: &mut TerminalIO,
+    credentials: &rv_api::AuthenticationResponse,
+    mut pin_gate: PinGate,
+) {
+    // The login PIN has been checked with the account details
+    let user_info = rv_api::get_user_info(&terminal_io.config.api, credentials).unwrap();
+    if let TimeoutResult::TIMEOUT =
+        onboarding::onboarding_tour(terminal_io, credentials, &user_info, &mut pin_gate)
+    {
+        return;
+    }
     print_user_loop_banner(terminal_io, credentials);
+    let mut last_transaction: Option<Transaction> = None;
 
     'main: loop {
-        let user_info = rv_api::get_user_info(&credentials).unwrap();
+        let user_info = rv_api::get_user_info(&terminal_io.config.api, &credentials).unwrap();
         execute!(
             terminal_io.writer,
             Print(&format!(
@@ -439,30 +759,42 @@ pub fn user_loop(terminal_io: &mut TerminalIO, credentials: &rv_api::Authenticat
         )
         .unwrap();
 
-        let mut command = String::new();
+        let mut editor = LineEditor::new(Echo::Plain).accepting(|c| c.is_ascii_digit());
         loop {
-            match terminal_io.recv.recv_timeout(INPUT_TIMEOUT_SHORT) {
+            match terminal_io
+                .recv
+                .recv_timeout(terminal_io.config.input.timeout_short())
+            {
                 Err(RecvTimeoutError::Timeout) => {
                     utils::printline(terminal_io, "Timed out!");
                     std::thread::sleep(std::time::Duration::from_millis(2000));
                     break 'main;
                 }
                 Ok(input::InputEvent::Terminal(Event::Key(ev))) => match ev.code {
-                    KeyCode::Char(c) => match c.to_ascii_lowercase() {
+                    KeyCode::Char(c) if c.is_ascii_alphabetic() => match c.to_ascii_lowercase() {
                         'b' => {
                             printline(terminal_io, "\n");
-                            match multibuy(terminal_io, credentials) {
+                            match multibuy(terminal_io, credentials, &mut pin_gate) {
                                 TimeoutResult::TIMEOUT => break 'main,
-                                _ => (),
+                                TimeoutResult::RESULT(tx) => {
+                                    last_transaction = tx.or(last_transaction)
+                                }
                             }
                             printline(terminal_io, "");
                             break;
                         }
                         'd' => {
                             printline(terminal_io, "\n");
+                            match pin_gate.check(terminal_io, user_info.user_id) {
+                                TimeoutResult::TIMEOUT => break 'main,
+                                TimeoutResult::RESULT(false) => break,
+                                TimeoutResult::RESULT(true) => (),
+                            }
                             match deposit(terminal_io, &credentials) {
                                 TimeoutResult::TIMEOUT => break 'main,
-                                _ => (),
+                                TimeoutResult::RESULT(tx) => {
+                                    last_transaction = tx.or(last_transaction)
+                                }
                             }
                             printline(terminal_io, "");
                             break;
@@ -479,7 +811,8 @@ pub fn user_loop(terminal_io: &mut TerminalIO, credentials: &rv_api::Authenticat
                         'h' => {
                             printline(terminal_io, "\n");
                             print_title(terminal_io, "Recent purchases");
-                            let mut events = rv_api::purchase_history(credentials);
+                            let mut events =
+                                rv_api::purchase_history(&terminal_io.config.api, credentials);
                             events.sort_by(|a, b| b.time.cmp(&a.time));
                             events.iter().take(10).rev().for_each(|event| {
                                 printline(
@@ -503,9 +836,23 @@ pub fn user_loop(terminal_io: &mut TerminalIO, credentials: &rv_api::Authenticat
                             printline(terminal_io, "");
                             break;
                         }
+                        't' => {
+                            printline(terminal_io, "\n");
+                            if let TimeoutResult::TIMEOUT =
+                                stats::stats_screen(terminal_io, credentials)
+                            {
+                                break 'main;
+                            }
+                            break;
+                        }
                         'm' => {
                             if user_info.is_admin() {
                                 printline(terminal_io, "\n");
+                                match pin_gate.check(terminal_io, user_info.user_id) {
+                                    TimeoutResult::TIMEOUT => break 'main,
+                                    TimeoutResult::RESULT(false) => break,
+                                    TimeoutResult::RESULT(true) => (),
+                                }
                                 match management::management_mode_loop(terminal_io, credentials) {
                                     TimeoutResult::TIMEOUT => break 'main,
                                     _ => (),
@@ -516,11 +863,25 @@ pub fn user_loop(terminal_io: &mut TerminalIO, credentials: &rv_api::Authenticat
                         }
                         's' => {
                             printline(terminal_io, "\n");
+                            match pin_gate.check(terminal_io, user_info.user_id) {
+                                TimeoutResult::TIMEOUT => break 'main,
+                                TimeoutResult::RESULT(false) => break,
+                                TimeoutResult::RESULT(true) => (),
+                            }
                             match setting::settings_loop(terminal_io, credentials) {
+                                TimeoutResult::TIMEOUT | TimeoutResult::RESULT(true) => break 'main,
+                                TimeoutResult::RESULT(false) => (),
+                            }
+                            print_user_loop_instructions(terminal_io, credentials);
+                            break;
+                        }
+                        'r' => {
+                            printline(terminal_io, "\n");
+                            match show_receipt(terminal_io, credentials, &last_transaction) {
                                 TimeoutResult::TIMEOUT => break 'main,
                                 _ => (),
                             }
-                            print_user_loop_instructions(terminal_io, credentials);
+                            printline(terminal_io, "");
                             break;
                         }
                         'u' => {
@@ -542,56 +903,66 @@ pub fn user_loop(terminal_io: &mut TerminalIO, credentials: &rv_api::Authenticat
                             // after logging in
                             break print_user_loop_banner(terminal_io, credentials);
                         }
-                        '0'..='9' => {
-                            terminal_io.writer.execute(Print(c)).unwrap();
-                            command.push(c);
-                        }
                         _ => (),
                     },
-                    KeyCode::Backspace => {
-                        if !command.is_empty() {
-                            execute!(
-                                terminal_io.writer,
-                                cursor::MoveLeft(1),
-                                Print(" "),
-                                cursor::MoveLeft(1)
-                            )
-                            .unwrap();
-                            command.pop();
-                        }
-                    }
-                    KeyCode::Enter => {
-                        command = command.trim().to_string();
-                        utils::printline(terminal_io, "\r\n");
-                        if command.is_empty() {
-                            break 'main; // Logout
-                        } else if command == "exit" {
-                            disable_raw_mode().unwrap();
-                            exit(0);
-                        } else if Regex::new("^[0-9]+$").expect("").is_match(&command) {
-                            purchase_items(&command, 1, terminal_io, credentials);
-                            printline(terminal_io, "");
-                            break;
-                        } else {
-                            utils::print_error_line(
-                                terminal_io,
-                                &format!("unknown command: {}\r\n", &command),
-                            );
-                            break;
-                        }
-                    }
                     KeyCode::F(5) => {
                         break;
                     }
-                    _ => (),
+                    _ => match editor.handle_key(&ev) {
+                        Action::Submit => {
+                            let command = editor.text();
+                            utils::printline(terminal_io, "\r\n");
+                            if command.is_empty() {
+                                break 'main; // Logout
+                            } else if command == "exit" {
+                                disable_raw_mode().unwrap();
+                                exit(0);
+                            } else if Regex::new("^[0-9]+$").expect("").is_match(&command) {
+                                if let Some(barcode) = check_barcode(terminal_io, &command, false) {
+                                    let barcode =
+                                        resolve_barcode(terminal_io, credentials, barcode, false);
+                                    last_transaction = purchase_items(
+                                        &barcode.code,
+                                        1,
+                                        terminal_io,
+                                        credentials,
+                                        &mut pin_gate,
+                                    )
+                                    .or(last_transaction);
+                                }
+                                printline(terminal_io, "");
+                                break;
+                            } else {
+                                utils::print_error_line(
+                                    terminal_io,
+                                    &format!("unknown command: {}\r\n", &command),
+                                );
+                                break;
+                            }
+                        }
+                        Action::Edited => editor.render(&mut terminal_io.writer, None).unwrap(),
+                        Action::Ignored => (),
+                    },
                 },
+                Ok(InputEvent::Terminal(Event::Paste(text))) => {
+                    if let Action::Edited = editor.insert_str(&text) {
+                        editor.render(&mut terminal_io.writer, None).unwrap();
+                    }
+                }
                 Ok(InputEvent::Barcode(barcode)) => {
-                    let trimmed_barcode = barcode.trim();
-                    if Regex::new("^[0-9]+$").expect("").is_match(trimmed_barcode) {
-                        purchase_items(&trimmed_barcode, 1, terminal_io, credentials);
-                        printline(terminal_io, "");
-                        break;
+                    if let Some(barcode) = check_barcode(terminal_io, &barcode, true) {
+                        let barcode = resolve_barcode(terminal_io, credentials, barcode, false);
+                        last_transaction = purchase_items(
+                            &barcode.code,
+                            1,
+                            terminal_io,
+                            credentials,
+                            &mut pin_gate,
+                        )
+                        .or(last_transaction);
                     }
+                    printline(terminal_io, "");
+                    break;
                 }
                 Ok(InputEvent::Rfid(_)) => {
                     // Logout
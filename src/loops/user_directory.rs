@@ -0,0 +1,778 @@
+use super::management::{send_temp_password_admin, set_user_password_admin};
+use super::setting::{confirm_phrase, forget_local_data, print_anonymization_notice};
+use crate::audit::{AuditAction, AuditEntry, AuditLog};
+use crate::rv_api::{self, ApiResult, ApiResultValue, UserInfo, UserInfoTrait};
+use crate::utils::{self, print_error_line, print_title, printline, ConfirmResult, TimeoutResult};
+use crate::TerminalIO;
+
+use chrono::{DateTime, Utc};
+use chrono_tz::Europe::Helsinki;
+use crossterm::{
+    execute,
+    style::{Color, Print, PrintStyledContent, Stylize},
+};
+use std::time::Duration;
+
+const PAGE_SIZE: usize = 10;
+const RECENT_EVENTS: usize = 5;
+const REFUNDABLE_EVENTS: usize = 20;
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + (ca != *cb) as usize;
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+fn is_subsequence(query: &str, text: &str) -> bool {
+    let mut chars = text.chars();
+    query.chars().all(|q| chars.any(|c| c == q))
+}
+
+// Higher is a better match, None if the text does not match at all
+fn match_score(query: &str, text: &str) -> Option<u32> {
+    let text = text.to_lowercase();
+    if text == query {
+        Some(100)
+    } else if text.starts_with(query) {
+        Some(80)
+    } else if text.contains(query) {
+        Some(60)
+    } else if query.chars().count() >= 3 && is_subsequence(query, &text) {
+        Some(30)
+    } else {
+        // Typos, one per four letters of the query
+        let allowed = query.chars().count() / 4;
+        text.split(|c: char| !c.is_alphanumeric())
+            .any(|word| allowed > 0 && edit_distance(query, word) <= allowed)
+            .then_some(20)
+    }
+}
+
+// Users matching the query by username, full name or email, best matches first
+fn search<'a>(users: &'a [UserInfo], query: &str) -> Vec<&'a UserInfo> {
+    let query = query.trim().to_lowercase();
+    let mut results: Vec<(u32, &UserInfo)> = users
+        .iter()
+        .filter_map(|user| {
+            [&user.username, &user.full_name, &user.email]
+                .iter()
+                .filter_map(|text| match_score(&query, text))
+                .max()
+                .map(|score| (score, user))
+        })
+        .collect();
+    results.sort_by(|(a_score, a), (b_score, b)| {
+        b_score
+            .cmp(a_score)
+            .then_with(|| a.username.cmp(&b.username))
+    });
+    results.into_iter().map(|(_, user)| user).collect()
+}
+
+pub(super) fn user_directory(
+    timeout: Duration,
+    terminal_io: &mut TerminalIO,
+    credentials: &rv_api::AuthenticationResponse,
+) -> TimeoutResult<()> {
+    print_title(terminal_io, "User directory");
+    execute!(
+        terminal_io.writer,
+        Print("Search by username, full name or email: ")
+    )
+    .unwrap();
+    let query = match utils::readline(terminal_io, timeout) {
+        TimeoutResult::TIMEOUT => return TimeoutResult::TIMEOUT,
+        TimeoutResult::RESULT(s) => s,
+    };
+    printline(terminal_io, "");
+    if query.trim().is_empty() {
+        return TimeoutResult::RESULT(());
+    }
+
+    let users = match rv_api::get_users_admin(&terminal_io.config.api, credentials) {
+        Ok(ApiResultValue::Success(users)) => users,
+        Ok(ApiResultValue::Fail(msg)) => {
+            print_error_line(terminal_io, &msg);
+            return TimeoutResult::RESULT(());
+        }
+        Err(e) => {
+            print_error_line(terminal_io, &format!("Fetching users failed: {e}"));
+            return TimeoutResult::RESULT(());
+        }
+    };
+    let admin = rv_api::get_user_info(&terminal_io.config.api, credentials).unwrap();
+    let results = search(&users, &query);
+    if results.is_empty() {
+        printline(terminal_io, &format!("No users found with query {query}"));
+        return TimeoutResult::RESULT(());
+    }
+
+    let pages = results.len().div_ceil(PAGE_SIZE);
+    let mut page = 0;
+    loop {
+        printline(
+            terminal_io,
+            &format!(
+                "\r\n{} users found, page {}/{}",
+                results.len(),
+                page + 1,
+                pages
+            ),
+        );
+        for (i, user) in results
+            .iter()
+            .enumerate()
+            .skip(page * PAGE_SIZE)
+            .take(PAGE_SIZE)
+        {
+            printline(
+                terminal_io,
+                &format!(
+                    "{:>3}. {} ({}) {}{}",
+                    i + 1,
+                    user.username,
+                    user.full_name,
+                    user.email,
+                    unverified_note(user)
+                ),
+            );
+        }
+        execute!(
+            terminal_io.writer,
+            Print("Number to open, N next page, P previous page, ENTER to exit: ")
+        )
+        .unwrap();
+        let input = match utils::readline(terminal_io, timeout) {
+            TimeoutResult::TIMEOUT => return TimeoutResult::TIMEOUT,
+            TimeoutResult::RESULT(s) => s.trim().to_lowercase(),
+        };
+        printline(terminal_io, "");
+        match input.as_str() {
+            "" => return TimeoutResult::RESULT(()),
+            "n" => page = (page + 1).min(pages - 1),
+            "p" => page = page.saturating_sub(1),
+            number => {
+                let user = number
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|number| results.get(number.checked_sub(1)?));
+                match user {
+                    Some(user) => {
+                        if let TimeoutResult::TIMEOUT =
+                            user_details(timeout, terminal_io, credentials, &admin, &user.username)
+                        {
+                            return TimeoutResult::TIMEOUT;
+                        }
+                    }
+                    None => print_error_line(terminal_io, "No such user"),
+                }
+            }
+        }
+    }
+}
+
+fn unverified_note(user: &UserInfo) -> &'static str {
+    match user.email_verified {
+        true => "",
+        false => " (not verified)",
+    }
+}
+
+fn local_time(time: &str) -> String {
+    match DateTime::parse_from_rfc3339(time) {
+        Ok(time) => time
+            .with_timezone(&Helsinki)
+            .format("%d/%m/%Y %H:%M")
+            .to_string(),
+        Err(_) => time.to_string(),
+    }
+}
+
+fn print_user_details(
+    terminal_io: &mut TerminalIO,
+    credentials: &rv_api::AuthenticationResponse,
+    user: &UserInfo,
+) {
+    print_title(terminal_io, &format!("User {}", user.username));
+    for (label, value) in [
+        ("User id: ", user.user_id.to_string()),
+        ("Full name: ", user.full_name.clone()),
+        (
+            "Email: ",
+            format!("{}{}", user.email, unverified_note(user)),
+        ),
+        ("Role: ", user.role.clone()),
+        ("Privacy level: ", user.privacy_level.to_string()),
+        (
+            "Balance: ",
+            format!("{}€", utils::format_money(&user.money_balance)),
+        ),
+    ] {
+        execute!(
+            terminal_io.writer,
+            PrintStyledContent(label.dark_green().bold()),
+            Print(format!("{value}\r\n")),
+        )
+        .unwrap();
+    }
+
+    printline(terminal_io, "\r\nRecent purchases:");
+    match rv_api::purchase_history_admin(&terminal_io.config.api, credentials, user.user_id) {
+        Ok(ApiResultValue::Success(mut purchases)) => {
+            purchases.sort_by(|a, b| b.time.cmp(&a.time));
+            for event in purchases.iter().take(RECENT_EVENTS).rev() {
+                printline(
+                    terminal_io,
+                    &format!(
+                        "{} {} {}€{}",
+                        local_time(&event.time),
+                        event.product.name,
+                        utils::format_money(&event.price),
+                        if event.returned { " (returned)" } else { "" }
+                    ),
+                );
+            }
+        }
+        Ok(ApiResultValue::Fail(msg)) => print_error_line(terminal_io, &msg),
+        Err(e) => print_error_line(terminal_io, &e.to_string()),
+    }
+    printline(terminal_io, "\r\nRecent deposits:");
+    match rv_api::deposit_history_admin(&terminal_io.config.api, credentials, user.user_id) {
+        Ok(ApiResultValue::Success(mut deposits)) => {
+            deposits.sort_by(|a, b| b.time.cmp(&a.time));
+            for event in deposits.iter().take(RECENT_EVENTS).rev() {
+                printline(
+                    terminal_io,
+                    &format!(
+                        "{} {}€",
+                        local_time(&event.time),
+                        utils::format_money(&event.amount)
+                    ),
+                );
+            }
+        }
+        Ok(ApiResultValue::Fail(msg)) => print_error_line(terminal_io, &msg),
+        Err(e) => print_error_line(terminal_io, &e.to_string()),
+    }
+    let audit_log = match AuditLog::load(&terminal_io.config.store.dir) {
+        Ok(audit_log) => audit_log,
+        Err(e) => {
+            print_error_line(terminal_io, &format!("Reading the audit log failed: {e}"));
+            AuditLog::default()
+        }
+    };
+    let adjustments = audit_log.for_user(user.user_id, RECENT_EVENTS);
+    if !adjustments.is_empty() {
+        printline(terminal_io, "\r\nBalance adjustments on this terminal:");
+        for entry in adjustments.iter().rev() {
+            let what = match &entry.action {
+                AuditAction::BalanceAdjustment => "adjustment".to_string(),
+                AuditAction::PurchaseRefund { product, .. } => format!("refund of {product}"),
+                AuditAction::AccountClosure => "account closed, donated".to_string(),
+            };
+            printline(
+                terminal_io,
+                &format!(
+                    "{} {what} {}€ by {}: {}",
+                    entry.time.with_timezone(&Helsinki).format("%d/%m/%Y %H:%M"),
+                    utils::format_money(&entry.amount),
+                    entry.admin,
+                    entry.reason
+                ),
+            );
+        }
+    }
+
+    execute!(
+        terminal_io.writer,
+        Print("\r\n"),
+        PrintStyledContent("P".dark_green().bold()),
+        Print(" - set a new password\r\n"),
+        PrintStyledContent("E".dark_green().bold()),
+        Print(" - generate temppassword and send it to the user\r\n"),
+        PrintStyledContent("O".dark_green().bold()),
+        Print(" - change role\r\n"),
+        PrintStyledContent("B".dark_green().bold()),
+        Print(" - adjust balance\r\n"),
+        PrintStyledContent("F".dark_green().bold()),
+        Print(" - refund a purchase\r\n"),
+        PrintStyledContent("C".dark_green().bold()),
+        Print(" - close the account, e.g. when requested by email\r\n"),
+        PrintStyledContent("<enter>".dark_green().bold()),
+        Print(" - back to the results\r\n"),
+    )
+    .unwrap();
+}
+
+fn user_details(
+    timeout: Duration,
+    terminal_io: &mut TerminalIO,
+    credentials: &rv_api::AuthenticationResponse,
+    admin: &UserInfo,
+    username: &str,
+) -> TimeoutResult<()> {
+    loop {
+        // Fetched again after every action to show the current balance and role
+        let user =
+            match rv_api::get_user_info_by_username(&terminal_io.config.api, credentials, username)
+            {
+                Ok(ApiResultValue::Success(user)) => user,
+                Ok(ApiResultValue::Fail(msg)) => {
+                    print_error_line(terminal_io, &msg);
+                    return TimeoutResult::RESULT(());
+                }
+                Err(e) => {
+                    print_error_line(terminal_io, &format!("Fetching the user failed: {e}"));
+                    return TimeoutResult::RESULT(());
+                }
+            };
+        print_user_details(terminal_io, credentials, &user);
+        execute!(terminal_io.writer, Print("> ")).unwrap();
+        let input = match utils::readline(terminal_io, timeout) {
+            TimeoutResult::TIMEOUT => return TimeoutResult::TIMEOUT,
+            TimeoutResult::RESULT(s) => s.trim().to_lowercase(),
+        };
+        printline(terminal_io, "");
+        let result = match input.as_str() {
+            "" => return TimeoutResult::RESULT(()),
+            "p" => set_user_password_admin(timeout, terminal_io, credentials, &user),
+            "e" => send_temp_password_admin(terminal_io, credentials, &user),
+            "o" => change_role(terminal_io, credentials, &user),
+            "b" => adjust_balance(timeout, terminal_io, credentials, admin, &user),
+            "f" => refund_purchase(timeout, terminal_io, credentials, admin, &user),
+            "c" => match close_account(timeout, terminal_io, credentials, admin, &user) {
+                TimeoutResult::RESULT(true) => return TimeoutResult::RESULT(()),
+                TimeoutResult::RESULT(false) => TimeoutResult::RESULT(()),
+                TimeoutResult::TIMEOUT => TimeoutResult::TIMEOUT,
+            },
+            _ => {
+                print_error_line(terminal_io, &format!("unknown command: {input}"));
+                TimeoutResult::RESULT(())
+            }
+        };
+        if let TimeoutResult::TIMEOUT = result {
+            return TimeoutResult::TIMEOUT;
+        }
+    }
+}
+
+fn change_role(
+    terminal_io: &mut TerminalIO,
+    credentials: &rv_api::AuthenticationResponse,
+    user: &UserInfo,
+) -> TimeoutResult<()> {
+    let role = match user.is_admin() {
+        true => "USER",
+        false => "ADMIN",
+    };
+    printline(
+        terminal_io,
+        &format!(
+            "Change the role of {} from {} to {role}? [yN]",
+            user.username, user.role
+        ),
+    );
+    match utils::confirm_with_default(terminal_io, ConfirmResult::NO).unwrap() {
+        ConfirmResult::YES => (),
+        ConfirmResult::NO => return TimeoutResult::RESULT(()),
+        ConfirmResult::TIMEOUT => return TimeoutResult::TIMEOUT,
+    }
+    match rv_api::change_role_admin(&terminal_io.config.api, credentials, user.user_id, role)
+        .unwrap()
+    {
+        ApiResult::Success => {
+            log::info!("event=change_role user_id={} role={role}", user.user_id);
+            printline(terminal_io, &format!("{} is now {role}", user.username));
+        }
+        ApiResult::Fail(msg) => {
+            print_error_line(terminal_io, &format!("Changing the role failed: {msg}"));
+        }
+    }
+    TimeoutResult::RESULT(())
+}
+
+// Empty cancels the action
+fn read_reason(timeout: Duration, terminal_io: &mut TerminalIO) -> TimeoutResult<Option<String>> {
+    execute!(terminal_io.writer, Print("Reason (required): ")).unwrap();
+    let reason = match utils::readline(terminal_io, timeout) {
+        TimeoutResult::TIMEOUT => return TimeoutResult::TIMEOUT,
+        TimeoutResult::RESULT(s) => s.trim().to_string(),
+    };
+    printline(terminal_io, "");
+    if reason.is_empty() {
+        printline(terminal_io, "Cancelled, a reason is required");
+        return TimeoutResult::RESULT(None);
+    }
+    TimeoutResult::RESULT(Some(reason))
+}
+
+// A yes/no question followed by typing the amount, as in the deposit confirmation
+fn confirm_balance_change(
+    timeout: Duration,
+    terminal_io: &mut TerminalIO,
+    question: &str,
+    amount: i32,
+) -> TimeoutResult<bool> {
+    printline(terminal_io, &format!("{question} [yN]"));
+    match utils::confirm_with_default(terminal_io, ConfirmResult::NO).unwrap() {
+        ConfirmResult::YES => (),
+        ConfirmResult::NO => return TimeoutResult::RESULT(false),
+        ConfirmResult::TIMEOUT => return TimeoutResult::TIMEOUT,
+    }
+    let amount_formatted = utils::format_money(&amount);
+    execute!(
+        terminal_io.writer,
+        Print("PLEASE TYPE '"),
+        PrintStyledContent(amount_formatted.clone().with(Color::Black).on(Color::White)),
+        Print("' FOLLOWED BY <ENTER>: ")
+    )
+    .unwrap();
+    let input = match utils::readline(terminal_io, timeout) {
+        TimeoutResult::TIMEOUT => return TimeoutResult::TIMEOUT,
+        TimeoutResult::RESULT(s) => s.trim().replace(',', "."),
+    };
+    printline(terminal_io, "");
+    if input != amount_formatted {
+        print_error_line(terminal_io, "Cancelled, the amounts do not match");
+        return TimeoutResult::RESULT(false);
+    }
+    TimeoutResult::RESULT(true)
+}
+
+fn record_audit(
+    terminal_io: &mut TerminalIO,
+    admin: &UserInfo,
+    user: &UserInfo,
+    action: AuditAction,
+    amount: i32,
+    reason: String,
+) {
+    let entry = AuditEntry {
+        time: Utc::now(),
+        admin_id: admin.user_id,
+        admin: admin.username.clone(),
+        user_id: user.user_id,
+        username: user.username.clone(),
+        action,
+        amount,
+        reason,
+    };
+    if let Err(e) = AuditLog::record(&terminal_io.config.store.dir, entry) {
+        log::error!("event=audit_save error={:?}", e.to_string());
+        print_error_line(terminal_io, "Saving the audit record failed, see the log");
+    }
+}
+
+fn adjust_balance(
+    timeout: Duration,
+    terminal_io: &mut TerminalIO,
+    credentials: &rv_api::AuthenticationResponse,
+    admin: &UserInfo,
+    user: &UserInfo,
+) -> TimeoutResult<()> {
+    execute!(
+        terminal_io.writer,
+        Print("Amount to credit, prefix with - to debit, e.g. '5' or '-1,20': ")
+    )
+    .unwrap();
+    let input = match utils::readline(terminal_io, timeout) {
+        TimeoutResult::TIMEOUT => return TimeoutResult::TIMEOUT,
+        TimeoutResult::RESULT(s) => s.trim().to_string(),
+    };
+    printline(terminal_io, "");
+    let amount = match input.strip_prefix('-') {
+        Some(amount) => utils::parse_money(amount).map(|cents| -(cents as i64)),
+        None => utils::parse_money(&input).map(|cents| cents as i64),
+    };
+    let Some(amount) = amount
+        .and_then(|amount| i32::try_from(amount).ok())
+        .filter(|&amount| amount != 0)
+    else {
+        if !input.is_empty() {
+            print_error_line(terminal_io, "Invalid amount");
+        }
+        return TimeoutResult::RESULT(());
+    };
+    let reason = match read_reason(timeout, terminal_io) {
+        TimeoutResult::TIMEOUT => return TimeoutResult::TIMEOUT,
+        TimeoutResult::RESULT(None) => return TimeoutResult::RESULT(()),
+        TimeoutResult::RESULT(Some(reason)) => reason,
+    };
+    let question = format!(
+        "Adjust the balance of {} by {}€ to {}€?",
+        user.username,
+        utils::format_money(&amount),
+        utils::format_money(&(user.money_balance + amount))
+    );
+    match confirm_balance_change(timeout, terminal_io, &question, amount) {
+        TimeoutResult::TIMEOUT => return TimeoutResult::TIMEOUT,
+        TimeoutResult::RESULT(false) => return TimeoutResult::RESULT(()),
+        TimeoutResult::RESULT(true) => (),
+    }
+    match rv_api::adjust_balance_admin(
+        &terminal_io.config.api,
+        credentials,
+        user.user_id,
+        amount,
+        &reason,
+    )
+    .unwrap()
+    {
+        ApiResult::Success => {
+            record_audit(
+                terminal_io,
+                admin,
+                user,
+                AuditAction::BalanceAdjustment,
+                amount,
+                reason,
+            );
+            printline(terminal_io, "Balance adjusted");
+        }
+        ApiResult::Fail(msg) => {
+            print_error_line(terminal_io, &format!("Adjusting the balance failed: {msg}"));
+        }
+    }
+    TimeoutResult::RESULT(())
+}
+
+fn refund_purchase(
+    timeout: Duration,
+    terminal_io: &mut TerminalIO,
+    credentials: &rv_api::AuthenticationResponse,
+    admin: &UserInfo,
+    user: &UserInfo,
+) -> TimeoutResult<()> {
+    let mut purchases =
+        match rv_api::purchase_history_admin(&terminal_io.config.api, credentials, user.user_id) {
+            Ok(ApiResultValue::Success(purchases)) => purchases,
+            Ok(ApiResultValue::Fail(msg)) => {
+                print_error_line(terminal_io, &msg);
+                return TimeoutResult::RESULT(());
+            }
+            Err(e) => {
+                print_error_line(terminal_io, &e.to_string());
+                return TimeoutResult::RESULT(());
+            }
+        };
+    // Refunds recorded in the log must not be offered again
+    let audit_log = match AuditLog::load(&terminal_io.config.store.dir) {
+        Ok(audit_log) => audit_log,
+        Err(e) => {
+            print_error_line(terminal_io, &format!("Reading the audit log failed: {e}"));
+            return TimeoutResult::RESULT(());
+        }
+    };
+    purchases.retain(|event| !event.returned && !audit_log.is_refunded(event.purchase_id));
+    purchases.sort_by(|a, b| b.time.cmp(&a.time));
+    purchases.truncate(REFUNDABLE_EVENTS);
+    if purchases.is_empty() {
+        printline(terminal_io, "No purchases to refund");
+        return TimeoutResult::RESULT(());
+    }
+    print_title(terminal_io, "Refund a purchase");
+    for (i, event) in purchases.iter().enumerate() {
+        printline(
+            terminal_io,
+            &format!(
+                "{:>3}. {} {} {}€",
+                i + 1,
+                local_time(&event.time),
+                event.product.name,
+                utils::format_money(&event.price)
+            ),
+        );
+    }
+    execute!(
+        terminal_io.writer,
+        Print("Number of the purchase to refund: ")
+    )
+    .unwrap();
+    let input = match utils::readline(terminal_io, timeout) {
+        TimeoutResult::TIMEOUT => return TimeoutResult::TIMEOUT,
+        TimeoutResult::RESULT(s) => s,
+    };
+    printline(terminal_io, "");
+    let Some(event) = input
+        .trim()
+        .parse::<usize>()
+        .ok()
+        .and_then(|number| purchases.get(number.checked_sub(1)?))
+    else {
+        if !input.is_empty() {
+            print_error_line(terminal_io, "No such purchase");
+        }
+        return TimeoutResult::RESULT(());
+    };
+    let reason = match read_reason(timeout, terminal_io) {
+        TimeoutResult::TIMEOUT => return TimeoutResult::TIMEOUT,
+        TimeoutResult::RESULT(None) => return TimeoutResult::RESULT(()),
+        TimeoutResult::RESULT(Some(reason)) => reason,
+    };
+    let question = format!(
+        "Refund {} bought {} and credit {}€ to {}?",
+        event.product.name,
+        local_time(&event.time),
+        utils::format_money(&event.price),
+        user.username
+    );
+    match confirm_balance_change(timeout, terminal_io, &question, event.price) {
+        TimeoutResult::TIMEOUT => return TimeoutResult::TIMEOUT,
+        TimeoutResult::RESULT(false) => return TimeoutResult::RESULT(()),
+        TimeoutResult::RESULT(true) => (),
+    }
+    match rv_api::refund_purchase_admin(
+        &terminal_io.config.api,
+        credentials,
+        user.user_id,
+        event.purchase_id,
+        &reason,
+    )
+    .unwrap()
+    {
+        ApiResult::Success => {
+            record_audit(
+                terminal_io,
+                admin,
+                user,
+                AuditAction::PurchaseRefund {
+                    purchase_id: event.purchase_id,
+                    product: event.product.name.clone(),
+                },
+                event.price,
+                reason,
+            );
+            printline(terminal_io, "Purchase refunded");
+        }
+        ApiResult::Fail(msg) => {
+            print_error_line(terminal_io, &format!("Refunding failed: {msg}"));
+        }
+    }
+    TimeoutResult::RESULT(())
+}
+
+// Returns true when the account was closed
+fn close_account(
+    timeout: Duration,
+    terminal_io: &mut TerminalIO,
+    credentials: &rv_api::AuthenticationResponse,
+    admin: &UserInfo,
+    user: &UserInfo,
+) -> TimeoutResult<bool> {
+    print_title(
+        terminal_io,
+        &format!("Close the account of {}", user.username),
+    );
+    print_anonymization_notice(terminal_io);
+    printline(terminal_io, "");
+    if user.money_balance < 0 {
+        print_error_line(
+            terminal_io,
+            &format!(
+                "The balance is {}€, it has to be cleared with a deposit or an adjustment first",
+                utils::format_money(&user.money_balance)
+            ),
+        );
+        return TimeoutResult::RESULT(false);
+    }
+    let donate = user.money_balance > 0;
+    if donate {
+        printline(
+            terminal_io,
+            &format!(
+                "The remaining balance of {}€ is donated, adjust it first if the user wants it back in cash",
+                utils::format_money(&user.money_balance)
+            ),
+        );
+    }
+    let reason = match read_reason(timeout, terminal_io) {
+        TimeoutResult::TIMEOUT => return TimeoutResult::TIMEOUT,
+        TimeoutResult::RESULT(None) => return TimeoutResult::RESULT(false),
+        TimeoutResult::RESULT(Some(reason)) => reason,
+    };
+    match confirm_phrase(timeout, terminal_io, &format!("close {}", user.username)) {
+        TimeoutResult::TIMEOUT => return TimeoutResult::TIMEOUT,
+        TimeoutResult::RESULT(false) => return TimeoutResult::RESULT(false),
+        TimeoutResult::RESULT(true) => (),
+    }
+    match rv_api::deactivate_user_admin(
+        &terminal_io.config.api,
+        credentials,
+        user.user_id,
+        donate,
+        &reason,
+    )
+    .unwrap()
+    {
+        ApiResult::Success => {
+            record_audit(
+                terminal_io,
+                admin,
+                user,
+                AuditAction::AccountClosure,
+                -user.money_balance,
+                reason,
+            );
+            forget_local_data(terminal_io, user.user_id);
+            printline(
+                terminal_io,
+                &format!("The account of {} is closed", user.username),
+            );
+            TimeoutResult::RESULT(true)
+        }
+        ApiResult::Fail(msg) => {
+            print_error_line(terminal_io, &format!("Closing the account failed: {msg}"));
+            TimeoutResult::RESULT(false)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user(username: &str, full_name: &str, email: &str) -> UserInfo {
+        UserInfo {
+            user_id: 0,
+            username: username.to_string(),
+            full_name: full_name.to_string(),
+            email: email.to_string(),
+            email_verified: true,
+            money_balance: 0,
+            role: "USER".to_string(),
+            privacy_level: 0,
+        }
+    }
+
+    #[test]
+    fn user_search_works() {
+        let users = [
+            user("mattim", "Matti Meikäläinen", "matti@example.com"),
+            user("maija", "Maija Virtanen", "maija.v@example.com"),
+            user("teemu", "Teemu Teekkari", "teemu@example.com"),
+        ];
+        let usernames = |query| {
+            search(&users, query)
+                .iter()
+                .map(|user| user.username.as_str())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(usernames("maija"), ["maija"]);
+        assert_eq!(usernames("VIRTA"), ["maija"]);
+        assert_eq!(usernames("ma"), ["maija", "mattim"]);
+        assert_eq!(usernames("mtm"), ["mattim"]);
+        assert_eq!(usernames("teekari"), ["teemu"]);
+        assert_eq!(usernames("meikalainen"), ["mattim"]);
+        assert_eq!(usernames("example.com").len(), 3);
+        assert!(usernames("xyz").is_empty());
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+    }
+}
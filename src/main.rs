@@ -1,6 +1,6 @@
 use rvterminal::start;
-use std::io;
+use std::process::ExitCode;
 
-fn main() -> io::Result<()> {
+fn main() -> ExitCode {
     start()
 }
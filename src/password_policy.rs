@@ -0,0 +1,120 @@
+use std::collections::HashSet;
+use std::sync::LazyLock;
+
+pub const MIN_LENGTH: usize = 8;
+
+static COMMON_PASSWORDS: LazyLock<HashSet<&str>> = LazyLock::new(|| {
+    include_str!("common_passwords.txt")
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .collect()
+});
+
+#[derive(Debug, PartialEq, PartialOrd)]
+pub enum Strength {
+    // Not accepted by the policy
+    Rejected,
+    Weak,
+    Fair,
+    Strong,
+}
+
+impl Strength {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Strength::Rejected => "not allowed",
+            Strength::Weak => "weak",
+            Strength::Fair => "fair",
+            Strength::Strong => "strong",
+        }
+    }
+}
+
+// 'Salasana2024!' is as common as 'salasana'
+fn is_common(password: &str) -> bool {
+    let password = password.to_lowercase();
+    let stem = password.trim_end_matches(|c: char| !c.is_alphabetic());
+    COMMON_PASSWORDS.contains(password.as_str())
+        || (!stem.is_empty() && COMMON_PASSWORDS.contains(stem))
+}
+
+fn contains_username(password: &str, username: &str) -> bool {
+    let password = password.to_lowercase();
+    let username = username.trim().to_lowercase();
+    let reversed: String = username.chars().rev().collect();
+    username.chars().count() >= 3 && (password.contains(&username) || password.contains(&reversed))
+}
+
+// The reason the password is not accepted, shown to the user as is
+pub fn check(password: &str, username: &str) -> Result<(), String> {
+    if password.chars().count() < MIN_LENGTH {
+        return Err(format!(
+            "The password must be at least {MIN_LENGTH} characters long"
+        ));
+    }
+    if contains_username(password, username) {
+        return Err("The password must not contain your username".to_string());
+    }
+    if is_common(password) {
+        return Err("The password is too common, choose another one".to_string());
+    }
+    Ok(())
+}
+
+// A rough estimate from the length and the character classes used
+pub fn strength(password: &str, username: &str) -> Strength {
+    if check(password, username).is_err() {
+        return Strength::Rejected;
+    }
+    let length = password.chars().count();
+    let classes = [
+        password.chars().any(|c| c.is_lowercase()),
+        password.chars().any(|c| c.is_uppercase()),
+        password.chars().any(|c| c.is_numeric()),
+        password.chars().any(|c| !c.is_alphanumeric()),
+    ]
+    .iter()
+    .filter(|&&used| used)
+    .count();
+    let distinct = password.chars().collect::<HashSet<_>>().len();
+    let mut score = match length {
+        0..=9 => 0,
+        10..=13 => 1,
+        _ => 2,
+    };
+    if classes >= 3 {
+        score += 1;
+    }
+    if distinct * 2 < length {
+        score = 0;
+    }
+    match score {
+        0 => Strength::Weak,
+        1 | 2 => Strength::Fair,
+        _ => Strength::Strong,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn password_policy_works() {
+        assert!(check("short", "matti").is_err());
+        assert!(check("matti1234", "matti").is_err());
+        assert!(check("xITTAMx99", "Matti").is_err());
+        assert!(check("Salasana2024!", "matti").is_err());
+        assert!(check("qwertyuiop", "matti").is_err());
+        assert!(check("correct horse", "matti").is_ok());
+        // Short usernames are not checked
+        assert!(check("pyjamabox", "bo").is_ok());
+
+        assert_eq!(strength("Salasana1", "matti"), Strength::Rejected);
+        assert_eq!(strength("kahvikuppi", "matti"), Strength::Fair);
+        assert_eq!(strength("aaaaaaaaaaaaaab1", "matti"), Strength::Weak);
+        assert_eq!(strength("lumi sataa", "matti"), Strength::Fair);
+        assert_eq!(strength("Lumi sataa 2026", "matti"), Strength::Strong);
+        assert!(Strength::Weak > Strength::Rejected);
+    }
+}
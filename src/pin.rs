@@ -0,0 +1,129 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+const ROUNDS: u32 = 10_000;
+const SALT_LEN: usize = 16;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum PinMode {
+    // Asked right after an RFID login
+    Always,
+    // Asked once per RFID session before deposits, settings, management mode and
+    // large purchases
+    Sensitive,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PinSettings {
+    pub mode: PinMode,
+    // Hex encoded PBKDF2-HMAC-SHA256 of the PIN
+    hash: String,
+    salt: String,
+    #[serde(default)]
+    failed_attempts: u32,
+    #[serde(default)]
+    locked_until: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum PinResult {
+    Correct,
+    Wrong { attempts_left: u32 },
+    Locked(DateTime<Utc>),
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn hash(pin: &str, salt: &str) -> String {
+    let mut out = [0u8; 32];
+    pbkdf2::pbkdf2_hmac::<Sha256>(pin.as_bytes(), salt.as_bytes(), ROUNDS, &mut out);
+    to_hex(&out)
+}
+
+// 4 to 8 digits
+pub fn is_valid_pin(pin: &str) -> bool {
+    (4..=8).contains(&pin.len()) && pin.chars().all(|c| c.is_ascii_digit())
+}
+
+impl PinSettings {
+    pub fn new(pin: &str, mode: PinMode) -> Self {
+        let mut salt = [0u8; SALT_LEN];
+        getrandom::fill(&mut salt).expect("no random source for the PIN salt");
+        let salt = to_hex(&salt);
+        Self {
+            mode,
+            hash: hash(pin, &salt),
+            salt,
+            failed_attempts: 0,
+            locked_until: None,
+        }
+    }
+
+    // The PIN is locked for `lockout` after `max_attempts` wrong tries in a row
+    pub fn verify(
+        &mut self,
+        pin: &str,
+        now: DateTime<Utc>,
+        max_attempts: u32,
+        lockout: Duration,
+    ) -> PinResult {
+        if let Some(until) = self.locked_until.filter(|&until| until > now) {
+            return PinResult::Locked(until);
+        }
+        self.locked_until = None;
+        if hash(pin, &self.salt) == self.hash {
+            self.failed_attempts = 0;
+            return PinResult::Correct;
+        }
+        self.failed_attempts += 1;
+        if self.failed_attempts >= max_attempts {
+            self.failed_attempts = 0;
+            self.locked_until = Some(now + lockout);
+            return PinResult::Locked(now + lockout);
+        }
+        PinResult::Wrong {
+            attempts_left: max_attempts - self.failed_attempts,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn pin_verify_and_lockout_work() {
+        assert!(is_valid_pin("0420"));
+        assert!(!is_valid_pin("123"));
+        assert!(!is_valid_pin("12a4"));
+
+        let mut pin = PinSettings::new("1234", PinMode::Sensitive);
+        assert_ne!(pin.hash, hash("1234", "other salt"));
+        let now = Utc::now();
+        let lockout = Duration::minutes(5);
+        assert_eq!(pin.verify("1234", now, 3, lockout), PinResult::Correct);
+        assert_eq!(
+            pin.verify("0000", now, 3, lockout),
+            PinResult::Wrong { attempts_left: 2 }
+        );
+        assert_eq!(
+            pin.verify("0000", now, 3, lockout),
+            PinResult::Wrong { attempts_left: 1 }
+        );
+        assert_eq!(
+            pin.verify("0000", now, 3, lockout),
+            PinResult::Locked(now + lockout)
+        );
+        // Even the correct PIN is refused while locked
+        assert_eq!(
+            pin.verify("1234", now + Duration::minutes(1), 3, lockout),
+            PinResult::Locked(now + lockout)
+        );
+        assert_eq!(
+            pin.verify("1234", now + Duration::minutes(6), 3, lockout),
+            PinResult::Correct
+        );
+    }
+}
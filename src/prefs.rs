@@ -0,0 +1,216 @@
+use crate::pin::PinSettings;
+use crate::rv_api::PurchaseHistoryEvent;
+use crate::store;
+use crate::utils::format_money;
+use chrono::{DateTime, Datelike, Duration, NaiveDate};
+use chrono_tz::Europe::Helsinki;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+const PREFS_FILE: &str = "prefs.json";
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ProductLimit {
+    pub name: String,
+    pub count: i32,
+}
+
+// Self-imposed caps, a purchase over a cap needs an explicit override
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct SpendingLimits {
+    pub daily_cents: Option<i32>,
+    pub weekly_cents: Option<i32>,
+    // Items per day by product barcode
+    pub products: BTreeMap<String, ProductLimit>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub struct UserPrefs {
+    pub limits: SpendingLimits,
+    // None uses the terminal default, 0 disables the warning
+    pub low_balance_cents: Option<i32>,
+    // Asked after RFID logins
+    pub pin: Option<PinSettings>,
+    // Set once the first login tour has been shown or skipped
+    pub onboarded: bool,
+}
+
+impl UserPrefs {
+    pub fn low_balance_threshold(&self, default: i32) -> i32 {
+        self.low_balance_cents.unwrap_or(default)
+    }
+}
+
+// Terminal-local settings of the users by user id
+#[derive(Serialize, Deserialize, Default)]
+pub struct Prefs {
+    pub users: BTreeMap<i32, UserPrefs>,
+}
+
+impl Prefs {
+    pub fn load(dir: &str) -> std::io::Result<Self> {
+        store::load(dir, PREFS_FILE)
+    }
+
+    pub fn save(&self, dir: &str) -> std::io::Result<()> {
+        store::save(dir, PREFS_FILE, self)
+    }
+
+    pub fn user(&self, user_id: i32) -> UserPrefs {
+        self.users.get(&user_id).cloned().unwrap_or_default()
+    }
+
+    pub fn user_mut(&mut self, user_id: i32) -> &mut UserPrefs {
+        self.users.entry(user_id).or_default()
+    }
+}
+
+// Spending of the current day and week (starting on Monday)
+#[derive(Default)]
+pub struct Spent {
+    pub today: i32,
+    pub week: i32,
+    // Items bought today by product barcode
+    pub products_today: HashMap<String, i32>,
+}
+
+impl Spent {
+    pub fn from_history(events: &[PurchaseHistoryEvent], today: NaiveDate) -> Self {
+        let week_start = today - Duration::days(today.weekday().num_days_from_monday() as i64);
+        let mut spent = Spent::default();
+        for event in events.iter().filter(|event| !event.returned) {
+            let Ok(time) = DateTime::parse_from_rfc3339(&event.time) else {
+                continue;
+            };
+            let date = time.with_timezone(&Helsinki).date_naive();
+            if date == today {
+                spent.today += event.price;
+                *spent
+                    .products_today
+                    .entry(event.product.barcode.clone())
+                    .or_default() += 1;
+            }
+            if date >= week_start && date <= today {
+                spent.week += event.price;
+            }
+        }
+        spent
+    }
+}
+
+impl SpendingLimits {
+    pub fn is_empty(&self) -> bool {
+        self.daily_cents.is_none() && self.weekly_cents.is_none() && self.products.is_empty()
+    }
+
+    // Messages about the caps the purchase would exceed
+    pub fn check(&self, spent: &Spent, barcode: &str, count: i32, total: i32) -> Vec<String> {
+        let mut exceeded = Vec::new();
+        if let Some(cap) = self.daily_cents.filter(|&cap| spent.today + total > cap) {
+            exceeded.push(format!(
+                "This purchase would exceed your daily limit of {}€ ({}€ left today)",
+                format_money(&cap),
+                format_money(&(cap - spent.today).max(0))
+            ));
+        }
+        if let Some(cap) = self.weekly_cents.filter(|&cap| spent.week + total > cap) {
+            exceeded.push(format!(
+                "This purchase would exceed your weekly limit of {}€ ({}€ left this week)",
+                format_money(&cap),
+                format_money(&(cap - spent.week).max(0))
+            ));
+        }
+        if let Some(limit) = self.products.get(barcode) {
+            let bought = spent.products_today.get(barcode).copied().unwrap_or(0);
+            if bought + count > limit.count {
+                exceeded.push(format!(
+                    "You allowed yourself {} {} per day, {} left today",
+                    limit.count,
+                    limit.name,
+                    (limit.count - bought).max(0)
+                ));
+            }
+        }
+        exceeded
+    }
+
+    // Allowance left after a purchase, None without any caps
+    pub fn remaining(
+        &self,
+        spent: &Spent,
+        barcode: &str,
+        count: i32,
+        total: i32,
+    ) -> Option<String> {
+        let mut parts = Vec::new();
+        if let Some(cap) = self.daily_cents {
+            parts.push(format!(
+                "{}€ today",
+                format_money(&(cap - spent.today - total).max(0))
+            ));
+        }
+        if let Some(cap) = self.weekly_cents {
+            parts.push(format!(
+                "{}€ this week",
+                format_money(&(cap - spent.week - total).max(0))
+            ));
+        }
+        if let Some(limit) = self.products.get(barcode) {
+            let bought = spent.products_today.get(barcode).copied().unwrap_or(0);
+            parts.push(format!(
+                "{} {} today",
+                (limit.count - bought - count).max(0),
+                limit.name
+            ));
+        }
+        match parts.is_empty() {
+            true => None,
+            false => Some(format!("Remaining allowance: {}", parts.join(", "))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn spending_limits_work() {
+        let limits = SpendingLimits {
+            daily_cents: Some(500),
+            weekly_cents: Some(2000),
+            products: BTreeMap::from([(
+                "06417047100124".to_string(),
+                ProductLimit {
+                    name: "Energy drink".to_string(),
+                    count: 3,
+                },
+            )]),
+        };
+        let spent = Spent {
+            today: 400,
+            week: 1500,
+            products_today: HashMap::from([("06417047100124".to_string(), 2)]),
+        };
+
+        assert!(limits.check(&spent, "06417047100124", 1, 100).is_empty());
+        assert_eq!(
+            limits.remaining(&spent, "06417047100124", 1, 100),
+            Some(
+                "Remaining allowance: 0.00€ today, 4.00€ this week, 0 Energy drink today"
+                    .to_string()
+            )
+        );
+        assert_eq!(limits.check(&spent, "06417047100124", 2, 200).len(), 2);
+        assert_eq!(
+            limits.check(&spent, "00000080111351", 1, 600),
+            vec![
+                "This purchase would exceed your daily limit of 5.00€ (1.00€ left today)",
+                "This purchase would exceed your weekly limit of 20.00€ (5.00€ left this week)",
+            ]
+        );
+        assert!(SpendingLimits::default().is_empty());
+        assert_eq!(SpendingLimits::default().remaining(&spent, "1", 1, 1), None);
+    }
+}
@@ -0,0 +1,175 @@
+use crate::config::ApiConfig;
+use crate::rv_api;
+use crate::utils;
+
+use chrono::{DateTime, FixedOffset};
+use std::{fs, io, io::Write};
+
+const RECEIPT_WIDTH: usize = 32;
+
+// Last successful transaction of the session, a receipt is built from it on request
+pub enum Transaction {
+    Purchase(usize), // Number of purchased items
+    Deposit,
+}
+
+pub struct ReceiptLine {
+    pub description: String,
+    pub count: i32,
+    pub unit_price: i32,
+}
+
+pub struct Receipt {
+    pub time: DateTime<FixedOffset>,
+    pub lines: Vec<ReceiptLine>,
+    pub balance_after: i32,
+}
+
+impl Receipt {
+    pub fn fetch(
+        api: &ApiConfig,
+        credentials: &rv_api::AuthenticationResponse,
+        transaction: &Transaction,
+    ) -> Option<Receipt> {
+        match transaction {
+            Transaction::Purchase(count) => {
+                let mut events = rv_api::purchase_history(api, credentials);
+                events.sort_by(|a, b| b.time.cmp(&a.time));
+                let newest = events.first()?;
+                let time = DateTime::parse_from_rfc3339(&newest.time).ok()?;
+                let balance_after = newest.balance_after;
+                let mut lines: Vec<ReceiptLine> = Vec::new();
+                for event in events.iter().take(*count).rev() {
+                    match lines.iter_mut().find(|l| {
+                        l.description == event.product.name && l.unit_price == event.price
+                    }) {
+                        Some(line) => line.count += 1,
+                        None => lines.push(ReceiptLine {
+                            description: event.product.name.clone(),
+                            count: 1,
+                            unit_price: event.price,
+                        }),
+                    }
+                }
+                Some(Receipt {
+                    time,
+                    lines,
+                    balance_after,
+                })
+            }
+            Transaction::Deposit => {
+                let mut deposits = rv_api::deposit_history(api, credentials).ok()?;
+                deposits.sort_by(|a, b| b.time.cmp(&a.time));
+                let newest = deposits.first()?;
+                Some(Receipt {
+                    time: DateTime::parse_from_rfc3339(&newest.time).ok()?,
+                    lines: vec![ReceiptLine {
+                        description: "Deposit".to_string(),
+                        count: 1,
+                        unit_price: newest.amount,
+                    }],
+                    balance_after: newest.balance_after,
+                })
+            }
+        }
+    }
+
+    pub fn total(&self) -> i32 {
+        self.lines.iter().map(|l| l.count * l.unit_price).sum()
+    }
+
+    // Plain text receipt, lines separated with '\n'
+    pub fn to_text(&self) -> String {
+        let separator = "-".repeat(RECEIPT_WIDTH);
+        let mut out = vec![
+            "TKO-äly ry - Ruokavälitys".to_string(),
+            format!(
+                "Receipt {}",
+                self.time
+                    .with_timezone(&chrono_tz::Europe::Helsinki)
+                    .format("%d/%m/%Y %H:%M")
+            ),
+            separator.clone(),
+        ];
+        for line in &self.lines {
+            out.push(receipt_row(
+                &format!("{}x {}", line.count, line.description),
+                &utils::format_money(&(line.count * line.unit_price)),
+            ));
+        }
+        out.push(separator);
+        out.push(receipt_row(
+            "Total",
+            &format!("{} EUR", utils::format_money(&self.total())),
+        ));
+        out.push(receipt_row(
+            "Balance after",
+            &format!("{} EUR", utils::format_money(&self.balance_after)),
+        ));
+        out.join("\n")
+    }
+
+    pub fn to_escpos(&self) -> Vec<u8> {
+        let mut out: Vec<u8> = vec![0x1b, b'@']; // Initialize printer
+        out.extend(self.to_text().chars().map(cp437));
+        out.extend([b'\n', 0x1b, b'd', 4]); // Feed 4 lines
+        out.extend([0x1d, b'V', 0]); // Full cut
+        out
+    }
+}
+
+fn receipt_row(left: &str, right: &str) -> String {
+    let left: String = left
+        .chars()
+        .take(RECEIPT_WIDTH - right.chars().count() - 1)
+        .collect();
+    let padding = RECEIPT_WIDTH - left.chars().count() - right.chars().count();
+    format!("{left}{}{right}", " ".repeat(padding))
+}
+
+// ESC/POS printers default to code page 437
+fn cp437(c: char) -> u8 {
+    match c {
+        'ä' => 0x84,
+        'Ä' => 0x8e,
+        'å' => 0x86,
+        'Å' => 0x8f,
+        'ö' => 0x94,
+        'Ö' => 0x99,
+        c if c.is_ascii() => c as u8,
+        _ => b'?',
+    }
+}
+
+pub fn print(device: &str, receipt: &Receipt) -> io::Result<()> {
+    let mut printer = fs::OpenOptions::new().write(true).open(device)?;
+    printer.write_all(&receipt.to_escpos())?;
+    printer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn receipt_text_works() {
+        let receipt = Receipt {
+            time: DateTime::parse_from_rfc3339("2024-06-01T09:30:00Z").unwrap(),
+            lines: vec![ReceiptLine {
+                description: "Coffee".to_string(),
+                count: 2,
+                unit_price: 50,
+            }],
+            balance_after: 1234,
+        };
+        assert_eq!(receipt.total(), 100);
+        let text = receipt.to_text();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[1], "Receipt 01/06/2024 12:30");
+        assert_eq!(lines[3], "2x Coffee                   1.00");
+        assert_eq!(lines[6], "Balance after          12.34 EUR");
+        assert!(lines
+            .iter()
+            .skip(2)
+            .all(|l| l.chars().count() == RECEIPT_WIDTH));
+    }
+}
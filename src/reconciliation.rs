@@ -0,0 +1,165 @@
+use crate::rv_api::CashDeposit;
+use crate::store;
+use crate::utils::format_money;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+
+const RECONCILIATIONS_FILE: &str = "reconciliations.json";
+
+// A closed cash box period, the next one starts at `closed_at`
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Reconciliation {
+    pub closed_at: DateTime<Utc>,
+    pub closed_by: String,
+    pub deposit_count: usize,
+    pub expected: i32,
+    pub counted: i32,
+    // Negative when the box is short
+    pub difference: i32,
+    pub flagged: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct Reconciliations {
+    pub periods: Vec<Reconciliation>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct UserTotal {
+    pub username: String,
+    pub count: usize,
+    pub total: i32,
+}
+
+impl Reconciliations {
+    pub fn load(dir: &str) -> std::io::Result<Self> {
+        store::load(dir, RECONCILIATIONS_FILE)
+    }
+
+    pub fn save(&self, dir: &str) -> std::io::Result<()> {
+        store::save(dir, RECONCILIATIONS_FILE, self)
+    }
+
+    pub fn last(&self) -> Option<&Reconciliation> {
+        self.periods.last()
+    }
+}
+
+// Largest totals first
+pub fn user_totals(deposits: &[CashDeposit]) -> Vec<UserTotal> {
+    let mut totals: BTreeMap<i32, UserTotal> = BTreeMap::new();
+    for deposit in deposits {
+        let total = totals.entry(deposit.user_id).or_insert_with(|| UserTotal {
+            username: deposit.username.clone(),
+            count: 0,
+            total: 0,
+        });
+        total.count += 1;
+        total.total += deposit.amount;
+    }
+    let mut totals: Vec<UserTotal> = totals.into_values().collect();
+    totals.sort_by_key(|total| Reverse(total.total));
+    totals
+}
+
+// Users to ask about a short count: those whose deposits alone would cover the
+// shortfall, or everyone if no single user does
+pub fn flagged_users(totals: &[UserTotal], difference: i32) -> Vec<String> {
+    if difference >= 0 {
+        return Vec::new();
+    }
+    let covering: Vec<String> = totals
+        .iter()
+        .filter(|total| total.total >= -difference)
+        .map(|total| total.username.clone())
+        .collect();
+    match covering.is_empty() {
+        true => totals.iter().map(|total| total.username.clone()).collect(),
+        false => covering,
+    }
+}
+
+fn csv_field(field: &str) -> String {
+    match field.contains([',', '"', '\n', '\r']) {
+        true => format!("\"{}\"", field.replace('"', "\"\"")),
+        false => field.to_string(),
+    }
+}
+
+pub fn to_csv(deposits: &[CashDeposit], reconciliation: &Reconciliation) -> String {
+    let mut csv = String::from("deposit_id,time,username,amount\n");
+    for deposit in deposits {
+        csv += &format!(
+            "{},{},{},{}\n",
+            deposit.deposit_id,
+            csv_field(&deposit.time),
+            csv_field(&deposit.username),
+            format_money(&deposit.amount)
+        );
+    }
+    csv += &format!(
+        ",,expected,{}\n,,counted,{}\n,,difference,{}\n",
+        format_money(&reconciliation.expected),
+        format_money(&reconciliation.counted),
+        format_money(&reconciliation.difference)
+    );
+    csv
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn reconciliation_works() {
+        let deposit = |deposit_id, user_id, username: &str, amount| CashDeposit {
+            deposit_id,
+            time: "2026-10-12T10:00:00Z".to_string(),
+            amount,
+            user_id,
+            username: username.to_string(),
+        };
+        let deposits = [
+            deposit(1, 7, "maija", 500),
+            deposit(2, 3, "matti, jr", 2000),
+            deposit(3, 7, "maija", 1000),
+        ];
+        let totals = user_totals(&deposits);
+        assert_eq!(
+            totals,
+            [
+                UserTotal {
+                    username: "matti, jr".to_string(),
+                    count: 1,
+                    total: 2000,
+                },
+                UserTotal {
+                    username: "maija".to_string(),
+                    count: 2,
+                    total: 1500,
+                },
+            ]
+        );
+        assert!(flagged_users(&totals, 0).is_empty());
+        assert_eq!(flagged_users(&totals, -1800), ["matti, jr"]);
+        assert_eq!(flagged_users(&totals, -5000), ["matti, jr", "maija"]);
+
+        let reconciliation = Reconciliation {
+            closed_at: Utc::now(),
+            closed_by: "admin".to_string(),
+            deposit_count: 3,
+            expected: 3500,
+            counted: 3000,
+            difference: -500,
+            flagged: flagged_users(&totals, -500),
+        };
+        let csv = to_csv(&deposits[1..2], &reconciliation);
+        assert_eq!(
+            csv,
+            "deposit_id,time,username,amount\n\
+             2,2026-10-12T10:00:00Z,\"matti, jr\",20.00\n\
+             ,,expected,35.00\n,,counted,30.00\n,,difference,-5.00\n"
+        );
+    }
+}
@@ -0,0 +1,122 @@
+// Finnish national reference numbers (viitenumero) and the international RF
+// creditor references (ISO 11649) built from them
+
+// Weights 7, 3, 1 from the right
+fn check_digit(base: &str) -> u32 {
+    let sum: u32 = base
+        .chars()
+        .rev()
+        .zip([7, 3, 1].into_iter().cycle())
+        .map(|(c, weight)| c.to_digit(10).unwrap_or(0) * weight)
+        .sum();
+    (10 - sum % 10) % 10
+}
+
+// Appends the check digit to a base of 3 to 19 digits
+pub fn national_reference(base: &str) -> Option<String> {
+    if !(3..=19).contains(&base.len()) || !base.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    Some(format!("{base}{}", check_digit(base)))
+}
+
+fn is_valid_national(reference: &str) -> bool {
+    (4..=20).contains(&reference.len())
+        && reference.chars().all(|c| c.is_ascii_digit())
+        && check_digit(&reference[..reference.len() - 1]).to_string()
+            == reference[reference.len() - 1..]
+}
+
+// Letters as 10..=35, as in IBAN check digits
+fn mod97(input: &str) -> Option<u32> {
+    input.chars().try_fold(0u32, |acc, c| {
+        let value = c.to_digit(36)?;
+        Some(match value < 10 {
+            true => (acc * 10 + value) % 97,
+            false => (acc * 100 + value) % 97,
+        })
+    })
+}
+
+pub fn rf_reference(national: &str) -> Option<String> {
+    if !is_valid_national(national) {
+        return None;
+    }
+    let check = 98 - mod97(&format!("{national}RF00"))?;
+    Some(format!("RF{check:02}{national}"))
+}
+
+fn is_valid_rf(reference: &str) -> bool {
+    // Checked before slicing, statements may contain any text
+    reference.is_ascii()
+        && reference.len() > 4
+        && reference.starts_with("RF")
+        && mod97(&format!("{}{}", &reference[4..], &reference[..4])) == Some(1)
+}
+
+// The national reference in canonical form, for comparing references given in
+// either format with spaces or leading zeros
+pub fn normalize(input: &str) -> Option<String> {
+    let reference: String = input
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect::<String>()
+        .to_uppercase();
+    let national = match reference.starts_with("RF") {
+        true if is_valid_rf(&reference) => &reference[4..],
+        true => return None,
+        false => &reference,
+    };
+    let national = national.trim_start_matches('0');
+    is_valid_national(national).then(|| national.to_string())
+}
+
+// Unique per user as long as the same user does not create two within a second
+pub fn deposit_reference(user_id: i32, unix_time: i64) -> String {
+    let base = format!(
+        "{}{:09}",
+        user_id.unsigned_abs(),
+        unix_time.rem_euclid(1_000_000_000)
+    );
+    let national = national_reference(&base).expect("base of digits");
+    rf_reference(&national).expect("valid national reference")
+}
+
+// Groups of four as printed on invoices
+pub fn display(reference: &str) -> String {
+    reference
+        .chars()
+        .collect::<Vec<_>>()
+        .chunks(4)
+        .map(|chunk| chunk.iter().collect::<String>())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn references_work() {
+        assert_eq!(national_reference("1234"), Some("12344".to_string()));
+        assert_eq!(national_reference("1"), None);
+        assert!(is_valid_national("12344"));
+        assert!(!is_valid_national("12345"));
+
+        assert_eq!(rf_reference("12344"), Some("RF0812344".to_string()));
+        assert!(is_valid_rf("RF18539007547034"));
+        assert!(!is_valid_rf("RF19539007547034"));
+
+        assert_eq!(normalize("RF08 12344"), Some("12344".to_string()));
+        assert_eq!(normalize("00000000000000012344"), Some("12344".to_string()));
+        assert_eq!(normalize("rf0812344"), Some("12344".to_string()));
+        assert_eq!(normalize("RF48 12344"), None);
+        assert_eq!(normalize("12345"), None);
+        assert_eq!(normalize("RF1é12344"), None);
+        assert_eq!(normalize("RFé"), None);
+
+        let reference = deposit_reference(42, 1_760_000_000);
+        assert_eq!(normalize(&reference), Some("427600000009".to_string()));
+        assert_eq!(display("RF0812344"), "RF08 1234 4");
+    }
+}
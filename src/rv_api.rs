@@ -1,13 +1,41 @@
+use crate::config::ApiConfig;
+use chrono::{DateTime, SecondsFormat, Utc};
 use reqwest;
 use serde::Deserialize;
 use serde::Serialize;
-use std::{collections::HashMap, sync::LazyLock};
+use std::{collections::HashMap, time::Instant};
 
-static API_URL: LazyLock<String> = LazyLock::new(|| {
-    std::env::var("RV_API_URL").unwrap_or("http://localhost:4040/api".to_string())
-});
-static RV_TERMINAL_SECRET: LazyLock<String> =
-    LazyLock::new(|| std::env::var("RV_TERMINAL_SECRET").unwrap_or("unsecure".to_string()));
+trait SendLogged {
+    fn send_logged(self) -> reqwest::Result<reqwest::blocking::Response>;
+}
+
+// Logs latency and errors of every request, request bodies and headers are never logged
+impl SendLogged for reqwest::blocking::RequestBuilder {
+    fn send_logged(self) -> reqwest::Result<reqwest::blocking::Response> {
+        let (client, request) = self.build_split();
+        let request = request?;
+        let method = request.method().clone();
+        let path = request.url().path().to_string();
+        let start = Instant::now();
+        let result = client.execute(request);
+        let latency_ms = start.elapsed().as_millis();
+        match &result {
+            Ok(resp) if resp.status().is_server_error() => log::warn!(
+                "event=api method={method} path={path:?} status={} latency_ms={latency_ms}",
+                resp.status().as_u16()
+            ),
+            Ok(resp) => log::info!(
+                "event=api method={method} path={path:?} status={} latency_ms={latency_ms}",
+                resp.status().as_u16()
+            ),
+            Err(e) => log::error!(
+                "event=api_error method={method} path={path:?} latency_ms={latency_ms} error={:?}",
+                e.to_string()
+            ),
+        }
+        result
+    }
+}
 
 #[derive(Deserialize)]
 pub struct AuthenticationResponse {
@@ -17,8 +45,7 @@ pub struct AuthenticationResponse {
     pub password_reset: bool,
 }
 
-#[derive(Deserialize)]
-
+#[derive(Serialize, Deserialize)]
 pub struct PurchaseHistoryEvent {
     #[serde(rename = "purchaseId")]
     pub purchase_id: i64,
@@ -30,24 +57,55 @@ pub struct PurchaseHistoryEvent {
     pub returned: bool,
 }
 
-pub fn purchase_history(credentials: &AuthenticationResponse) -> Vec<PurchaseHistoryEvent> {
+pub fn purchase_history(
+    api: &ApiConfig,
+    credentials: &AuthenticationResponse,
+) -> Vec<PurchaseHistoryEvent> {
     #[derive(Deserialize)]
     struct Hax {
         purchases: Vec<PurchaseHistoryEvent>,
     }
     let client = reqwest::blocking::Client::new();
     let resp = client
-        .get(format!("{}/v1/user/purchaseHistory", *API_URL))
+        .get(format!("{}/v1/user/purchaseHistory", api.url))
         .header(
             "Authorization",
             String::from("Bearer ") + &credentials.access_token,
         )
-        .send()
+        .send_logged()
         .expect("api error");
     return resp.json::<Hax>().map(|v| v.purchases).unwrap();
 }
 
+#[derive(Serialize, Deserialize)]
+pub struct DepositHistoryEvent {
+    pub time: String,
+    pub amount: i32,
+    #[serde(rename = "balanceAfter")]
+    pub balance_after: i32,
+}
+
+pub fn deposit_history(
+    api: &ApiConfig,
+    credentials: &AuthenticationResponse,
+) -> Result<Vec<DepositHistoryEvent>, reqwest::Error> {
+    #[derive(Deserialize)]
+    struct Hax {
+        deposits: Vec<DepositHistoryEvent>,
+    }
+    let client = reqwest::blocking::Client::new();
+    let resp = client
+        .get(format!("{}/v1/user/depositHistory", api.url))
+        .header(
+            "Authorization",
+            String::from("Bearer ") + &credentials.access_token,
+        )
+        .send_logged()?;
+    resp.json::<Hax>().map(|v| v.deposits)
+}
+
 pub fn add_box(
+    api: &ApiConfig,
     box_barcode: &str,
     product_barcode: &str,
     items_per_box: i32,
@@ -69,13 +127,13 @@ pub fn add_box(
     };
     let client = reqwest::blocking::Client::new();
     let resp = client
-        .post(format!("{}/v1/admin/boxes", *API_URL))
+        .post(format!("{}/v1/admin/boxes", api.url))
         .header(
             "Authorization",
             String::from("Bearer ") + &credentials.access_token,
         )
         .json(&body)
-        .send()
+        .send_logged()
         .unwrap();
     match resp.status().as_u16() {
         201 => Ok(ApiResult::Success),
@@ -86,7 +144,9 @@ pub fn add_box(
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn add_product(
+    api: &ApiConfig,
     barcode: &str,
     name: &str,
     category_id: i32,
@@ -117,13 +177,13 @@ pub fn add_product(
     };
     let client = reqwest::blocking::Client::new();
     let resp = client
-        .post(format!("{}/v1/admin/products/", *API_URL))
+        .post(format!("{}/v1/admin/products/", api.url))
         .header(
             "Authorization",
             String::from("Bearer ") + &credentials.access_token,
         )
         .json(&hm)
-        .send()?;
+        .send_logged()?;
     match resp.status().as_u16() {
         201 => Ok(ApiResult::Success),
         409 => Ok(ApiResult::Fail(format!("error: barcode already in use"))),
@@ -133,16 +193,20 @@ pub fn add_product(
     }
 }
 
-pub fn login(username: &str, password: &str) -> ApiResultValue<AuthenticationResponse> {
+pub fn login(
+    api: &ApiConfig,
+    username: &str,
+    password: &str,
+) -> ApiResultValue<AuthenticationResponse> {
     let client = reqwest::blocking::Client::new();
     let resp = client
-        .post(format!("{}/v2/authenticate", *API_URL))
+        .post(format!("{}/v2/authenticate", api.url))
         .json(&HashMap::from([
             ("username", &username),
             ("password", &password),
-            ("rvTerminalSecret", &RV_TERMINAL_SECRET.as_str()),
+            ("rvTerminalSecret", &api.terminal_secret.as_str()),
         ]))
-        .send()
+        .send_logged()
         .expect("api error");
     match resp.status().as_u16() {
         200 => ApiResultValue::Success(
@@ -153,15 +217,15 @@ pub fn login(username: &str, password: &str) -> ApiResultValue<AuthenticationRes
     }
 }
 
-pub fn login_rfid(rfid: &str) -> Option<AuthenticationResponse> {
+pub fn login_rfid(api: &ApiConfig, rfid: &str) -> Option<AuthenticationResponse> {
     let client = reqwest::blocking::Client::new();
     let resp = client
-        .post(format!("{}/v2/authenticate/rfid", *API_URL))
+        .post(format!("{}/v2/authenticate/rfid", api.url))
         .json(&HashMap::from([
             ("rfid", &rfid),
-            ("rvTerminalSecret", &RV_TERMINAL_SECRET.as_str()),
+            ("rvTerminalSecret", &api.terminal_secret.as_str()),
         ]))
-        .send()
+        .send_logged()
         .expect("api error");
     match resp.status().as_u16() {
         200 => Some(
@@ -172,7 +236,7 @@ pub fn login_rfid(rfid: &str) -> Option<AuthenticationResponse> {
     }
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug)]
 pub struct UserInfo {
     #[serde(rename = "userId")]
     pub user_id: i32,
@@ -181,6 +245,9 @@ pub struct UserInfo {
     pub full_name: String,
     #[serde(rename = "email")]
     pub email: String,
+    // Accounts from before email verification count as verified
+    #[serde(rename = "emailVerified", default = "email_verified_default")]
+    pub email_verified: bool,
     #[serde(rename = "moneyBalance")]
     pub money_balance: i32,
     pub role: String,
@@ -188,6 +255,10 @@ pub struct UserInfo {
     pub privacy_level: u8,
 }
 
+fn email_verified_default() -> bool {
+    true
+}
+
 pub trait UserInfoTrait {
     fn is_admin(&self) -> bool;
 }
@@ -198,43 +269,140 @@ impl UserInfoTrait for UserInfo {
     }
 }
 
-pub fn get_user_info(credentials: &AuthenticationResponse) -> Result<UserInfo, reqwest::Error> {
+pub fn get_user_info(
+    api: &ApiConfig,
+    credentials: &AuthenticationResponse,
+) -> Result<UserInfo, reqwest::Error> {
     #[derive(Deserialize)]
     struct Hax {
         user: UserInfo,
     }
     let client = reqwest::blocking::Client::new();
     let resp = client
-        .get(format!("{}/v1/user", *API_URL))
+        .get(format!("{}/v1/user", api.url))
         .header(
             "Authorization",
             String::from("Bearer ") + &credentials.access_token,
         )
-        .send()
+        .send_logged()
         .expect("api error");
     return resp.json::<Hax>().map(|v| v.user);
 }
 
+// Rows without a privacy level are not shown by name
+fn privacy_level_default() -> u8 {
+    2
+}
+
 #[derive(Deserialize)]
 pub struct LeaderboardRow {
     pub saldo: i32,
     pub name: String,
+    #[serde(rename = "privacyLevel", default = "privacy_level_default")]
+    pub privacy_level: u8,
 }
 
-pub fn get_leaderboard() -> Result<ApiResultValue<Vec<LeaderboardRow>>, reqwest::Error> {
+pub fn get_leaderboard(
+    api: &ApiConfig,
+) -> Result<ApiResultValue<Vec<LeaderboardRow>>, reqwest::Error> {
     let client = reqwest::blocking::Client::new();
     let resp = client
-        .get(format!("{}/v1/statistics/leaderboard", *API_URL))
-        .header("RV-Terminal-Secret", RV_TERMINAL_SECRET.as_str())
-        .send()
-        .expect("api error");
+        .get(format!("{}/v1/statistics/leaderboard", api.url))
+        .header("RV-Terminal-Secret", api.terminal_secret.as_str())
+        .send_logged()?;
+    Ok(match resp.status().as_u16() {
+        200 => ApiResultValue::Success(resp.json::<Vec<LeaderboardRow>>()?),
+        code => ApiResultValue::Fail(format!("http response {code}")),
+    })
+}
+
+// Most recently bought in products
+pub fn get_new_products(
+    api: &ApiConfig,
+) -> Result<ApiResultValue<Vec<ProductInfo>>, reqwest::Error> {
+    let client = reqwest::blocking::Client::new();
+    let resp = client
+        .get(format!("{}/v1/statistics/newProducts", api.url))
+        .header("RV-Terminal-Secret", api.terminal_secret.as_str())
+        .send_logged()?;
     Ok(match resp.status().as_u16() {
-        200 => ApiResultValue::Success(resp.json::<Vec<LeaderboardRow>>().unwrap()),
+        200 => ApiResultValue::Success(resp.json::<Vec<ProductInfo>>()?),
+        code => ApiResultValue::Fail(format!("http response {code}")),
+    })
+}
+
+#[derive(Deserialize)]
+pub struct TopSellerRow {
+    pub name: String,
+    pub count: i32,
+}
+
+pub fn get_top_sellers(
+    api: &ApiConfig,
+    since: &DateTime<Utc>,
+) -> Result<ApiResultValue<Vec<TopSellerRow>>, reqwest::Error> {
+    let client = reqwest::blocking::Client::new();
+    let resp = client
+        .get(format!(
+            "{}/v1/statistics/topSellers?since={}",
+            api.url,
+            since.to_rfc3339_opts(SecondsFormat::Secs, true)
+        ))
+        .header("RV-Terminal-Secret", api.terminal_secret.as_str())
+        .send_logged()?;
+    Ok(match resp.status().as_u16() {
+        200 => ApiResultValue::Success(resp.json::<Vec<TopSellerRow>>()?),
+        code => ApiResultValue::Fail(format!("http response {code}")),
+    })
+}
+
+#[derive(Deserialize)]
+pub struct RankingRow {
+    pub name: String,
+    pub count: i32,
+    #[serde(rename = "privacyLevel", default = "privacy_level_default")]
+    pub privacy_level: u8,
+}
+
+// Users ranked by their purchases since the given time
+pub fn get_purchase_ranking(
+    api: &ApiConfig,
+    since: &DateTime<Utc>,
+) -> Result<ApiResultValue<Vec<RankingRow>>, reqwest::Error> {
+    get_ranking(api, "purchaseRanking", since)
+}
+
+// Users ranked by the coffee they bought since the given time
+pub fn get_coffee_ranking(
+    api: &ApiConfig,
+    since: &DateTime<Utc>,
+) -> Result<ApiResultValue<Vec<RankingRow>>, reqwest::Error> {
+    get_ranking(api, "coffeeRanking", since)
+}
+
+fn get_ranking(
+    api: &ApiConfig,
+    ranking: &str,
+    since: &DateTime<Utc>,
+) -> Result<ApiResultValue<Vec<RankingRow>>, reqwest::Error> {
+    let client = reqwest::blocking::Client::new();
+    let resp = client
+        .get(format!(
+            "{}/v1/statistics/{}?since={}",
+            api.url,
+            ranking,
+            since.to_rfc3339_opts(SecondsFormat::Secs, true)
+        ))
+        .header("RV-Terminal-Secret", api.terminal_secret.as_str())
+        .send_logged()?;
+    Ok(match resp.status().as_u16() {
+        200 => ApiResultValue::Success(resp.json::<Vec<RankingRow>>()?),
         code => ApiResultValue::Fail(format!("http response {code}")),
     })
 }
 
 pub fn change_privacy_level(
+    api: &ApiConfig,
     credentials: &AuthenticationResponse,
     privacy_level: i32,
 ) -> Result<ApiResult, reqwest::Error> {
@@ -246,13 +414,13 @@ pub fn change_privacy_level(
     }
     let hm: Body = Body { privacy_level };
     let resp = client
-        .post(format!("{}/v1/user/changePrivacyLevel", *API_URL))
+        .post(format!("{}/v1/user/changePrivacyLevel", api.url))
         .header(
             "Authorization",
             String::from("Bearer ") + &credentials.access_token,
         )
         .json(&hm)
-        .send()
+        .send_logged()
         .expect("api error");
     Ok(match resp.status().as_u16() {
         203 => ApiResult::Success,
@@ -261,6 +429,7 @@ pub fn change_privacy_level(
 }
 
 pub fn get_user_info_by_username(
+    api: &ApiConfig,
     credentials: &AuthenticationResponse,
     username: &str,
 ) -> Result<ApiResultValue<UserInfo>, reqwest::Error> {
@@ -272,13 +441,13 @@ pub fn get_user_info_by_username(
     let resp = client
         .get(format!(
             "{}/v1/admin/utils/getUserByUsername/{username}",
-            *API_URL
+            api.url
         ))
         .header(
             "Authorization",
             String::from("Bearer ") + &credentials.access_token,
         )
-        .send()
+        .send_logged()
         .expect("api error");
     Ok(match resp.status().as_u16() {
         200 => ApiResultValue::Success(resp.json::<Hax>().map(|v| v.user).unwrap()),
@@ -288,62 +457,261 @@ pub fn get_user_info_by_username(
     })
 }
 
-pub fn get_user_info_by_email(
+pub fn get_users_admin(
+    api: &ApiConfig,
     credentials: &AuthenticationResponse,
-    email: &str,
-) -> Result<ApiResultValue<UserInfo>, reqwest::Error> {
+) -> Result<ApiResultValue<Vec<UserInfo>>, reqwest::Error> {
     #[derive(Deserialize)]
     struct Hax {
-        user: UserInfo,
+        users: Vec<UserInfo>,
+    }
+    let client = reqwest::blocking::Client::new();
+    let resp = client
+        .get(format!("{}/v1/admin/users", api.url))
+        .header(
+            "Authorization",
+            String::from("Bearer ") + &credentials.access_token,
+        )
+        .send_logged()?;
+    Ok(match resp.status().as_u16() {
+        200 => ApiResultValue::Success(resp.json::<Hax>()?.users),
+        401 => ApiResultValue::Fail("Not authorized".to_string()),
+        code => ApiResultValue::Fail(format!("http response {code}")),
+    })
+}
+
+pub fn purchase_history_admin(
+    api: &ApiConfig,
+    credentials: &AuthenticationResponse,
+    user_id: i32,
+) -> Result<ApiResultValue<Vec<PurchaseHistoryEvent>>, reqwest::Error> {
+    #[derive(Deserialize)]
+    struct Hax {
+        purchases: Vec<PurchaseHistoryEvent>,
     }
     let client = reqwest::blocking::Client::new();
     let resp = client
         .get(format!(
-            "{}/v1/admin/utils/getUserByEmail/{email}",
-            *API_URL
+            "{}/v1/admin/users/{user_id}/purchaseHistory",
+            api.url
         ))
         .header(
             "Authorization",
             String::from("Bearer ") + &credentials.access_token,
         )
-        .send()
-        .expect("api error");
+        .send_logged()?;
     Ok(match resp.status().as_u16() {
-        200 => ApiResultValue::Success(resp.json::<Hax>().map(|v| v.user).unwrap()),
-        404 => ApiResultValue::Fail("User with the given email not found".to_string()),
+        200 => ApiResultValue::Success(resp.json::<Hax>()?.purchases),
+        404 => ApiResultValue::Fail("User not found".to_string()),
         401 => ApiResultValue::Fail("Not authorized".to_string()),
         code => ApiResultValue::Fail(format!("http response {code}")),
     })
 }
 
-pub fn get_user_info_by_full_name(
+#[derive(Deserialize, Clone, Debug)]
+pub struct CashDeposit {
+    #[serde(rename = "depositId")]
+    pub deposit_id: i64,
+    pub time: String,
+    pub amount: i32,
+    #[serde(rename = "userId")]
+    pub user_id: i32,
+    pub username: String,
+}
+
+// Cash deposits of all users, oldest first
+pub fn get_cash_deposits_admin(
+    api: &ApiConfig,
     credentials: &AuthenticationResponse,
-    full_name: &str,
-) -> Result<ApiResultValue<UserInfo>, reqwest::Error> {
+    since: Option<&DateTime<Utc>>,
+) -> Result<ApiResultValue<Vec<CashDeposit>>, reqwest::Error> {
     #[derive(Deserialize)]
     struct Hax {
-        user: UserInfo,
+        deposits: Vec<CashDeposit>,
+    }
+    let since = match since {
+        Some(since) => format!(
+            "&since={}",
+            since.to_rfc3339_opts(SecondsFormat::Secs, true)
+        ),
+        None => String::new(),
+    };
+    let client = reqwest::blocking::Client::new();
+    let resp = client
+        .get(format!("{}/v1/admin/deposits?type=cash{since}", api.url))
+        .header(
+            "Authorization",
+            String::from("Bearer ") + &credentials.access_token,
+        )
+        .send_logged()?;
+    Ok(match resp.status().as_u16() {
+        200 => ApiResultValue::Success(resp.json::<Hax>()?.deposits),
+        401 => ApiResultValue::Fail("Not authorized".to_string()),
+        code => ApiResultValue::Fail(format!("http response {code}")),
+    })
+}
+
+pub fn deposit_history_admin(
+    api: &ApiConfig,
+    credentials: &AuthenticationResponse,
+    user_id: i32,
+) -> Result<ApiResultValue<Vec<DepositHistoryEvent>>, reqwest::Error> {
+    #[derive(Deserialize)]
+    struct Hax {
+        deposits: Vec<DepositHistoryEvent>,
     }
     let client = reqwest::blocking::Client::new();
     let resp = client
         .get(format!(
-            "{}/v1/admin/utils/getUserByFullName/{full_name}",
-            *API_URL
+            "{}/v1/admin/users/{user_id}/depositHistory",
+            api.url
         ))
         .header(
             "Authorization",
             String::from("Bearer ") + &credentials.access_token,
         )
-        .send()
-        .expect("api error");
+        .send_logged()?;
     Ok(match resp.status().as_u16() {
-        200 => ApiResultValue::Success(resp.json::<Hax>().map(|v| v.user).unwrap()),
-        404 => ApiResultValue::Fail("User with the given full name not found".to_string()),
+        200 => ApiResultValue::Success(resp.json::<Hax>()?.deposits),
+        404 => ApiResultValue::Fail("User not found".to_string()),
         401 => ApiResultValue::Fail("Not authorized".to_string()),
         code => ApiResultValue::Fail(format!("http response {code}")),
     })
 }
 
+// Positive amounts credit and negative debit the balance of the user
+pub fn adjust_balance_admin(
+    api: &ApiConfig,
+    credentials: &AuthenticationResponse,
+    user_id: i32,
+    amount: i32,
+    reason: &str,
+) -> Result<ApiResult, reqwest::Error> {
+    #[derive(Serialize)]
+    struct Body<'a> {
+        amount: i32,
+        reason: &'a str,
+    }
+    let client = reqwest::blocking::Client::new();
+    let resp = client
+        .post(format!(
+            "{}/v1/admin/users/{user_id}/adjustBalance",
+            api.url
+        ))
+        .header(
+            "Authorization",
+            String::from("Bearer ") + &credentials.access_token,
+        )
+        .json(&Body { amount, reason })
+        .send_logged()?;
+    Ok(match resp.status().as_u16() {
+        200 => ApiResult::Success,
+        404 => ApiResult::Fail("User not found".to_string()),
+        400 => ApiResult::Fail("Missing or invalid fields in request".to_string()),
+        401 => ApiResult::Fail("Not authorized".to_string()),
+        code => ApiResult::Fail(format!("http response {code}")),
+    })
+}
+
+// Anonymizes the account on the backend, a positive balance is donated when
+// `donate_balance` is set and any other non-zero balance is refused
+pub fn deactivate_account(
+    api: &ApiConfig,
+    credentials: &AuthenticationResponse,
+    password: &str,
+    donate_balance: bool,
+) -> Result<ApiResult, reqwest::Error> {
+    #[derive(Serialize)]
+    struct Body<'a> {
+        password: &'a str,
+        #[serde(rename = "donateBalance")]
+        donate_balance: bool,
+    }
+    let client = reqwest::blocking::Client::new();
+    let resp = client
+        .post(format!("{}/v1/user/deactivate", api.url))
+        .header(
+            "Authorization",
+            String::from("Bearer ") + &credentials.access_token,
+        )
+        .json(&Body {
+            password,
+            donate_balance,
+        })
+        .send_logged()?;
+    Ok(match resp.status().as_u16() {
+        200 | 204 => ApiResult::Success,
+        400 => ApiResult::Fail("Missing or invalid fields in request".to_string()),
+        401 | 403 => ApiResult::Fail("Wrong password".to_string()),
+        409 => ApiResult::Fail("The balance is not zero".to_string()),
+        code => ApiResult::Fail(format!("http response {code}")),
+    })
+}
+
+pub fn deactivate_user_admin(
+    api: &ApiConfig,
+    credentials: &AuthenticationResponse,
+    user_id: i32,
+    donate_balance: bool,
+    reason: &str,
+) -> Result<ApiResult, reqwest::Error> {
+    #[derive(Serialize)]
+    struct Body<'a> {
+        #[serde(rename = "donateBalance")]
+        donate_balance: bool,
+        reason: &'a str,
+    }
+    let client = reqwest::blocking::Client::new();
+    let resp = client
+        .post(format!("{}/v1/admin/users/{user_id}/deactivate", api.url))
+        .header(
+            "Authorization",
+            String::from("Bearer ") + &credentials.access_token,
+        )
+        .json(&Body {
+            donate_balance,
+            reason,
+        })
+        .send_logged()?;
+    Ok(match resp.status().as_u16() {
+        200 | 204 => ApiResult::Success,
+        404 => ApiResult::Fail("User not found".to_string()),
+        400 => ApiResult::Fail("Missing or invalid fields in request".to_string()),
+        401 => ApiResult::Fail("Not authorized".to_string()),
+        409 => ApiResult::Fail("The balance is not zero".to_string()),
+        code => ApiResult::Fail(format!("http response {code}")),
+    })
+}
+
+// Unlike return_product, works on any purchase regardless of its age
+pub fn refund_purchase_admin(
+    api: &ApiConfig,
+    credentials: &AuthenticationResponse,
+    user_id: i32,
+    purchase_id: i64,
+    reason: &str,
+) -> Result<ApiResult, reqwest::Error> {
+    let client = reqwest::blocking::Client::new();
+    let resp = client
+        .post(format!(
+            "{}/v1/admin/users/{user_id}/purchases/{purchase_id}/refund",
+            api.url
+        ))
+        .header(
+            "Authorization",
+            String::from("Bearer ") + &credentials.access_token,
+        )
+        .json(&HashMap::from([("reason", reason)]))
+        .send_logged()?;
+    Ok(match resp.status().as_u16() {
+        200 => ApiResult::Success,
+        404 => ApiResult::Fail("Purchase not found".to_string()),
+        409 => ApiResult::Fail("Purchase already returned".to_string()),
+        401 => ApiResult::Fail("Not authorized".to_string()),
+        code => ApiResult::Fail(format!("http response {code}")),
+    })
+}
+
 pub enum ApiResultValue<T> {
     Success(T),
     Fail(String),
@@ -354,6 +722,7 @@ pub enum ApiResult {
 }
 
 pub fn buy_in_box(
+    api: &ApiConfig,
     barcode: &str,
     product_buy_price: i32,
     product_sell_price: i32,
@@ -376,13 +745,13 @@ pub fn buy_in_box(
     };
     let client = reqwest::blocking::Client::new();
     let resp = client
-        .post(format!("{}/v1/admin/boxes/{barcode}/buyIn", *API_URL))
+        .post(format!("{}/v1/admin/boxes/{barcode}/buyIn", api.url))
         .header(
             "Authorization",
             String::from("Bearer ") + &credentials.access_token,
         )
         .json(&hm)
-        .send()
+        .send_logged()
         .unwrap();
     match resp.status().as_u16() {
         200 => Ok(ApiResult::Success),
@@ -393,6 +762,7 @@ pub fn buy_in_box(
 }
 
 pub fn change_password_admin(
+    api: &ApiConfig,
     credentials: &AuthenticationResponse,
     user_id: i32,
     password: &str,
@@ -401,14 +771,14 @@ pub fn change_password_admin(
     let resp = client
         .post(format!(
             "{}/v1/admin/users/{user_id}/changePassword",
-            *API_URL
+            api.url
         ))
         .header(
             "Authorization",
             String::from("Bearer ") + &credentials.access_token,
         )
         .json(&HashMap::from([("password", password)]))
-        .send()
+        .send_logged()
         .expect("api error");
     match resp.status().as_u16() {
         200 => Ok(ApiResult::Success),
@@ -424,19 +794,20 @@ pub fn change_password_admin(
 }
 
 pub fn change_role_admin(
+    api: &ApiConfig,
     credentials: &AuthenticationResponse,
     user_id: i32,
     role: &str,
 ) -> Result<ApiResult, reqwest::Error> {
     let client = reqwest::blocking::Client::new();
     let resp = client
-        .post(format!("{}/v1/admin/users/{user_id}/changeRole", *API_URL))
+        .post(format!("{}/v1/admin/users/{user_id}/changeRole", api.url))
         .header(
             "Authorization",
             String::from("Bearer ") + &credentials.access_token,
         )
         .json(&HashMap::from([("role", role)]))
-        .send()
+        .send_logged()
         .expect("api error");
     match resp.status().as_u16() {
         200 => Ok(ApiResult::Success),
@@ -452,18 +823,19 @@ pub fn change_role_admin(
 }
 
 pub fn change_password(
+    api: &ApiConfig,
     credentials: &AuthenticationResponse,
     password: &str,
 ) -> Result<ApiResult, reqwest::Error> {
     let client = reqwest::blocking::Client::new();
     let resp = client
-        .post(format!("{}/v1/user/changePassword", *API_URL))
+        .post(format!("{}/v1/user/changePassword", api.url))
         .header(
             "Authorization",
             String::from("Bearer ") + &credentials.access_token,
         )
         .json(&HashMap::from([("password", password)]))
-        .send()
+        .send_logged()
         .expect("api error");
     match resp.status().as_u16() {
         204 => Ok(ApiResult::Success),
@@ -475,43 +847,161 @@ pub fn change_password(
     }
 }
 
-pub fn change_rfid(
+#[derive(Serialize, Deserialize)]
+pub struct RfidTag {
+    #[serde(rename = "tagId")]
+    pub tag_id: i32,
+    pub label: String,
+    #[serde(rename = "lastUsed")]
+    pub last_used: Option<String>,
+}
+
+fn rfid_tags(
+    credentials: &AuthenticationResponse,
+    url: String,
+) -> Result<ApiResultValue<Vec<RfidTag>>, reqwest::Error> {
+    #[derive(Deserialize)]
+    struct Hax {
+        tags: Vec<RfidTag>,
+    }
+    let client = reqwest::blocking::Client::new();
+    let resp = client
+        .get(url)
+        .header(
+            "Authorization",
+            String::from("Bearer ") + &credentials.access_token,
+        )
+        .send_logged()?;
+    Ok(match resp.status().as_u16() {
+        200 => ApiResultValue::Success(resp.json::<Hax>()?.tags),
+        401 => ApiResultValue::Fail("Not authorized".to_string()),
+        404 => ApiResultValue::Fail("User not found".to_string()),
+        code => ApiResultValue::Fail(format!("http response {code}")),
+    })
+}
+
+fn revoke_rfid(
+    credentials: &AuthenticationResponse,
+    url: String,
+) -> Result<ApiResult, reqwest::Error> {
+    let client = reqwest::blocking::Client::new();
+    let resp = client
+        .delete(url)
+        .header(
+            "Authorization",
+            String::from("Bearer ") + &credentials.access_token,
+        )
+        .send_logged()?;
+    Ok(match resp.status().as_u16() {
+        204 => ApiResult::Success,
+        401 => ApiResult::Fail("Not authorized".to_string()),
+        404 => ApiResult::Fail("RFID tag not found".to_string()),
+        code => ApiResult::Fail(format!("http response {code}")),
+    })
+}
+
+pub fn list_rfid_tags(
+    api: &ApiConfig,
+    credentials: &AuthenticationResponse,
+) -> Result<ApiResultValue<Vec<RfidTag>>, reqwest::Error> {
+    rfid_tags(credentials, format!("{}/v1/user/rfidTags", api.url))
+}
+
+// The backend emails the document to the user's address on record
+pub fn email_data_export(
+    api: &ApiConfig,
+    credentials: &AuthenticationResponse,
+    document: &str,
+) -> Result<ApiResult, reqwest::Error> {
+    let client = reqwest::blocking::Client::new();
+    let resp = client
+        .post(format!("{}/v1/user/dataExport", api.url))
+        .header(
+            "Authorization",
+            String::from("Bearer ") + &credentials.access_token,
+        )
+        .json(&HashMap::from([("document", document)]))
+        .send_logged()?;
+    Ok(match resp.status().as_u16() {
+        200 | 202 | 204 => ApiResult::Success,
+        401 => ApiResult::Fail("Not authorized".to_string()),
+        409 => ApiResult::Fail("Your email address is not verified".to_string()),
+        code => ApiResult::Fail(format!("http response {code}")),
+    })
+}
+
+pub fn add_rfid_tag(
+    api: &ApiConfig,
     credentials: &AuthenticationResponse,
     rfid: &str,
+    label: &str,
 ) -> Result<ApiResult, reqwest::Error> {
     let client = reqwest::blocking::Client::new();
     let resp = client
-        .post(format!("{}/v1/user/changeRfid", *API_URL))
+        .post(format!("{}/v1/user/rfidTags", api.url))
         .header(
             "Authorization",
             String::from("Bearer ") + &credentials.access_token,
         )
-        .json(&HashMap::from([("rfid", rfid)]))
-        .send()
-        .expect("api error");
-    match resp.status().as_u16() {
-        204 => Ok(ApiResult::Success),
-        400 => Ok(ApiResult::Fail(
-            "Missing or invalid fields in request".to_string(),
-        )),
-        401 => Ok(ApiResult::Fail("Not authorized".to_string())),
-        code => Ok(ApiResult::Fail(format!("http response {code}"))),
-    }
+        .json(&HashMap::from([("rfid", rfid), ("label", label)]))
+        .send_logged()?;
+    Ok(match resp.status().as_u16() {
+        201 | 204 => ApiResult::Success,
+        400 => ApiResult::Fail("Missing or invalid fields in request".to_string()),
+        401 => ApiResult::Fail("Not authorized".to_string()),
+        409 => ApiResult::Fail("The tag is already registered".to_string()),
+        code => ApiResult::Fail(format!("http response {code}")),
+    })
+}
+
+pub fn revoke_rfid_tag(
+    api: &ApiConfig,
+    credentials: &AuthenticationResponse,
+    tag_id: i32,
+) -> Result<ApiResult, reqwest::Error> {
+    revoke_rfid(
+        credentials,
+        format!("{}/v1/user/rfidTags/{tag_id}", api.url),
+    )
+}
+
+pub fn list_rfid_tags_admin(
+    api: &ApiConfig,
+    credentials: &AuthenticationResponse,
+    user_id: i32,
+) -> Result<ApiResultValue<Vec<RfidTag>>, reqwest::Error> {
+    rfid_tags(
+        credentials,
+        format!("{}/v1/admin/users/{user_id}/rfidTags", api.url),
+    )
+}
+
+pub fn revoke_rfid_tag_admin(
+    api: &ApiConfig,
+    credentials: &AuthenticationResponse,
+    user_id: i32,
+    tag_id: i32,
+) -> Result<ApiResult, reqwest::Error> {
+    revoke_rfid(
+        credentials,
+        format!("{}/v1/admin/users/{user_id}/rfidTags/{tag_id}", api.url),
+    )
 }
 
 pub fn change_email(
+    api: &ApiConfig,
     credentials: &AuthenticationResponse,
     email: &str,
 ) -> Result<ApiResult, reqwest::Error> {
     let client = reqwest::blocking::Client::new();
     let resp = client
-        .patch(format!("{}/v1/user", *API_URL))
+        .patch(format!("{}/v1/user", api.url))
         .header(
             "Authorization",
             String::from("Bearer ") + &credentials.access_token,
         )
         .json(&HashMap::from([("email", email)]))
-        .send()
+        .send_logged()
         .expect("api error");
     match resp.status().as_u16() {
         200 => Ok(ApiResult::Success),
@@ -524,19 +1014,162 @@ pub fn change_email(
     }
 }
 
+// Registering and changing the email address send a code automatically
+pub fn request_email_verification(
+    api: &ApiConfig,
+    credentials: &AuthenticationResponse,
+) -> Result<ApiResult, reqwest::Error> {
+    let client = reqwest::blocking::Client::new();
+    let resp = client
+        .post(format!("{}/v1/user/email/verification", api.url))
+        .header(
+            "Authorization",
+            String::from("Bearer ") + &credentials.access_token,
+        )
+        .send_logged()?;
+    Ok(match resp.status().as_u16() {
+        200 | 204 => ApiResult::Success,
+        401 => ApiResult::Fail("Not authorized".to_string()),
+        409 => ApiResult::Fail("The email address is already verified".to_string()),
+        429 => ApiResult::Fail("A code was sent recently, try again later".to_string()),
+        code => ApiResult::Fail(format!("http response {code}")),
+    })
+}
+
+pub fn verify_email(
+    api: &ApiConfig,
+    credentials: &AuthenticationResponse,
+    code: &str,
+) -> Result<ApiResult, reqwest::Error> {
+    let client = reqwest::blocking::Client::new();
+    let resp = client
+        .post(format!("{}/v1/user/email/verify", api.url))
+        .header(
+            "Authorization",
+            String::from("Bearer ") + &credentials.access_token,
+        )
+        .json(&HashMap::from([("code", code)]))
+        .send_logged()?;
+    Ok(match resp.status().as_u16() {
+        200 | 204 => ApiResult::Success,
+        400 => ApiResult::Fail("Invalid or expired code".to_string()),
+        401 => ApiResult::Fail("Not authorized".to_string()),
+        code => ApiResult::Fail(format!("http response {code}")),
+    })
+}
+
+// Whether the user has enrolled TOTP. The backend keeps the secret and never
+// returns it after the enrollment.
+pub fn get_totp_enabled(
+    api: &ApiConfig,
+    credentials: &AuthenticationResponse,
+) -> Result<ApiResultValue<bool>, reqwest::Error> {
+    #[derive(Deserialize)]
+    struct Hax {
+        enabled: bool,
+    }
+    let client = reqwest::blocking::Client::new();
+    let resp = client
+        .get(format!("{}/v1/user/totp/status", api.url))
+        .header(
+            "Authorization",
+            String::from("Bearer ") + &credentials.access_token,
+        )
+        .send_logged()?;
+    Ok(match resp.status().as_u16() {
+        200 => ApiResultValue::Success(resp.json::<Hax>()?.enabled),
+        401 => ApiResultValue::Fail("Not authorized".to_string()),
+        code => ApiResultValue::Fail(format!("http response {code}")),
+    })
+}
+
+// The backend checks the first code against the secret before enabling TOTP
+pub fn enroll_totp(
+    api: &ApiConfig,
+    credentials: &AuthenticationResponse,
+    secret: &str,
+    code: &str,
+) -> Result<ApiResult, reqwest::Error> {
+    let client = reqwest::blocking::Client::new();
+    let resp = client
+        .post(format!("{}/v1/user/totp", api.url))
+        .header(
+            "Authorization",
+            String::from("Bearer ") + &credentials.access_token,
+        )
+        .json(&HashMap::from([("secret", secret), ("code", code)]))
+        .send_logged()?;
+    Ok(match resp.status().as_u16() {
+        200 | 201 | 204 => ApiResult::Success,
+        400 => ApiResult::Fail("Invalid code".to_string()),
+        401 => ApiResult::Fail("Not authorized".to_string()),
+        409 => ApiResult::Fail("Two-factor authentication is already enabled".to_string()),
+        code => ApiResult::Fail(format!("http response {code}")),
+    })
+}
+
+// Checked by the backend, which accepts each code only once
+pub fn verify_totp(
+    api: &ApiConfig,
+    credentials: &AuthenticationResponse,
+    code: &str,
+) -> Result<ApiResult, reqwest::Error> {
+    let client = reqwest::blocking::Client::new();
+    let resp = client
+        .post(format!("{}/v1/user/totp/verify", api.url))
+        .header(
+            "Authorization",
+            String::from("Bearer ") + &credentials.access_token,
+        )
+        .json(&HashMap::from([("code", code)]))
+        .send_logged()?;
+    Ok(match resp.status().as_u16() {
+        200 | 204 => ApiResult::Success,
+        400 => ApiResult::Fail("Invalid or already used code".to_string()),
+        401 => ApiResult::Fail("Not authorized".to_string()),
+        404 => ApiResult::Fail("Two-factor authentication is not enabled".to_string()),
+        code => ApiResult::Fail(format!("http response {code}")),
+    })
+}
+
+// Needs a valid code, checked by the backend
+pub fn delete_totp(
+    api: &ApiConfig,
+    credentials: &AuthenticationResponse,
+    code: &str,
+) -> Result<ApiResult, reqwest::Error> {
+    let client = reqwest::blocking::Client::new();
+    let resp = client
+        .delete(format!("{}/v1/user/totp", api.url))
+        .header(
+            "Authorization",
+            String::from("Bearer ") + &credentials.access_token,
+        )
+        .json(&HashMap::from([("code", code)]))
+        .send_logged()?;
+    Ok(match resp.status().as_u16() {
+        204 => ApiResult::Success,
+        400 => ApiResult::Fail("Invalid or already used code".to_string()),
+        401 => ApiResult::Fail("Not authorized".to_string()),
+        404 => ApiResult::Fail("Two-factor authentication is not enabled".to_string()),
+        code => ApiResult::Fail(format!("http response {code}")),
+    })
+}
+
 pub fn change_username(
+    api: &ApiConfig,
     credentials: &AuthenticationResponse,
     username: &str,
 ) -> Result<ApiResult, reqwest::Error> {
     let client = reqwest::blocking::Client::new();
     let resp = client
-        .patch(format!("{}/v1/user", *API_URL))
+        .patch(format!("{}/v1/user", api.url))
         .header(
             "Authorization",
             String::from("Bearer ") + &credentials.access_token,
         )
         .json(&HashMap::from([("username", username)]))
-        .send()
+        .send_logged()
         .expect("api error");
     match resp.status().as_u16() {
         200 => Ok(ApiResult::Success),
@@ -549,18 +1182,19 @@ pub fn change_username(
 }
 
 pub fn generate_temp_password(
+    api: &ApiConfig,
     credentials: &AuthenticationResponse,
     user_id: i32,
 ) -> Result<ApiResult, reqwest::Error> {
     let client = reqwest::blocking::Client::new();
     let resp = client
-        .patch(format!("{}/v1/email/temp_password", *API_URL))
+        .patch(format!("{}/v1/email/temp_password", api.url))
         .header(
             "Authorization",
             String::from("Bearer ") + &credentials.access_token,
         )
         .json(&HashMap::from([("userId", user_id)]))
-        .send()
+        .send_logged()
         .expect("api error");
     match resp.status().as_u16() {
         201 => Ok(ApiResult::Success),
@@ -573,18 +1207,19 @@ pub fn generate_temp_password(
 }
 
 pub fn change_full_name(
+    api: &ApiConfig,
     credentials: &AuthenticationResponse,
     fullname: &str,
 ) -> Result<ApiResult, reqwest::Error> {
     let client = reqwest::blocking::Client::new();
     let resp = client
-        .patch(format!("{}/v1/user", *API_URL))
+        .patch(format!("{}/v1/user", api.url))
         .header(
             "Authorization",
             String::from("Bearer ") + &credentials.access_token,
         )
         .json(&HashMap::from([("fullName", fullname)]))
-        .send()
+        .send_logged()
         .expect("api error");
     match resp.status().as_u16() {
         200 => Ok(ApiResult::Success),
@@ -597,17 +1232,18 @@ pub fn change_full_name(
 }
 
 pub fn return_product(
+    api: &ApiConfig,
     credentials: &AuthenticationResponse,
     barcode: &str,
 ) -> Result<ApiResult, reqwest::Error> {
     let client = reqwest::blocking::Client::new();
     let resp = client
-        .post(format!("{}/v1/products/{barcode}/return", *API_URL))
+        .post(format!("{}/v1/products/{barcode}/return", api.url))
         .header(
             "Authorization",
             String::from("Bearer ") + &credentials.access_token,
         )
-        .send()
+        .send_logged()
         .expect("api error");
 
     match resp.status().as_u16() {
@@ -640,19 +1276,20 @@ pub enum ApiResultPurchaseItem {
 }
 
 pub fn purchase_item(
+    api: &ApiConfig,
     credentials: &AuthenticationResponse,
     barcode: &str,
     count: &i32,
 ) -> Result<ApiResultPurchaseItem, reqwest::Error> {
     let client = reqwest::blocking::Client::new();
     let resp = client
-        .post(format!("{}/v1/products/{barcode}/purchase", *API_URL))
+        .post(format!("{}/v1/products/{barcode}/purchase", api.url))
         .header(
             "Authorization",
             String::from("Bearer ") + &credentials.access_token,
         )
         .json(&HashMap::from([("count", count)]))
-        .send()
+        .send_logged()
         .expect("api error");
 
     match resp.status().as_u16() {
@@ -716,7 +1353,102 @@ pub fn purchase_item(
     }
 }
 
+// A bank transfer deposit waiting for the money to show up on the bank statement
+#[derive(Deserialize, Clone, Debug)]
+pub struct PendingDeposit {
+    #[serde(rename = "depositId")]
+    pub deposit_id: i64,
+    #[serde(rename = "userId")]
+    pub user_id: i32,
+    pub username: String,
+    pub amount: i32,
+    pub reference: String,
+    pub created: String,
+}
+
+pub fn create_pending_deposit(
+    api: &ApiConfig,
+    credentials: &AuthenticationResponse,
+    amount: u32,
+    reference: &str,
+) -> Result<ApiResult, reqwest::Error> {
+    #[derive(Serialize)]
+    struct Body<'a> {
+        amount: u32,
+        reference: &'a str,
+    }
+    let client = reqwest::blocking::Client::new();
+    let resp = client
+        .post(format!("{}/v1/user/pendingDeposits", api.url))
+        .header(
+            "Authorization",
+            String::from("Bearer ") + &credentials.access_token,
+        )
+        .json(&Body { amount, reference })
+        .send_logged()?;
+    Ok(match resp.status().as_u16() {
+        200 | 201 => ApiResult::Success,
+        400 => ApiResult::Fail("Missing or invalid fields in request".to_string()),
+        code => ApiResult::Fail(format!("http response {code}")),
+    })
+}
+
+pub fn get_pending_deposits_admin(
+    api: &ApiConfig,
+    credentials: &AuthenticationResponse,
+) -> Result<ApiResultValue<Vec<PendingDeposit>>, reqwest::Error> {
+    #[derive(Deserialize)]
+    struct Hax {
+        deposits: Vec<PendingDeposit>,
+    }
+    let client = reqwest::blocking::Client::new();
+    let resp = client
+        .get(format!("{}/v1/admin/pendingDeposits", api.url))
+        .header(
+            "Authorization",
+            String::from("Bearer ") + &credentials.access_token,
+        )
+        .send_logged()?;
+    Ok(match resp.status().as_u16() {
+        200 => ApiResultValue::Success(resp.json::<Hax>()?.deposits),
+        401 => ApiResultValue::Fail("Not authorized".to_string()),
+        code => ApiResultValue::Fail(format!("http response {code}")),
+    })
+}
+
+// Confirming credits the amount to the user, rejecting drops the deposit
+pub fn resolve_pending_deposit_admin(
+    api: &ApiConfig,
+    credentials: &AuthenticationResponse,
+    deposit_id: i64,
+    confirm: bool,
+) -> Result<ApiResult, reqwest::Error> {
+    let action = match confirm {
+        true => "confirm",
+        false => "reject",
+    };
+    let client = reqwest::blocking::Client::new();
+    let resp = client
+        .post(format!(
+            "{}/v1/admin/pendingDeposits/{deposit_id}/{action}",
+            api.url
+        ))
+        .header(
+            "Authorization",
+            String::from("Bearer ") + &credentials.access_token,
+        )
+        .send_logged()?;
+    Ok(match resp.status().as_u16() {
+        200 => ApiResult::Success,
+        404 => ApiResult::Fail("Pending deposit not found".to_string()),
+        409 => ApiResult::Fail("Deposit already resolved".to_string()),
+        401 => ApiResult::Fail("Not authorized".to_string()),
+        code => ApiResult::Fail(format!("http response {code}")),
+    })
+}
+
 pub fn deposit(
+    api: &ApiConfig,
     credentials: &AuthenticationResponse,
     amount: &u32,
     deposit_type: &str,
@@ -729,7 +1461,7 @@ pub fn deposit(
     }
     let client = reqwest::blocking::Client::new();
     client
-        .post(format!("{}/v1/user/deposit", *API_URL))
+        .post(format!("{}/v1/user/deposit", api.url))
         .header(
             "Authorization",
             String::from("Bearer ") + &credentials.access_token,
@@ -738,7 +1470,7 @@ pub fn deposit(
             amount: *amount,
             deposit_type: deposit_type.to_string(),
         })
-        .send()?;
+        .send_logged()?;
     Ok(())
 }
 
@@ -771,6 +1503,7 @@ pub struct BoxInfoAdmin {
 }
 
 pub fn get_box_info_admin(
+    api: &ApiConfig,
     barcode: &str,
     credentials: &AuthenticationResponse,
 ) -> Result<Option<BoxInfoAdmin>, reqwest::Error> {
@@ -781,12 +1514,12 @@ pub fn get_box_info_admin(
     }
     let client = reqwest::blocking::Client::new();
     let resp = client
-        .get(format!("{}/v1/admin/boxes/{barcode}", *API_URL))
+        .get(format!("{}/v1/admin/boxes/{barcode}", api.url))
         .header(
             "Authorization",
             String::from("Bearer ") + &credentials.access_token,
         )
-        .send()
+        .send_logged()
         .expect("");
     match resp.status().as_u16() {
         200 => Ok(Some(resp.json::<Hax>().map(|v| v.box_).unwrap())),
@@ -796,6 +1529,7 @@ pub fn get_box_info_admin(
 }
 
 pub fn get_product_info_admin(
+    api: &ApiConfig,
     credentials: &AuthenticationResponse,
     barcode: &str,
 ) -> Result<ApiResultValue<ProductInfoAdmin>, reqwest::Error> {
@@ -805,12 +1539,12 @@ pub fn get_product_info_admin(
     }
     let client = reqwest::blocking::Client::new();
     let resp = client
-        .get(format!("{}/v1/admin/products/{barcode}", *API_URL))
+        .get(format!("{}/v1/admin/products/{barcode}", api.url))
         .header(
             "Authorization",
             String::from("Bearer ") + &credentials.access_token,
         )
-        .send()
+        .send_logged()
         .expect("api error");
     match resp.status().as_u16() {
         200 => Ok(ApiResultValue::Success(
@@ -821,7 +1555,7 @@ pub fn get_product_info_admin(
     }
 }
 
-#[derive(Deserialize)]
+#[derive(Serialize, Deserialize)]
 pub struct ProductInfo {
     pub barcode: String,
     pub name: String,
@@ -831,6 +1565,7 @@ pub struct ProductInfo {
 }
 
 pub fn buy_in_product(
+    api: &ApiConfig,
     barcode: &str,
     buy_price: i32,
     sell_price: i32,
@@ -852,13 +1587,13 @@ pub fn buy_in_product(
     };
     let client = reqwest::blocking::Client::new();
     let resp = client
-        .post(format!("{}/v1/admin/products/{barcode}/buyIn", *API_URL))
+        .post(format!("{}/v1/admin/products/{barcode}/buyIn", api.url))
         .header(
             "Authorization",
             String::from("Bearer ") + &credentials.access_token,
         )
         .json(&hm)
-        .send()
+        .send_logged()
         .unwrap();
     match resp.status().as_u16() {
         200 => ApiResult::Success,
@@ -867,6 +1602,7 @@ pub fn buy_in_product(
 }
 
 pub fn update_box(
+    api: &ApiConfig,
     barcode: &str,
     items_per_box: i32,
     product_barcode: &str,
@@ -885,13 +1621,13 @@ pub fn update_box(
     };
     let client = reqwest::blocking::Client::new();
     let resp = client
-        .patch(format!("{}/v1/admin/boxes/{barcode}", *API_URL))
+        .patch(format!("{}/v1/admin/boxes/{barcode}", api.url))
         .header(
             "Authorization",
             String::from("Bearer ") + &credentials.access_token,
         )
         .json(&hm)
-        .send()
+        .send_logged()
         .unwrap();
     match resp.status().as_u16() {
         200 => ApiResult::Success,
@@ -899,7 +1635,9 @@ pub fn update_box(
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn update_product(
+    api: &ApiConfig,
     barcode: &str,
     name: &str,
     category_id: i32,
@@ -928,13 +1666,13 @@ pub fn update_product(
     };
     let client = reqwest::blocking::Client::new();
     let resp = client
-        .patch(format!("{}/v1/admin/products/{barcode}", *API_URL))
+        .patch(format!("{}/v1/admin/products/{barcode}", api.url))
         .header(
             "Authorization",
             String::from("Bearer ") + &credentials.access_token,
         )
         .json(&hm)
-        .send()?;
+        .send_logged()?;
     match resp.status().as_u16() {
         200 => Ok(()),
         _ => panic!(),
@@ -942,6 +1680,7 @@ pub fn update_product(
 }
 
 pub fn search_boxes(
+    api: &ApiConfig,
     credentials: &AuthenticationResponse,
     query: &str,
 ) -> Result<Vec<BoxInfoAdmin>, reqwest::Error> {
@@ -952,18 +1691,19 @@ pub fn search_boxes(
     let client = reqwest::blocking::Client::new();
     let hm = HashMap::from([("query", &query)]);
     let resp = client
-        .post(format!("{}/v1/admin/boxes/search", *API_URL))
+        .post(format!("{}/v1/admin/boxes/search", api.url))
         .header(
             "Authorization",
             String::from("Bearer ") + &credentials.access_token,
         )
         .json(&hm)
-        .send()
+        .send_logged()
         .expect("api error");
     return resp.json::<Hax>().map(|v| v.boxes);
 }
 
 pub fn search_products(
+    api: &ApiConfig,
     credentials: &AuthenticationResponse,
     query: &str,
 ) -> Result<Vec<ProductInfo>, reqwest::Error> {
@@ -974,18 +1714,19 @@ pub fn search_products(
     let client = reqwest::blocking::Client::new();
     let hm = HashMap::from([("query", &query)]);
     let resp = client
-        .post(format!("{}/v1/products/search", *API_URL))
+        .post(format!("{}/v1/products/search", api.url))
         .header(
             "Authorization",
             String::from("Bearer ") + &credentials.access_token,
         )
         .json(&hm)
-        .send()
+        .send_logged()
         .expect("api error");
     return resp.json::<Hax>().map(|v| v.products);
 }
 
 pub fn get_product_info(
+    api: &ApiConfig,
     credentials: &AuthenticationResponse,
     barcode: &str,
 ) -> Option<ProductInfo> {
@@ -995,12 +1736,12 @@ pub fn get_product_info(
     }
     let client = reqwest::blocking::Client::new();
     let resp = client
-        .get(format!("{}/v1/products/{barcode}", *API_URL))
+        .get(format!("{}/v1/products/{barcode}", api.url))
         .header(
             "Authorization",
             String::from("Bearer ") + &credentials.access_token,
         )
-        .send()
+        .send_logged()
         .expect("api error");
     match resp.status().as_u16() {
         200 => return Some(resp.json::<Hax>().map(|v| v.product).unwrap()),
@@ -1011,21 +1752,31 @@ pub fn get_product_info(
     }
 }
 
-pub fn user_exists(username: &str) -> Result<bool, reqwest::Error> {
-    #[derive(Deserialize)]
-    struct Hax {
-        exists: bool,
+// Asks the backend to email the owner of the username about a login lockout
+pub fn notify_failed_logins(
+    api: &ApiConfig,
+    username: &str,
+    attempts: u32,
+) -> Result<ApiResult, reqwest::Error> {
+    #[derive(Serialize)]
+    struct Body<'a> {
+        username: &'a str,
+        attempts: u32,
     }
     let client = reqwest::blocking::Client::new();
     let resp = client
-        .post(format!("{}/v1/user/user_exists", *API_URL))
-        .json(&HashMap::from([("username", &username)]))
-        .send()
-        .expect("api error");
-    Ok(resp.json::<Hax>().unwrap().exists)
+        .post(format!("{}/v1/authenticate/failedAttempts", api.url))
+        .header("RV-Terminal-Secret", api.terminal_secret.as_str())
+        .json(&Body { username, attempts })
+        .send_logged()?;
+    Ok(match resp.status().as_u16() {
+        200 | 202 | 204 => ApiResult::Success,
+        code => ApiResult::Fail(format!("http response {code}")),
+    })
 }
 
 pub fn register(
+    api: &ApiConfig,
     username: &str,
     password: &str,
     full_name: &str,
@@ -1038,15 +1789,15 @@ pub fn register(
     }
     let client = reqwest::blocking::Client::new();
     let resp = client
-        .post(format!("{}/v1/register", *API_URL))
-        .header("RV-Terminal-Secret", RV_TERMINAL_SECRET.as_str())
+        .post(format!("{}/v1/register", api.url))
+        .header("RV-Terminal-Secret", api.terminal_secret.as_str())
         .json(&HashMap::from([
             ("username", &username),
             ("password", &password),
             ("email", &email),
             ("fullName", &full_name),
         ]))
-        .send()
+        .send_logged()
         .expect("api error");
     //Ok()
     match resp.status().as_u16() {
@@ -1057,26 +1808,33 @@ pub fn register(
 }
 
 //credentials: &AuthenticationResponse,
-pub fn set_margin(margin: f32, credentials: &AuthenticationResponse) -> Result<(), reqwest::Error> {
+pub fn set_margin(
+    api: &ApiConfig,
+    margin: f32,
+    credentials: &AuthenticationResponse,
+) -> Result<(), reqwest::Error> {
     let client = reqwest::blocking::Client::new();
     let resp = client
         .patch(format!(
             "{}/v1/admin/preferences/globalDefaultMargin",
-            *API_URL
+            api.url
         ))
         .header(
             "Authorization",
             String::from("Bearer ") + &credentials.access_token,
         )
         .json(&HashMap::from([("value", margin)]))
-        .send()?;
+        .send_logged()?;
     if resp.status().as_u16() != 200 {
         panic!();
     }
     Ok(())
 }
 
-pub fn get_margin(credentials: &AuthenticationResponse) -> Result<f32, reqwest::Error> {
+pub fn get_margin(
+    api: &ApiConfig,
+    credentials: &AuthenticationResponse,
+) -> Result<f32, reqwest::Error> {
     #[derive(Deserialize)]
     struct Preference {
         key: String,
@@ -1090,17 +1848,18 @@ pub fn get_margin(credentials: &AuthenticationResponse) -> Result<f32, reqwest::
     let resp = client
         .get(format!(
             "{}/v1/admin/preferences/globalDefaultMargin",
-            *API_URL
+            api.url
         ))
         .header(
             "Authorization",
             String::from("Bearer ") + &credentials.access_token,
         )
-        .send()?;
+        .send_logged()?;
     Ok(resp.json::<Hax>().unwrap().preference.value)
 }
 
 pub fn get_categories(
+    api: &ApiConfig,
     credentials: &AuthenticationResponse,
 ) -> Result<Vec<ProductCategory>, reqwest::Error> {
     #[derive(Deserialize)]
@@ -1109,11 +1868,11 @@ pub fn get_categories(
     }
     let client = reqwest::blocking::Client::new();
     let resp = client
-        .get(format!("{}/v1/categories", *API_URL))
+        .get(format!("{}/v1/categories", api.url))
         .header(
             "Authorization",
             String::from("Bearer ") + &credentials.access_token,
         )
-        .send()?;
+        .send_logged()?;
     Ok(resp.json::<Hax>().unwrap().categories)
 }
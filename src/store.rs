@@ -0,0 +1,54 @@
+use serde::{de::DeserializeOwned, Serialize};
+use std::{fs, io, path::Path};
+
+// Local JSON files for data the backend does not keep, e.g. <dir>/batches.json
+
+// A missing file is treated as empty. A file that cannot be read or parsed is an
+// error, so that a later save never replaces it with an empty store.
+pub fn load<T: DeserializeOwned + Default>(dir: &str, name: &str) -> io::Result<T> {
+    let path = Path::new(dir).join(name);
+    let content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(T::default()),
+        Err(e) => {
+            log::error!("event=store_load file={:?} error={:?}", name, e.to_string());
+            return Err(e);
+        }
+    };
+    serde_json::from_str(&content).map_err(|e| {
+        log::error!("event=store_load file={:?} error={:?}", name, e.to_string());
+        io::Error::new(io::ErrorKind::InvalidData, e)
+    })
+}
+
+// Writes into a temporary file first so that a crash never leaves a truncated file behind
+pub fn save<T: Serialize>(dir: &str, name: &str, value: &T) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+    let path = Path::new(dir).join(name);
+    let tmp_path = Path::new(dir).join(format!("{name}.tmp"));
+    fs::write(&tmp_path, serde_json::to_string_pretty(value)?)?;
+    fs::rename(tmp_path, path).inspect_err(|e| {
+        log::error!("event=store_save file={:?} error={:?}", name, e.to_string());
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    #[test]
+    fn corrupt_store_is_not_replaced() {
+        let dir = std::env::temp_dir().join(format!("rvterminal-store-{}", std::process::id()));
+        let dir = dir.to_str().unwrap();
+        let missing: BTreeMap<String, i32> = load(dir, "missing.json").unwrap();
+        assert!(missing.is_empty());
+
+        save(dir, "store.json", &BTreeMap::from([("a", 1)])).unwrap();
+        let stored: BTreeMap<String, i32> = load(dir, "store.json").unwrap();
+        assert_eq!(stored["a"], 1);
+
+        fs::write(Path::new(dir).join("store.json"), "{\"a\": ").unwrap();
+        assert!(load::<BTreeMap<String, i32>>(dir, "store.json").is_err());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
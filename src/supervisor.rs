@@ -0,0 +1,98 @@
+use crate::{loops, utils, TerminalIO};
+
+use crossterm::{
+    cursor,
+    event::{DisableBracketedPaste, EnableBracketedPaste},
+    execute,
+    style::ResetColor,
+    terminal::{
+        self, disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
+    },
+};
+use std::{
+    collections::VecDeque,
+    io,
+    panic::{self, AssertUnwindSafe},
+    time::{Duration, Instant},
+};
+
+pub fn enter_terminal(terminal_io: &mut TerminalIO) -> io::Result<()> {
+    enable_raw_mode()?;
+    execute!(
+        terminal_io.writer,
+        EnterAlternateScreen,
+        EnableBracketedPaste,
+        cursor::EnableBlinking,
+        cursor::MoveTo(0, 0)
+    )
+}
+
+pub fn restore_terminal(terminal_io: &mut TerminalIO) {
+    let _ = execute!(
+        terminal_io.writer,
+        ResetColor,
+        terminal::Clear(terminal::ClearType::All),
+        cursor::Show,
+        DisableBracketedPaste,
+        LeaveAlternateScreen
+    );
+    let _ = disable_raw_mode();
+}
+
+// Panics are only logged, the default hook would print over the TUI
+pub fn install_panic_hook() {
+    panic::set_hook(Box::new(|info| {
+        let message = match info.payload().downcast_ref::<&str>() {
+            Some(s) => s.to_string(),
+            None => match info.payload().downcast_ref::<String>() {
+                Some(s) => s.clone(),
+                None => "unknown panic payload".to_string(),
+            },
+        };
+        log::error!(
+            "event=panic thread={:?} location={:?} message={:?}",
+            std::thread::current().name().unwrap_or("unnamed"),
+            info.location().map(|l| l.to_string()).unwrap_or_default(),
+            message
+        );
+    }));
+}
+
+// Runs main_loop, restarting it at the login screen after a panic.
+// Exit the process only when the sessions keep crashing.
+pub fn run(terminal_io: &mut TerminalIO) -> io::Result<()> {
+    let max_crashes = terminal_io.config.supervisor.max_crashes;
+    let crash_window = Duration::from_secs(terminal_io.config.supervisor.crash_window_secs);
+    let mut crashes: VecDeque<Instant> = VecDeque::new();
+    loop {
+        match panic::catch_unwind(AssertUnwindSafe(|| loops::main_loop(terminal_io))) {
+            Ok(result) => {
+                restore_terminal(terminal_io);
+                return result;
+            }
+            Err(_) => {
+                // The session and its credentials were dropped while unwinding
+                restore_terminal(terminal_io);
+                utils::set_big_font(&terminal_io.config.font);
+                while terminal_io.recv.try_recv().is_ok() {
+                    // Discard input meant for the crashed session
+                }
+
+                crashes.push_back(Instant::now());
+                while crashes.front().is_some_and(|t| t.elapsed() > crash_window) {
+                    crashes.pop_front();
+                }
+                if crashes.len() >= max_crashes {
+                    log::error!("event=supervisor_exit crashes={}", crashes.len());
+                    return Err(io::Error::other(format!(
+                        "main loop crashed {} times in {} minutes",
+                        crashes.len(),
+                        crash_window.as_secs() / 60
+                    )));
+                }
+                log::warn!("event=session_restart crashes={}", crashes.len());
+                enter_terminal(terminal_io)?;
+            }
+        }
+    }
+}
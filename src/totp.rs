@@ -0,0 +1,144 @@
+use hmac::{Hmac, Mac};
+use sha1::Sha1;
+
+const BASE32_ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+const SECRET_LEN: usize = 20;
+const STEP_SECS: u64 = 30;
+const DIGITS: u32 = 6;
+// Codes of the previous and the next time step are accepted too
+const WINDOW: u64 = 1;
+
+// Generated on enrollment and handed to the backend, which checks the codes after that
+pub struct TotpSettings {
+    // Base32 as shown to authenticator apps
+    pub secret: String,
+    // A code is accepted only once
+    last_step: u64,
+}
+
+pub fn base32_encode(data: &[u8]) -> String {
+    let mut out = String::new();
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+    for &byte in data {
+        buffer = (buffer << 8) | byte as u32;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            out.push(BASE32_ALPHABET[((buffer >> bits) & 31) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        out.push(BASE32_ALPHABET[((buffer << (5 - bits)) & 31) as usize] as char);
+    }
+    out
+}
+
+// Case insensitive, padding and spaces are ignored
+pub fn base32_decode(input: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::new();
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+    for c in input.chars().filter(|&c| c != '=' && c != ' ') {
+        let value = BASE32_ALPHABET
+            .iter()
+            .position(|&a| a as char == c.to_ascii_uppercase())?;
+        buffer = (buffer << 5) | value as u32;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((buffer >> bits) as u8);
+        }
+    }
+    Some(out)
+}
+
+// RFC 4226
+fn hotp(secret: &[u8], counter: u64, digits: u32) -> u32 {
+    let mut mac = Hmac::<Sha1>::new_from_slice(secret).expect("HMAC accepts any key length");
+    mac.update(&counter.to_be_bytes());
+    let hash = mac.finalize().into_bytes();
+    let offset = (hash[hash.len() - 1] & 0x0f) as usize;
+    let code = u32::from_be_bytes([
+        hash[offset] & 0x7f,
+        hash[offset + 1],
+        hash[offset + 2],
+        hash[offset + 3],
+    ]);
+    code % 10u32.pow(digits)
+}
+
+// RFC 6238 with SHA-1 and 30 second steps
+fn totp(secret: &[u8], unix_time: u64, digits: u32) -> u32 {
+    hotp(secret, unix_time / STEP_SECS, digits)
+}
+
+impl TotpSettings {
+    pub fn generate() -> Self {
+        let mut secret = [0u8; SECRET_LEN];
+        getrandom::fill(&mut secret).expect("no random source for the TOTP secret");
+        Self {
+            secret: base32_encode(&secret),
+            last_step: 0,
+        }
+    }
+
+    // For the QR code read by authenticator apps
+    pub fn uri(&self, account: &str) -> String {
+        format!(
+            "otpauth://totp/RV:{account}?secret={}&issuer=RV&digits={DIGITS}&period={STEP_SECS}",
+            self.secret
+        )
+    }
+
+    pub fn verify(&mut self, code: &str, unix_time: u64) -> bool {
+        let code = code.trim();
+        if code.len() != DIGITS as usize || !code.chars().all(|c| c.is_ascii_digit()) {
+            return false;
+        }
+        let Some(secret) = base32_decode(&self.secret) else {
+            return false;
+        };
+        let matching = (0..=2 * WINDOW)
+            .filter_map(|i| (unix_time + i * STEP_SECS).checked_sub(WINDOW * STEP_SECS))
+            .filter(|&time| time / STEP_SECS > self.last_step)
+            .find(|&time| format!("{:06}", totp(&secret, time, DIGITS)) == code);
+        match matching {
+            Some(time) => {
+                self.last_step = time / STEP_SECS;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn totp_rfc6238_vectors_work() {
+        let secret = b"12345678901234567890";
+        assert_eq!(totp(secret, 59, 8), 94287082);
+        assert_eq!(totp(secret, 1111111109, 8), 7081804);
+        assert_eq!(totp(secret, 1111111111, 8), 14050471);
+        assert_eq!(totp(secret, 1234567890, 8), 89005924);
+        assert_eq!(totp(secret, 2000000000, 8), 69279037);
+        assert_eq!(totp(secret, 20000000000, 8), 65353130);
+
+        assert_eq!(base32_encode(secret), "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ");
+        assert_eq!(base32_decode("gezdgnbvgy3tqojq").unwrap(), b"1234567890");
+        assert_eq!(base32_decode("GE======").unwrap(), b"1");
+        assert!(base32_decode("1").is_none());
+
+        let mut settings = TotpSettings {
+            secret: base32_encode(secret),
+            last_step: 0,
+        };
+        // 6 digit code of the previous step is accepted once
+        assert!(!settings.verify("000000", 1111111111));
+        assert!(settings.verify(&format!("{:06}", totp(secret, 1111111090, 6)), 1111111111));
+        assert!(!settings.verify(&format!("{:06}", totp(secret, 1111111090, 6)), 1111111111));
+        assert!(!settings.verify(&format!("{:06}", totp(secret, 1111111019, 6)), 1111111111));
+    }
+}
@@ -1,20 +1,28 @@
-use crate::{input::InputEvent, TerminalIO, INPUT_TIMEOUT_SHORT, NO_FONT_CHANGE};
+use crate::{
+    barcode::{self, Barcode, BarcodeError},
+    config::FontConfig,
+    input::InputEvent,
+    line_editor::{Action, Echo, LineEditor},
+    password_policy::{self, Strength},
+    rv_api, TerminalIO,
+};
 
 use super::input;
 use crossterm::{
     cursor::{self, RestorePosition, SavePosition},
     event::{Event, KeyCode, KeyEvent},
     execute,
-    style::{Print, PrintStyledContent, Stylize},
+    style::{Print, PrintStyledContent, StyledContent, Stylize},
     terminal,
 };
+use qrcode::{render::unicode, QrCode};
+use regex::Regex;
 
 pub enum TimeoutResult<T> {
     RESULT(T),
     TIMEOUT,
 }
 
-use core::str;
 use std::{
     fs,
     process::{Command, ExitStatus},
@@ -38,6 +46,45 @@ pub fn format_money(cents: &i32) -> String {
         cents.abs() % 100
     )
 }
+
+// Euros with a '.' or ',' decimal separator, e.g. '12,50', '-3.2' or '1 000', to cents
+pub fn parse_signed_money(input: &str) -> Option<i32> {
+    let input: String = input
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '+')
+        .collect();
+    let (negative, input) = match input.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, input.as_str()),
+    };
+    let (euros, cents) = match input.split_once([',', '.']) {
+        Some((euros, cents)) if (1..=2).contains(&cents.len()) => (euros, format!("{cents:0<2}")),
+        Some(_) => return None,
+        None => (input, "00".to_string()),
+    };
+    if euros.is_empty()
+        || !euros
+            .chars()
+            .chain(cents.chars())
+            .all(|c| c.is_ascii_digit())
+    {
+        return None;
+    }
+    let amount = euros.parse::<i32>().ok()?.checked_mul(100)? + cents.parse::<i32>().ok()?;
+    Some(if negative { -amount } else { amount })
+}
+
+// Euros with optional two decimals, e.g. '1', '0.10' or '14,42', to cents
+pub fn parse_money(input: &str) -> Option<u32> {
+    if !Regex::new("^[0-9]+((\\.|,)[0-9][0-9])?$")
+        .unwrap()
+        .is_match(input)
+    {
+        return None;
+    }
+    parse_signed_money(input).and_then(|cents| u32::try_from(cents).ok())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -55,48 +102,45 @@ mod tests {
     }
 
     #[test]
-    fn is_barcode_works() {
-        assert!(is_barcode("38588901797050"));
-        assert!(is_barcode("3858890179705"));
-        assert!(is_barcode("4901234567894"));
-        assert!(is_barcode("700941359952"));
-        assert!(is_barcode("80111351"));
-        assert!(is_barcode("01234565"));
-        assert!(!is_barcode("user"));
+    fn parse_money_works() {
+        assert_eq!(parse_money("1"), Some(100));
+        assert_eq!(parse_money("0.10"), Some(10));
+        assert_eq!(parse_money("14,42"), Some(1442));
+        assert_eq!(parse_money("1.5"), None);
+        assert_eq!(parse_money("-1"), None);
     }
-}
 
-pub fn set_small_font() {
-    if !*NO_FONT_CHANGE {
-        let output = Command::new("setfont")
-            .arg("Uni2-VGA16.psf.gz")
-            .arg("-C")
-            .arg("/dev/tty1")
-            .output()
-            .unwrap();
-        if !ExitStatus::success(&output.status) {
-            eprintln!(
-                "Setfont exit code {}\n stderr: {}",
-                output.status,
-                &str::from_utf8(&output.stderr).unwrap()
-            );
-        }
+    #[test]
+    fn parse_signed_money_works() {
+        assert_eq!(parse_signed_money("12,5"), Some(1250));
+        assert_eq!(parse_signed_money("-1 000.00"), Some(-100000));
+        assert_eq!(parse_signed_money("+20"), Some(2000));
+        assert_eq!(parse_signed_money("1.234"), None);
     }
 }
 
-pub fn set_big_font() {
-    if !*NO_FONT_CHANGE {
+pub fn set_small_font(config: &FontConfig) {
+    set_font(config, &config.small);
+}
+
+pub fn set_big_font(config: &FontConfig) {
+    set_font(config, &config.big);
+}
+
+fn set_font(config: &FontConfig, font: &str) {
+    if config.change {
         let output = Command::new("setfont")
-            .arg("Uni2-VGA28x16.psf.gz")
+            .arg(font)
             .arg("-C")
-            .arg("/dev/tty1")
+            .arg(&config.console)
             .output()
             .unwrap();
         if !ExitStatus::success(&output.status) {
-            eprintln!(
-                "Setfont exit code {}\n stderr: {}",
-                output.status,
-                &str::from_utf8(&output.stderr).unwrap()
+            log::warn!(
+                "event=setfont font={:?} status={:?} stderr={:?}",
+                font,
+                output.status.code(),
+                String::from_utf8_lossy(&output.stderr)
             );
         }
     }
@@ -150,15 +194,42 @@ pub fn print_rv_logo(terminal_io: &mut TerminalIO) {
         cursor::MoveTo(0, 3),
         PrintStyledContent(RV_LOGO.to_string().yellow()),
         RestorePosition
-    ).unwrap();
+    )
+    .unwrap();
+}
+
+pub fn print_qr(terminal_io: &mut TerminalIO, data: &str) {
+    let code = match QrCode::new(data.as_bytes()) {
+        Ok(code) => code,
+        Err(_) => {
+            print_error_line(terminal_io, "Data does not fit in a QR code");
+            return;
+        }
+    };
+    // Colors are inverted for the dark terminal background
+    let image = code
+        .render::<unicode::Dense1x2>()
+        .dark_color(unicode::Dense1x2::Light)
+        .light_color(unicode::Dense1x2::Dark)
+        .build();
+    printline(terminal_io, &image.replace("\n", "\r\n"));
 }
 
 pub fn readpasswd(terminal_io: &mut TerminalIO, timeout: Duration) -> TimeoutResult<String> {
-    readline_internal(false, timeout, terminal_io).unwrap()
+    readline_internal(Echo::Masked, None, timeout, terminal_io).unwrap()
+}
+
+// For choosing a password, shows the strength of the password while typing
+pub fn read_new_password(
+    terminal_io: &mut TerminalIO,
+    timeout: Duration,
+    username: &str,
+) -> TimeoutResult<String> {
+    readline_internal(Echo::Masked, Some(username), timeout, terminal_io).unwrap()
 }
 
 pub fn readline(terminal_io: &mut TerminalIO, timeout: Duration) -> TimeoutResult<String> {
-    readline_internal(true, timeout, terminal_io).unwrap()
+    readline_internal(Echo::Plain, None, timeout, terminal_io).unwrap()
 }
 
 pub enum ConfirmResult {
@@ -168,7 +239,10 @@ pub enum ConfirmResult {
 }
 pub fn confirm(terminal_io: &mut TerminalIO) -> Result<ConfirmResult, std::io::Error> {
     loop {
-        match terminal_io.recv.recv_timeout(INPUT_TIMEOUT_SHORT) {
+        match terminal_io
+            .recv
+            .recv_timeout(terminal_io.config.input.timeout_short())
+        {
             Ok(InputEvent::Terminal(Event::Key(KeyEvent {
                 code: KeyCode::Char(c),
                 ..
@@ -190,7 +264,10 @@ pub fn confirm_with_default(
     default: ConfirmResult,
 ) -> Result<ConfirmResult, std::io::Error> {
     loop {
-        match terminal_io.recv.recv_timeout(INPUT_TIMEOUT_SHORT) {
+        match terminal_io
+            .recv
+            .recv_timeout(terminal_io.config.input.timeout_short())
+        {
             Ok(InputEvent::Terminal(Event::Key(KeyEvent {
                 code: KeyCode::Char(c),
                 ..
@@ -231,7 +308,10 @@ pub fn clear_line(terminal_io: &mut TerminalIO) {
 pub fn confirm_enter_to_continue(terminal_io: &mut TerminalIO) -> ConfirmResult {
     printline(terminal_io, "Press ENTER to continue");
     loop {
-        match terminal_io.recv.recv_timeout(INPUT_TIMEOUT_SHORT) {
+        match terminal_io
+            .recv
+            .recv_timeout(terminal_io.config.input.timeout_short())
+        {
             Ok(InputEvent::Terminal(Event::Key(KeyEvent {
                 code: KeyCode::Enter,
                 ..
@@ -243,132 +323,154 @@ pub fn confirm_enter_to_continue(terminal_io: &mut TerminalIO) -> ConfirmResult
     }
 }
 
+fn strength_meter(password: &str, username: &str) -> Option<StyledContent<String>> {
+    if password.is_empty() {
+        return None;
+    }
+    let strength = password_policy::strength(password, username);
+    let label = format!("  strength: {}", strength.label());
+    Some(match strength {
+        Strength::Rejected => label.red(),
+        Strength::Weak => label.dark_yellow(),
+        Strength::Fair => label.yellow(),
+        Strength::Strong => label.green(),
+    })
+}
+
+// Shows a strength meter after the text when `meter` has the username
 fn readline_internal(
-    echo: bool,
+    echo: Echo,
+    meter: Option<&str>,
     timeout: Duration,
     terminal_io: &mut TerminalIO,
 ) -> Result<TimeoutResult<String>, std::io::Error> {
-    let mut ret = String::new();
+    let mut editor = LineEditor::new(echo);
     loop {
-        match terminal_io.recv.recv_timeout(timeout) {
+        let action = match terminal_io.recv.recv_timeout(timeout) {
             Err(RecvTimeoutError::Timeout) => {
                 printline(terminal_io, "");
                 return Ok(TimeoutResult::TIMEOUT);
             }
-            Ok(input::InputEvent::Terminal(Event::Key(ev))) => match ev.code {
-                KeyCode::Char(c) => {
-                    ret.push(c);
-                    if echo {
-                        execute!(terminal_io.writer, Print(c))?;
-                    }
-                }
-                KeyCode::Backspace => {
-                    if !ret.is_empty() {
-                        if echo {
-                            execute!(
-                                terminal_io.writer,
-                                cursor::MoveLeft(1),
-                                Print(" "),
-                                cursor::MoveLeft(1)
-                            )?;
-                        }
-                        ret.pop();
-                    }
-                }
-                KeyCode::Enter => {
-                    break;
-                }
-                _ => (),
-            },
-            _ => (),
+            Ok(input::InputEvent::Terminal(Event::Key(ev))) => editor.handle_key(&ev),
+            Ok(input::InputEvent::Terminal(Event::Paste(text))) => editor.insert_str(&text),
+            _ => Action::Ignored,
+        };
+        match action {
+            Action::Submit => break,
+            Action::Edited => {
+                let suffix = meter.and_then(|username| strength_meter(&editor.text(), username));
+                editor.render(&mut terminal_io.writer, suffix)?;
+            }
+            Action::Ignored => (),
         }
     }
     printline(terminal_io, "");
-    Ok(TimeoutResult::RESULT(ret.trim().to_string()))
+    Ok(TimeoutResult::RESULT(editor.text().trim().to_string()))
 }
 
 pub fn readline_barcode(terminal_io: &mut TerminalIO, timeout: Duration) -> TimeoutResult<String> {
-    let mut barcode = String::new();
+    match readline_barcode_internal(terminal_io, timeout) {
+        TimeoutResult::RESULT((barcode, _)) => TimeoutResult::RESULT(barcode),
+        TimeoutResult::TIMEOUT => TimeoutResult::TIMEOUT,
+    }
+}
+
+// Reads a barcode for an API lookup, None if it was rejected
+pub fn read_barcode(
+    terminal_io: &mut TerminalIO,
+    timeout: Duration,
+) -> TimeoutResult<Option<Barcode>> {
+    match readline_barcode_internal(terminal_io, timeout) {
+        TimeoutResult::RESULT((barcode, scanned)) => {
+            TimeoutResult::RESULT(check_barcode(terminal_io, &barcode, scanned))
+        }
+        TimeoutResult::TIMEOUT => TimeoutResult::TIMEOUT,
+    }
+}
+
+// Scanned barcodes with a bad check digit are rejected, typed ones only warned about
+pub fn check_barcode(terminal_io: &mut TerminalIO, input: &str, scanned: bool) -> Option<Barcode> {
+    match barcode::parse(input) {
+        Ok(barcode) => Some(barcode),
+        Err(BarcodeError::CheckDigit(code)) if !scanned => {
+            execute!(
+                terminal_io.writer,
+                PrintStyledContent("WARNING".yellow()),
+                Print(format!(
+                    ": check digit of {code} is invalid, check for typos\r\n"
+                ))
+            )
+            .unwrap();
+            Some(Barcode {
+                scanned: code.clone(),
+                code,
+                batch: None,
+                best_before: None,
+            })
+        }
+        Err(err) => {
+            log::warn!(
+                "event=barcode_rejected scanned={scanned} error={:?}",
+                err.to_string()
+            );
+            print_error_line(terminal_io, &format!("Invalid barcode: {err}"));
+            None
+        }
+    }
+}
+
+// Uses the first form of the barcode known to the backend as its code, boxes are
+// looked up too for admins. Unknown barcodes keep the normalized code.
+pub fn resolve_barcode(
+    terminal_io: &TerminalIO,
+    credentials: &rv_api::AuthenticationResponse,
+    mut barcode: Barcode,
+    boxes: bool,
+) -> Barcode {
+    let api = &terminal_io.config.api;
+    let known = barcode.lookup_codes().into_iter().find(|code| {
+        rv_api::get_product_info(api, credentials, code).is_some()
+            || (boxes
+                && matches!(
+                    rv_api::get_box_info_admin(api, code, credentials),
+                    Ok(Some(_))
+                ))
+    });
+    if let Some(code) = known {
+        barcode.code = code;
+    }
+    barcode
+}
+
+// Returns the input and whether it came from a scanner
+fn readline_barcode_internal(
+    terminal_io: &mut TerminalIO,
+    timeout: Duration,
+) -> TimeoutResult<(String, bool)> {
+    let mut editor = LineEditor::new(Echo::Plain)
+        .accepting(|c| c.is_ascii_alphanumeric() || c == '(' || c == ')');
     loop {
-        match terminal_io.recv.recv_timeout(timeout) {
+        let action = match terminal_io.recv.recv_timeout(timeout) {
             Err(RecvTimeoutError::Timeout) => {
                 printline(terminal_io, "");
                 return TimeoutResult::TIMEOUT;
             }
-            Ok(input::InputEvent::Terminal(Event::Key(ev))) => match ev.code {
-                KeyCode::Char(c) => {
-                    if c.is_ascii_digit() {
-                        barcode.push(c);
-                        execute!(terminal_io.writer, Print(c)).unwrap();
-                    }
-                }
-                KeyCode::Backspace => {
-                    if !barcode.is_empty() {
-                        execute!(
-                            terminal_io.writer,
-                            cursor::MoveLeft(1),
-                            Print(" "),
-                            cursor::MoveLeft(1)
-                        )
-                        .unwrap();
-                        barcode.pop();
-                    }
-                }
-                KeyCode::Enter => {
-                    break;
-                }
-                _ => (),
-            },
+            Ok(input::InputEvent::Terminal(Event::Key(ev))) => editor.handle_key(&ev),
+            Ok(input::InputEvent::Terminal(Event::Paste(text))) => editor.insert_str(&text),
             Ok(input::InputEvent::Barcode(input)) => {
-                barcode = input;
-                break;
+                printline(terminal_io, "");
+                return TimeoutResult::RESULT((input.trim().to_string(), true));
             }
-            _ => (),
+            _ => Action::Ignored,
+        };
+        match action {
+            Action::Submit => break,
+            Action::Edited => editor.render(&mut terminal_io.writer, None).unwrap(),
+            Action::Ignored => (),
         }
     }
     printline(terminal_io, "");
-    return TimeoutResult::RESULT(barcode.trim().to_string());
-}
-
-pub fn is_barcode(input: &str) -> bool {
-    if !input.chars().all(|chr| chr.is_ascii_digit()) {
-        return false;
-    }
-
-    let len = input.len();
-    let size_even = len % 2 == 0;
-
-    if len != 8 && len != 12 && len != 13 && len != 14 {
-        return false;
-    }
-
-    let code: Vec<u32> = input
-        .chars()
-        .map(|char| char.to_digit(10).unwrap())
-        .collect();
-
-    let sum: u32 = code[..len - 1]
-        .iter()
-        .enumerate()
-        .map(|(i, &digit)| {
-            if i % 2 == 0 {
-                if size_even {
-                    3 * digit
-                } else {
-                    digit
-                }
-            } else {
-                if size_even {
-                    digit
-                } else {
-                    3 * digit
-                }
-            }
-        })
-        .sum();
-    let check_sum = (10 - (sum % 10)) % 10;
-    println!("{}", check_sum);
-    return check_sum == *code.last().unwrap();
+    TimeoutResult::RESULT((editor.text().trim().to_string(), false))
 }
 
 pub fn calculator_input(input: &str) -> Option<i32> {
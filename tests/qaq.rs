@@ -1,6 +1,6 @@
 use crossterm::{self, event};
 use regex::Regex;
-use rvterminal::{self, main_loop, TerminalIO, TerminalWriter};
+use rvterminal::{self, config::Config, main_loop, TerminalIO, TerminalWriter};
 use std::sync::mpsc::Sender;
 
 fn send_string_to_channel(str: &str, sender: &Sender<rvterminal::input::InputEvent>) {
@@ -27,6 +27,10 @@ fn can_quit_main_loop() {
     let mut terminal_io = TerminalIO {
         recv: receiver,
         writer: TerminalWriter::new(true),
+        config: Config {
+            development: true,
+            ..Config::default()
+        },
     };
     send_string_to_channel("quit", &sender);
     send_enter_to_channel(&sender);
@@ -40,6 +44,10 @@ fn login_test() {
     let mut terminal_io = TerminalIO {
         recv: receiver,
         writer: TerminalWriter::new(true),
+        config: Config {
+            development: true,
+            ..Config::default()
+        },
     };
     send_string_to_channel("test", &sender);
     send_enter_to_channel(&sender);